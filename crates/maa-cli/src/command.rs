@@ -79,7 +79,16 @@ pub(crate) enum Command {
     ///
     /// This command will print the path used by maa-cli.
     /// Some of these paths are used by maa-core and maa-run.
-    Dir { dir: Dir },
+    ///
+    /// With `--size`, print the size of the given directory, or the sizes of data, cache, log,
+    /// resource and copilot directories if no directory is given.
+    Dir {
+        #[arg(required_unless_present = "size")]
+        dir: Option<Dir>,
+        /// Print the size of directories in bytes
+        #[arg(long)]
+        size: bool,
+    },
     /// Print version of given component
     ///
     /// This command will print the version of given component.
@@ -160,6 +169,16 @@ pub(crate) enum Command {
         #[command(flatten)]
         common: run::CommonArgs,
     },
+    /// Run a Custom or SingleStep task with params from a JSON file
+    ///
+    /// The JSON file must contain an object, which will be passed to MaaCore as the params
+    /// of the task without any processing.
+    Raw {
+        #[command(flatten)]
+        params: run::preset::RawTaskParams,
+        #[command(flatten)]
+        common: run::CommonArgs,
+    },
     /// Convert file format between TOML, YAML and JSON
     ///
     /// This command will convert a file from TOML, YAML or JSON format to another format.
@@ -436,34 +455,58 @@ mod test {
     #[test]
     fn dir() {
         assert_matches!(parse_from(["maa", "dir", "data"]).command, Command::Dir {
-            dir: Dir::Data
+            dir: Some(Dir::Data),
+            ..
         });
         assert_matches!(
             parse_from(["maa", "dir", "library"]).command,
-            Command::Dir { dir: Dir::Library }
+            Command::Dir {
+                dir: Some(Dir::Library),
+                ..
+            }
         );
         assert_matches!(parse_from(["maa", "dir", "lib"]).command, Command::Dir {
-            dir: Dir::Library
+            dir: Some(Dir::Library),
+            ..
         });
         assert_matches!(parse_from(["maa", "dir", "config"]).command, Command::Dir {
-            dir: Dir::Config
+            dir: Some(Dir::Config),
+            ..
         });
         assert_matches!(parse_from(["maa", "dir", "cache"]).command, Command::Dir {
-            dir: Dir::Cache
+            dir: Some(Dir::Cache),
+            ..
         });
         assert_matches!(
             parse_from(["maa", "dir", "resource"]).command,
-            Command::Dir { dir: Dir::Resource }
+            Command::Dir {
+                dir: Some(Dir::Resource),
+                ..
+            }
         );
         assert_matches!(
             parse_from(["maa", "dir", "hot-update"]).command,
             Command::Dir {
-                dir: Dir::HotUpdate
+                dir: Some(Dir::HotUpdate),
+                ..
             }
         );
         assert_matches!(parse_from(["maa", "dir", "log"]).command, Command::Dir {
-            dir: Dir::Log
+            dir: Some(Dir::Log),
+            ..
+        });
+        assert_matches!(
+            parse_from(["maa", "dir", "cache", "--size"]).command,
+            Command::Dir {
+                dir: Some(Dir::Cache),
+                size: true,
+            }
+        );
+        assert_matches!(parse_from(["maa", "dir", "--size"]).command, Command::Dir {
+            dir: None,
+            size: true,
         });
+        assert!(Cli::try_parse_from(["maa", "dir"]).is_err());
     }
 
     #[test]
@@ -14,6 +14,8 @@ pub struct Config {
     download_url: String,
     #[serde(default)]
     components: CLIComponents,
+    #[serde(default)]
+    cert_pins: Vec<String>,
 }
 
 impl Default for Config {
@@ -23,6 +25,7 @@ impl Default for Config {
             api_url: default_api_url(),
             download_url: default_download_url(),
             components: Default::default(),
+            cert_pins: Vec::new(),
         }
     }
 }
@@ -59,6 +62,13 @@ impl Config {
         &self.components
     }
 
+    /// SHA-256 fingerprints of certificates allowed for the update servers
+    ///
+    /// Empty if certificate pinning is disabled.
+    pub fn cert_pins(&self) -> &[String] {
+        &self.cert_pins
+    }
+
     pub fn with_args(mut self, args: &CommonArgs) -> Self {
         if let Some(channel) = args.channel {
             self.set_channel(channel);
@@ -128,6 +138,7 @@ pub mod tests {
             download_url: "https://github.com/MaaAssistantArknights/maa-cli/releases/download/"
                 .to_string(),
             components: CLIComponents { binary: false },
+            cert_pins: Vec::new(),
         }
     }
 
@@ -158,9 +169,10 @@ pub mod tests {
                     api_url: "https://foo.bar/api/".to_owned(),
                     download_url: "https://foo.bar/download/".to_owned(),
                     components: CLIComponents { binary: false },
+                    cert_pins: vec!["ab:cd".to_owned()],
                 },
                 &[
-                    Token::Map { len: Some(4) },
+                    Token::Map { len: Some(5) },
                     Token::Str("channel"),
                     Channel::Alpha.to_token(),
                     Token::Str("api_url"),
@@ -172,6 +184,10 @@ pub mod tests {
                     Token::Str("binary"),
                     Token::Bool(false),
                     Token::MapEnd,
+                    Token::Str("cert_pins"),
+                    Token::Seq { len: Some(1) },
+                    Token::Str("ab:cd"),
+                    Token::SeqEnd,
                     Token::MapEnd,
                 ],
             );
@@ -14,6 +14,8 @@ pub struct Config {
     api_url: String,
     #[serde(default)]
     components: Components,
+    #[serde(default)]
+    cert_pins: Vec<String>,
 }
 
 impl Default for Config {
@@ -23,6 +25,7 @@ impl Default for Config {
             test_time: default_test_time(),
             api_url: default_api_url(),
             components: Default::default(),
+            cert_pins: Vec::new(),
         }
     }
 }
@@ -64,6 +67,13 @@ impl Config {
         self
     }
 
+    /// SHA-256 fingerprints of certificates allowed for the update servers
+    ///
+    /// Empty if certificate pinning is disabled.
+    pub fn cert_pins(&self) -> &[String] {
+        &self.cert_pins
+    }
+
     pub fn apply_args(mut self, args: &CommonArgs) -> Self {
         if let Some(channel) = args.channel {
             self.set_channel(channel);
@@ -170,6 +180,7 @@ pub mod tests {
                 library: true,
                 resource: true,
             },
+            cert_pins: Vec::new(),
         }
     }
 
@@ -219,6 +230,7 @@ pub mod tests {
                         library: true,
                         resource: true,
                     },
+                    cert_pins: Vec::new(),
                 },
                 &[Token::Map { len: Some(0) }, Token::MapEnd],
             );
@@ -237,9 +249,10 @@ pub mod tests {
                         library: false,
                         resource: false,
                     },
+                    cert_pins: vec!["ab:cd".to_owned()],
                 },
                 &[
-                    Token::Map { len: Some(4) },
+                    Token::Map { len: Some(5) },
                     Token::Str("channel"),
                     Channel::Beta.to_token(),
                     Token::Str("test_time"),
@@ -253,6 +266,10 @@ pub mod tests {
                     Token::Str("resource"),
                     Token::Bool(false),
                     Token::MapEnd,
+                    Token::Str("cert_pins"),
+                    Token::Seq { len: Some(1) },
+                    Token::Str("ab:cd"),
+                    Token::SeqEnd,
                     Token::MapEnd,
                 ],
             );
@@ -379,6 +396,7 @@ pub mod tests {
                         resource: false,
                         ..Default::default()
                     },
+                    cert_pins: Vec::new(),
                 }
             );
         }
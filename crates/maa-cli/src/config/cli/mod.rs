@@ -49,8 +49,11 @@ pub(crate) static CLI_CONFIG: LazyLock<CLIConfig> = LazyLock::new(|| {
         .expect("Failed to load installer config")
 });
 
-#[cfg_attr(test, derive(Debug, PartialEq))]
-#[derive(ValueEnum, Clone, Copy, Default, Deserialize)]
+/// Release channel of prebuilt packages
+///
+/// Channels are ordered from the most stable one to the least stable one.
+#[cfg_attr(test, derive(Debug))]
+#[derive(ValueEnum, Clone, Copy, Default, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Channel {
     #[default]
     #[serde(alias = "stable")]
@@ -71,6 +74,40 @@ impl std::fmt::Display for Channel {
     }
 }
 
+impl std::str::FromStr for Channel {
+    type Err = UnknownChannelError;
+
+    /// Parse a channel from its name case-insensitively
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [Channel::Stable, Channel::Beta, Channel::Alpha]
+            .into_iter()
+            .find(|channel| channel.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownChannelError(s.to_owned()))
+    }
+}
+
+impl serde::Serialize for Channel {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug)]
+pub struct UnknownChannelError(String);
+
+impl std::fmt::Display for UnknownChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown channel `{}`, expected one of `stable`, `beta` or `alpha`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownChannelError {}
+
 fn return_true() -> bool {
     true
 }
@@ -118,6 +155,38 @@ mod tests {
         assert_de_tokens(&Channel::Alpha, &[Channel::Alpha.to_token()]);
     }
 
+    #[test]
+    fn parse_channel() {
+        assert_eq!("stable".parse(), Ok(Channel::Stable));
+        assert_eq!("Beta".parse(), Ok(Channel::Beta));
+        assert_eq!("ALPHA".parse(), Ok(Channel::Alpha));
+
+        let err = "nightly".parse::<Channel>().unwrap_err();
+        assert_eq!(err, UnknownChannelError("nightly".to_owned()));
+        assert_eq!(
+            err.to_string(),
+            "unknown channel `nightly`, expected one of `stable`, `beta` or `alpha`"
+        );
+    }
+
+    #[test]
+    fn channel_order() {
+        assert!(Channel::Stable < Channel::Beta);
+        assert!(Channel::Beta < Channel::Alpha);
+
+        let mut channels = [Channel::Alpha, Channel::Stable, Channel::Beta];
+        channels.sort();
+        assert_eq!(channels, [Channel::Stable, Channel::Beta, Channel::Alpha]);
+    }
+
+    #[test]
+    fn serialize_channel() {
+        assert_eq!(
+            serde_json::to_string(&[Channel::Stable, Channel::Beta, Channel::Alpha]).unwrap(),
+            r#"["stable","beta","alpha"]"#
+        );
+    }
+
     #[test]
     fn deserialize_installer_config() {
         assert_de_tokens(&CLIConfig::default(), &[
@@ -10,7 +10,10 @@ pub use condition::{remainder_of_day_mod, TimeOffset};
 use maa_sys::TaskType;
 use serde::Deserialize;
 
-use crate::{dirs, object, value::MAAValue};
+use crate::{
+    dirs, object,
+    value::{MAAValue, Map},
+};
 
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(Deserialize, Default)]
@@ -140,6 +143,9 @@ pub struct TaskConfig {
     client_type: Option<ClientType>,
     startup: Option<bool>,
     closedown: Option<bool>,
+    /// Named fragments which can be referenced in params by `{ "ref": "name" }`
+    #[serde(default, alias = "anchors")]
+    definitions: Map<String, MAAValue>,
     tasks: Vec<Task>,
 }
 
@@ -149,6 +155,7 @@ impl TaskConfig {
             client_type: None,
             startup: None,
             closedown: None,
+            definitions: Map::new(),
             tasks: Vec::new(),
         }
     }
@@ -174,7 +181,9 @@ impl TaskConfig {
             }
 
             let task_type = task.task_type();
-            let mut params = task.params().init()?;
+            let mut params = task.params();
+            params.resolve_refs(&self.definitions)?;
+            let mut params = params.init()?;
 
             // If startup task is not enabled, enable it automatically
             match task_type {
@@ -661,6 +670,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![],
                 }
                 .init()
@@ -679,6 +689,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(StartUp, object!("start_game_enabled" => true))
                         .with_variants(vec![TaskVariant {
                             condition: Condition::Not {
@@ -702,6 +713,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(
                         StartUp,
                         object!(
@@ -733,6 +745,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(
                         StartUp,
                         object!(
@@ -763,6 +776,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(CloseDown, object!("client_type" => "YoStarEN"))],
                 }
                 .init()
@@ -783,6 +797,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(
                         CloseDown,
                         object!(
@@ -812,6 +827,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(CloseDown, object!())],
                 }
                 .init()
@@ -832,6 +848,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![Task::new(Fight, object!("client_type" => "YoStarEN"))],
                 }
                 .init()
@@ -852,6 +869,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![
                         Task::new(
                             StartUp,
@@ -895,6 +913,7 @@ mod tests {
                     client_type: Some(Official),
                     startup: Some(true),
                     closedown: Some(true),
+                    definitions: Map::new(),
                     tasks: vec![
                         Task::new(StartUp, object!( "start_game_enabled" => false)),
                         Task::new(Fight, object!("stage" => "1-7")),
@@ -939,6 +958,7 @@ mod tests {
                     client_type: None,
                     startup: Some(true),
                     closedown: Some(true),
+                    definitions: Map::new(),
                     tasks: vec![Task::new(Fight, object!("stage" => "1-7"))],
                 }
                 .init()
@@ -972,6 +992,7 @@ mod tests {
                     client_type: Some(YoStarEN),
                     startup: Some(true),
                     closedown: Some(true),
+                    definitions: Map::new(),
                     tasks: vec![Task::new(Fight, object!("stage" => "1-7"))],
                 }
                 .init()
@@ -1006,6 +1027,7 @@ mod tests {
                     client_type: Some(Official),
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![
                         Task::new(StartUp, object!("client_type" => "YoStarEN")),
                         Task::new(CloseDown, object!("client_type" => "YoStarJP")),
@@ -1024,6 +1046,58 @@ mod tests {
                 }
             );
 
+            // References in params are resolved from definitions
+            assert_eq!(
+                TaskConfig {
+                    client_type: None,
+                    startup: None,
+                    closedown: None,
+                    definitions: Map::from([(
+                        "my_fight".to_string(),
+                        object!("stage" => "1-7", "medicine" => 1),
+                    )]),
+                    tasks: vec![
+                        Task::new(Fight, object!("ref" => "my_fight")),
+                        Task::new(Fight, object!("ref" => "my_fight", "stage" => "CE-6")),
+                    ],
+                }
+                .init()
+                .unwrap(),
+                InitializedTaskConfig {
+                    client_type: Official,
+                    start_app: false,
+                    close_app: false,
+                    tasks: vec![
+                        InitializedTask::new(
+                            Fight,
+                            object!(
+                                "stage" => "1-7",
+                                "medicine" => 1,
+                                "client_type" => "Official",
+                            )
+                        ),
+                        InitializedTask::new(
+                            Fight,
+                            object!(
+                                "stage" => "CE-6",
+                                "medicine" => 1,
+                                "client_type" => "Official",
+                            )
+                        ),
+                    ]
+                }
+            );
+
+            assert!(TaskConfig {
+                client_type: None,
+                startup: None,
+                closedown: None,
+                definitions: Map::new(),
+                tasks: vec![Task::new(Fight, object!("ref" => "not_exist"))],
+            }
+            .init()
+            .is_err());
+
             // Filename will be converted to absolute path
             #[cfg(unix)]
             assert_eq!(
@@ -1031,6 +1105,7 @@ mod tests {
                     client_type: None,
                     startup: None,
                     closedown: None,
+                    definitions: Map::new(),
                     tasks: vec![
                         Task::new(Infrast, object!("filename" => "daily.json")),
                         Task::new(Infrast, object!("filename" => "/tmp/daily.json")),
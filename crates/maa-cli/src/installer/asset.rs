@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use super::{
+    download::human_size,
+    verify::{self, ChecksumError, SizeError},
+};
+
+/// A file to be downloaded by the installer, e.g. a package of maa-cli or MaaCore.
+pub trait DownloadableAsset {
+    /// Name of the asset, which is also the file name in the cache directory.
+    fn name(&self) -> &str;
+
+    /// Size of the asset in bytes.
+    fn size(&self) -> u64;
+
+    /// Links to download the asset from, the source url comes first.
+    ///
+    /// Empty if the links are not given by the asset itself but by the configuration.
+    fn urls(&self) -> Vec<&str>;
+
+    /// The sha256 checksum of the asset, if any.
+    fn checksum(&self) -> Option<&str>;
+
+    /// Size of the asset with binary units, e.g. `148.05 MiB`.
+    fn human_size(&self) -> String {
+        human_size(self.size())
+    }
+
+    /// Verify the size of the downloaded asset at `path`.
+    fn verify_size(&self, path: &Path) -> Result<(), SizeError> {
+        verify::verify_size(path, self.size())
+    }
+
+    /// Verify the sha256 checksum of the downloaded asset at `path`.
+    ///
+    /// Always succeeds if the asset has no checksum.
+    fn verify_checksum(&self, path: &Path) -> Result<(), ChecksumError> {
+        match self.checksum() {
+            Some(checksum) => verify::verify_sha256(path, checksum),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,22 +1,35 @@
 use std::{
     cmp::min,
-    fs::{remove_file, File},
-    io::Write,
-    path::Path,
-    time::{Duration, Instant},
+    ffi::OsString,
+    fs::{read_dir, remove_file, rename, File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
 };
 
 use digest::Digest;
 use futures_util::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
 use log::debug;
-use reqwest::Client;
+use reqwest::{
+    header::{CONTENT_RANGE, RANGE},
+    Client, StatusCode,
+};
 use sha2::Sha256;
 
+use super::{
+    asset::DownloadableAsset,
+    pin::{CertPins, PinError},
+    verify::SizeError,
+};
+use crate::dirs::{self, Ensure};
+
 #[derive(Debug)]
 pub enum Error {
     Reqwest(reqwest::Error),
     Io(std::io::Error),
+    Pin(PinError),
+    Size(SizeError),
     Verify,
 }
 
@@ -32,11 +45,25 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<PinError> for Error {
+    fn from(e: PinError) -> Self {
+        Error::Pin(e)
+    }
+}
+
+impl From<SizeError> for Error {
+    fn from(e: SizeError) -> Self {
+        Error::Size(e)
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Error::Reqwest(e) => e.fmt(f),
             Error::Io(e) => e.fmt(f),
+            Error::Pin(e) => e.fmt(f),
+            Error::Size(e) => e.fmt(f),
             Error::Verify => write!(f, "Checksum verification failed"),
         }
     }
@@ -46,6 +73,25 @@ impl std::error::Error for Error {}
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// Format a size in bytes with binary units, e.g. `148.14 MiB`.
+///
+/// Sizes less than 1 KiB are formatted in bytes without decimals.
+pub fn human_size(size: u64) -> String {
+    const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if size < 1024 {
+        return format!("{size} B");
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.2} {}", UNITS[unit])
+}
+
 /// Checksum checker.
 ///
 /// Currently only support sha256.
@@ -79,6 +125,18 @@ impl Hasher {
         }
     }
 
+    /// Update the hasher with all the content of `reader`.
+    pub fn update_from(&mut self, mut reader: impl Read) -> std::io::Result<()> {
+        let mut buf = [0; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                return Ok(());
+            }
+            self.update(&buf[..n]);
+        }
+    }
+
     pub fn verify(self, checksum: &str) -> bool {
         match self {
             Self::Sha256(hasher) => {
@@ -89,23 +147,188 @@ impl Hasher {
     }
 }
 
-// download a file with given url and size to a given path,
-// with optional checksum verification.
+/// Extension of in-progress downloads in the staging directory.
+const PART_EXTENSION: &str = "part";
+
+/// Partial downloads not modified for this long are considered stale and removed.
+const STALE_PART_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Bookkeeping of a partial download in the staging directory.
+///
+/// A download interrupted mid-way leaves its partial file in the staging directory, which can be
+/// resumed by requesting the remaining bytes with a `Range` header.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct PartialDownload {
+    path: PathBuf,
+    expected_size: u64,
+    downloaded: u64,
+}
+
+impl PartialDownload {
+    /// Inspect the partial file at `path` of a download with `expected_size` bytes.
+    ///
+    /// Nothing is downloaded if the file does not exist.
+    pub fn new(path: PathBuf, expected_size: u64) -> std::io::Result<Self> {
+        let downloaded = match path.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            expected_size,
+            downloaded,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of bytes already downloaded.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// Offset to resume the download from.
+    ///
+    /// Return `None` if nothing is downloaded yet, or the partial file is not smaller than
+    /// expected, in which case it is corrupted and should be downloaded again from scratch.
+    pub fn range_start(&self) -> Option<u64> {
+        (self.downloaded > 0 && self.downloaded < self.expected_size).then_some(self.downloaded)
+    }
+
+    /// Value of the `Range` header to request the remaining bytes.
+    pub fn range_header(&self) -> Option<String> {
+        self.range_start().map(|start| format!("bytes={start}-"))
+    }
+
+    /// Validate the total size of the file reported by the server.
+    ///
+    /// A mismatch means the file on the server is different from the expected one, so the
+    /// partial file can not be resumed.
+    pub fn validate_total(&self, total: u64) -> std::result::Result<(), SizeError> {
+        if total == self.expected_size {
+            Ok(())
+        } else {
+            Err(SizeError::Mismatch {
+                expected: self.expected_size,
+                actual: total,
+            })
+        }
+    }
+}
+
+/// Parse the total size from a `Content-Range` header, e.g. `bytes 100-199/200`.
+///
+/// Return `None` if the header is invalid or the total size is unknown (`*`).
+fn content_range_total(value: &str) -> Option<u64> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    if range != "*" {
+        let (start, end) = range.split_once('-')?;
+        start.trim().parse::<u64>().ok()?;
+        end.trim().parse::<u64>().ok()?;
+    }
+    total.trim().parse().ok()
+}
+
+/// Path of the partial file in `staging` for a download saved to `path`.
+fn part_path(staging: &Path, path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(PART_EXTENSION);
+    staging.join(name)
+}
+
+/// Move a complete and verified partial file to its final location.
+fn promote(part: &Path, path: &Path) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        parent.ensure()?;
+    }
+    rename(part, path)
+}
+
+/// Remove partial files in `staging` which have not been modified for `max_age`.
+///
+/// Files without the `.part` extension are left untouched.
+///
+/// # Returns
+/// The number of removed files.
+fn clean_stale_parts(staging: &Path, max_age: Duration) -> std::io::Result<usize> {
+    if !staging.exists() {
+        return Ok(0);
+    }
+
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in read_dir(staging)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != PART_EXTENSION) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        if now.duration_since(modified).is_ok_and(|age| age >= max_age) {
+            debug!("Removing stale partial download {}", path.display());
+            remove_file(path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+// download an asset with given url to a given path,
+// verifying its size and checksum if any.
+//
+// The file is written to the download staging directory with a `.part` suffix first,
+// and only moved to the given path after the download completes and passes verification.
 //
 // # Arguments
 // * `client` - A reqwest client.
+// * `pins` - The pinned certificates of the server.
 // * `url` - The url to download from.
 // * `path` - The path to save the downloaded file.
-// * `size` - The size of the file.
-// * `checker` - The optional checksum checker.
+// * `asset` - The asset to download, which gives the size and the optional checksum.
 pub async fn download(
     client: &Client,
+    pins: &CertPins,
     url: &str,
     path: &Path,
-    size: u64,
-    checker: Option<Checker<'_>>,
+    asset: &impl DownloadableAsset,
 ) -> Result<()> {
-    let resp = client.get(url).send().await?;
+    let size = asset.size();
+    let checker = asset.checksum().map(Checker::Sha256);
+    let staging = dirs::download_staging().ensure()?;
+    if let Err(e) = clean_stale_parts(staging, STALE_PART_AGE) {
+        debug!("Failed to clean stale partial downloads: {e}");
+    }
+    let partial = PartialDownload::new(part_path(staging, path), size)?;
+
+    let mut request = client.get(url);
+    if let Some(range) = partial.range_header() {
+        debug!(
+            "Resuming download of {} from byte {}",
+            path.display(),
+            partial.downloaded()
+        );
+        request = request.header(RANGE, range);
+    }
+    let resp = request.send().await?.error_for_status()?;
+    pins.verify(&resp)?;
+
+    // The server may ignore the range and send the whole file
+    let resumed = resp.status() == StatusCode::PARTIAL_CONTENT;
+    if resumed {
+        if let Some(total) = resp
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(content_range_total)
+        {
+            partial.validate_total(total)?;
+        }
+    }
 
     let progress_bar = ProgressBar::new(size);
     progress_bar.set_style(
@@ -116,41 +339,52 @@ pub async fn download(
     );
     progress_bar.set_message("Downloading...");
 
-    let mut stream = resp.bytes_stream();
-    let mut file = File::create(path)?;
-
-    if let Some(checker) = checker {
-        let mut downloaded: u64 = 0;
-        let mut hasher = checker.hasher();
+    let part = partial.path();
+    let mut hasher = checker.as_ref().map(Checker::hasher);
+    let (mut file, mut downloaded) = if resumed {
+        // Bytes downloaded before should be verified together with the new ones
+        if let Some(hasher) = hasher.as_mut() {
+            hasher.update_from(File::open(part)?)?;
+        }
+        (
+            OpenOptions::new().append(true).open(part)?,
+            partial.downloaded(),
+        )
+    } else {
+        (File::create(part)?, 0)
+    };
+    progress_bar.set_position(downloaded);
 
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            file.write_all(&chunk)?;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        file.write_all(&chunk)?;
+        if let Some(hasher) = hasher.as_mut() {
             hasher.update(&chunk);
-            downloaded = min(downloaded + chunk.len() as u64, size);
-            progress_bar.set_position(downloaded);
         }
+        downloaded = min(downloaded + chunk.len() as u64, size);
+        progress_bar.set_position(downloaded);
+    }
+    drop(file);
 
+    // A truncated file is kept, so the download can be resumed next time
+    asset.verify_size(part)?;
+
+    if let (Some(checker), Some(hasher)) = (checker, hasher) {
         progress_bar.finish_with_message("Downloaded, verifying checksum...");
 
         if hasher.verify(checker.checksum()) {
             println!("Checksum verified");
         } else {
-            remove_file(path)?;
+            remove_file(part)?;
             return Err(Error::Verify);
         }
     } else {
-        let mut downloaded: u64 = 0;
-        while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
-            file.write_all(&chunk)?;
-            downloaded = min(downloaded + chunk.len() as u64, size);
-            progress_bar.set_position(downloaded);
-        }
-
         progress_bar.finish_with_message("Downloaded.");
     }
 
+    promote(part, path)?;
+
     Ok(())
 }
 
@@ -158,13 +392,20 @@ pub async fn download(
 ///
 /// # Arguments
 /// * `client` - A reqwest client.
+/// * `pins` - The pinned certificates of the server.
 /// * `url` - The url to download from.
 /// * `timeout` - The timeout.
 ///
 /// # Returns
 /// The number of bytes downloaded.
-async fn try_download(client: &Client, url: &str, timeout: Duration) -> Result<u64> {
+async fn try_download(
+    client: &Client,
+    pins: &CertPins,
+    url: &str,
+    timeout: Duration,
+) -> Result<u64> {
     let resp = client.get(url).send().await?;
+    pins.verify(&resp)?;
 
     let mut stream = resp.bytes_stream();
     let mut downloaded: u64 = 0;
@@ -185,18 +426,18 @@ async fn try_download(client: &Client, url: &str, timeout: Duration) -> Result<u
 ///
 /// # Arguments
 /// * `client` - A reqwest client.
+/// * `pins` - The pinned certificates of the servers.
 /// * `mirrors` - The mirrors to choose from.
 /// * `path` - The path to save the downloaded file.
-/// * `size` - The size of the file.
 /// * `t` - The test duration for each mirror, in seconds, 0 to skip the test.
-/// * `checker` - The optional checksum checker.
+/// * `asset` - The asset to download, which gives the size and the optional checksum.
 pub async fn download_mirrors(
     client: &Client,
+    pins: &CertPins,
     mirrors: Vec<String>,
     path: &Path,
-    size: u64,
     t: u64,
-    checker: Option<Checker<'_>>,
+    asset: &impl DownloadableAsset,
 ) -> Result<()> {
     // The first mirror is the default download link.
     let mut download_link = &mirrors[0];
@@ -204,7 +445,7 @@ pub async fn download_mirrors(
     if t == 0 {
         println!("Skip speed test, downloading from first link...");
         debug!("First link: {}", download_link);
-        download(client, download_link, path, size, checker).await?;
+        download(client, pins, download_link, path, asset).await?;
         return Ok(());
     }
 
@@ -214,7 +455,7 @@ pub async fn download_mirrors(
     println!("Testing download speed...");
     for link in mirrors.iter() {
         debug!("Testing {}", link);
-        if let Ok(downloaded) = try_download(client, link, test_duration).await {
+        if let Ok(downloaded) = try_download(client, pins, link, test_duration).await {
             if downloaded > largest {
                 debug!(
                     "Found faster link {} with {} bytes downloaded",
@@ -228,11 +469,137 @@ pub async fn download_mirrors(
 
     println!("Downloading from fastest mirror...");
     debug!("Fastest link: {}", download_link);
-    download(client, download_link, path, size, checker).await?;
+    download(client, pins, download_link, path, asset).await?;
 
     Ok(())
 }
 
-pub fn check_file_exists(path: &Path, size: u64) -> bool {
-    path.exists() && path.is_file() && path.metadata().is_ok_and(|metadata| metadata.len() == size)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::installer::verify::verify_size;
+
+    #[test]
+    fn format_size() {
+        assert_eq!(human_size(0), "0 B");
+        assert_eq!(human_size(1023), "1023 B");
+        assert_eq!(human_size(1024), "1.00 KiB");
+        assert_eq!(human_size(1024 * 1024 - 1), "1024.00 KiB");
+        assert_eq!(human_size(1024 * 1024), "1.00 MiB");
+        assert_eq!(human_size(155241185), "148.05 MiB");
+        assert_eq!(
+            human_size(5 * 1024 * 1024 * 1024 + 512 * 1024 * 1024),
+            "5.50 GiB"
+        );
+        assert_eq!(human_size(u64::MAX), "16.00 EiB");
+    }
+
+    #[test]
+    fn partial_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("MAA-v5.0.0-linux-x86_64.tar.gz.part");
+
+        // Nothing downloaded
+        let partial = PartialDownload::new(path.clone(), 10).unwrap();
+        assert_eq!(partial.downloaded(), 0);
+        assert_eq!(partial.range_start(), None);
+        assert_eq!(partial.range_header(), None);
+
+        // Resume from the end of the partial file
+        std::fs::write(&path, b"content").unwrap();
+        let partial = PartialDownload::new(path.clone(), 10).unwrap();
+        assert_eq!(partial.downloaded(), 7);
+        assert_eq!(partial.range_start(), Some(7));
+        assert_eq!(partial.range_header().as_deref(), Some("bytes=7-"));
+
+        // Complete or oversized partial files are downloaded again
+        assert_eq!(
+            PartialDownload::new(path.clone(), 7).unwrap().range_start(),
+            None
+        );
+        assert_eq!(PartialDownload::new(path, 5).unwrap().range_start(), None);
+    }
+
+    #[test]
+    fn partial_download_total() {
+        let partial = PartialDownload {
+            path: PathBuf::from("MAA.tar.gz.part"),
+            expected_size: 200,
+            downloaded: 100,
+        };
+        assert!(partial.validate_total(200).is_ok());
+        assert!(matches!(
+            partial.validate_total(300),
+            Err(SizeError::Mismatch {
+                expected: 200,
+                actual: 300
+            })
+        ));
+
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes */200"), Some(200));
+        assert_eq!(content_range_total("bytes 100-199/*"), None);
+        assert_eq!(content_range_total("bytes 100-199"), None);
+        assert_eq!(content_range_total("items 100-199/200"), None);
+    }
+
+    #[test]
+    fn hash_reader() {
+        let checker =
+            Checker::Sha256("ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73");
+        let mut hasher = checker.hasher();
+        hasher.update_from(&b"con"[..]).unwrap();
+        hasher.update(b"tent");
+        assert!(hasher.verify(checker.checksum()));
+    }
+
+    #[test]
+    fn staging_to_final() {
+        let root = tempfile::tempdir().unwrap();
+        let staging = root.path().join("downloads").join("tmp");
+        let path = root.path().join("MAA-v5.0.0-linux-x86_64.tar.gz");
+
+        let part = part_path(&staging, &path);
+        assert_eq!(part, staging.join("MAA-v5.0.0-linux-x86_64.tar.gz.part"));
+
+        staging.as_path().ensure().unwrap();
+        std::fs::write(&part, b"content").unwrap();
+        assert!(verify_size(&path, 7).is_err());
+
+        promote(&part, &path).unwrap();
+        assert!(!part.exists());
+        assert!(verify_size(&path, 7).is_ok());
+    }
+
+    #[test]
+    fn clean_stale() {
+        let root = tempfile::tempdir().unwrap();
+        let staging = root.path();
+
+        assert_eq!(
+            clean_stale_parts(&staging.join("not_exist"), STALE_PART_AGE).unwrap(),
+            0
+        );
+
+        let stale = staging.join("stale.zip.part");
+        let fresh = staging.join("fresh.zip.part");
+        let other = staging.join("other.zip");
+        for path in [&stale, &fresh, &other] {
+            File::create(path).unwrap();
+        }
+        let old = SystemTime::now() - STALE_PART_AGE * 2;
+        for path in [&stale, &other] {
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        assert_eq!(clean_stale_parts(staging, STALE_PART_AGE).unwrap(), 1);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(other.exists());
+    }
 }
@@ -1,15 +1,20 @@
 use std::{collections::BTreeMap, time::Duration};
 
 use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
 use maa_dirs::MAA_CLI_EXE;
 use semver::Version;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 use tokio::runtime::Runtime;
 
 use super::{
-    download::{download, Checker},
+    asset::DownloadableAsset,
+    download::download,
     extract::Archive,
-    version_json::VersionJSON,
+    pin::{CertPins, Pinned},
+    verify,
+    version_json::{deserialize_timestamp, VersionJSON},
 };
 use crate::{
     config::cli::{maa_cli::CommonArgs, CLI_CONFIG},
@@ -19,40 +24,49 @@ use crate::{
 pub fn update(args: &CommonArgs) -> Result<()> {
     let config = CLI_CONFIG.cli_config().with_args(args);
 
+    let pins = CertPins::new(config.cert_pins());
+    let client = pins
+        .configure(reqwest::Client::builder())
+        .connect_timeout(Duration::from_secs(10))
+        .build()
+        .context("Failed to build reqwest client")?;
+    let runtime = Runtime::new().context("Failed to create tokio runtime")?;
+
     println!("Fetching maa-cli version info...");
-    let version_json: VersionJSON<Details> = reqwest::blocking::get(config.api_url())
-        .context("Failed to fetch version info")?
-        .json()
-        .context("Failed to parse version info")?;
+    let version_json: VersionJSON<Details> = runtime.block_on(VersionJSON::fetch(
+        &Pinned {
+            client: &client,
+            pins: &pins,
+        },
+        &config.api_url(),
+    ))?;
     let current_version: Version = env!("MAA_VERSION").parse()?;
     if !version_json.can_update("maa-cli", &current_version)? {
         return Ok(());
     }
 
     let details = version_json.details();
+    if let Some(released_at) = details.released_at() {
+        debug!(
+            "maa-cli {} was released at {}",
+            version_json.version(),
+            released_at
+        );
+    }
     let asset = details.asset()?;
     let asset_name = asset.name();
-    let asset_size = asset.size();
-    let asset_checksum = asset.checksum();
     let cache_path = dirs::cache().ensure()?.join(asset_name);
 
-    if cache_path.exists() && cache_path.metadata()?.len() == asset_size {
+    if cache_path.exists()
+        && asset.verify_size(&cache_path).is_ok()
+        && asset.verify_checksum(&cache_path).is_ok()
+    {
         println!("Found existing file: {}", cache_path.display());
     } else {
+        println!("Downloading {} ({})...", asset_name, asset.human_size());
         let url = config.download_url(details.tag(), asset_name);
-        let client = reqwest::Client::builder()
-            .connect_timeout(Duration::from_secs(10))
-            .build()
-            .context("Failed to create reqwest client")?;
-        Runtime::new()
-            .context("Failed to create tokio runtime")?
-            .block_on(download(
-                &client,
-                &url,
-                &cache_path,
-                asset_size,
-                Some(Checker::Sha256(asset_checksum)),
-            ))
+        runtime
+            .block_on(download(&client, &pins, &url, &cache_path, asset))
             .context("Failed to download maa-cli")?;
     };
 
@@ -72,9 +86,16 @@ pub fn update(args: &CommonArgs) -> Result<()> {
     Ok(())
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct Details {
     tag: String,
+    #[serde(
+        rename = "published_at",
+        default,
+        deserialize_with = "deserialize_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    released_at: Option<DateTime<Utc>>,
     assets: Assets,
 }
 
@@ -83,12 +104,17 @@ impl Details {
         &self.tag
     }
 
+    /// Time when the release was published, if given in the version json.
+    fn released_at(&self) -> Option<DateTime<Utc>> {
+        self.released_at
+    }
+
     fn asset(&self) -> Result<&Asset> {
         self.assets.asset()
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 struct Assets(BTreeMap<String, Asset>);
 
 const PLATFORM: &str = env!("TARGET");
@@ -101,24 +127,44 @@ impl Assets {
     }
 }
 
-#[derive(Deserialize)]
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Serialize)]
 struct Asset {
     name: String,
     size: u64,
+    #[serde(deserialize_with = "deserialize_sha256")]
     sha256sum: String,
 }
 
-impl Asset {
-    pub fn name(&self) -> &str {
+/// Deserialize a sha256 checksum, which should be 64 hex characters after normalization.
+fn deserialize_sha256<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let checksum = String::deserialize(deserializer)?;
+    let normalized = verify::normalize_sha256(&checksum);
+    if normalized.len() == 64 && normalized.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Ok(normalized)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "invalid sha256sum `{checksum}`, expected 64 hex characters"
+        )))
+    }
+}
+
+impl DownloadableAsset for Asset {
+    fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn size(&self) -> u64 {
+    fn size(&self) -> u64 {
         self.size
     }
 
-    pub fn checksum(&self) -> &str {
-        &self.sha256sum
+    /// The download url of maa-cli is given by the configuration, see `download_url`.
+    fn urls(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    fn checksum(&self) -> Option<&str> {
+        Some(&self.sha256sum)
     }
 }
 
@@ -172,13 +218,103 @@ mod tests {
         "#;
 
         let version_json: VersionJSON<Details> = serde_json::from_str(json).unwrap();
+        assert_eq!(version_json.details().released_at(), None);
         let asset = version_json.details().asset().unwrap();
 
         assert_eq!(asset.name(), format!("maa_cli-0.1.0-{}.zip", PLATFORM));
         assert_eq!(asset.size(), 123456);
         assert_eq!(
             asset.checksum(),
-            "1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef"
+            Some("1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef")
+        );
+        assert!(asset.urls().is_empty());
+    }
+
+    #[test]
+    fn released_at() {
+        let json = serde_json::json!({
+            "tag": "v0.1.0",
+            "published_at": "2024-01-01T08:00:00+08:00",
+            "assets": {},
+        });
+        let details: Details = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            details.released_at(),
+            Some("2024-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            serde_json::to_value(&details).unwrap(),
+            serde_json::json!({
+                "tag": "v0.1.0",
+                "published_at": "2024-01-01T00:00:00Z",
+                "assets": {},
+            })
+        );
+
+        let details: Details =
+            serde_json::from_value(serde_json::json!({ "tag": "v0.1.0", "assets": {} })).unwrap();
+        assert_eq!(details.released_at(), None);
+        assert_eq!(
+            serde_json::to_value(&details).unwrap(),
+            serde_json::json!({ "tag": "v0.1.0", "assets": {} })
+        );
+    }
+
+    #[test]
+    fn deserialize_sha256() {
+        fn parse(sha256sum: &str) -> Result<Asset, serde_json::Error> {
+            serde_json::from_value(serde_json::json!({
+                "name": "maa_cli.zip",
+                "size": 7,
+                "sha256sum": sha256sum,
+            }))
+        }
+
+        let checksum = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+        assert_eq!(parse(checksum).unwrap().checksum(), Some(checksum));
+        assert_eq!(
+            parse(&format!("sha256:{}", checksum.to_ascii_uppercase()))
+                .unwrap()
+                .checksum(),
+            Some(checksum)
+        );
+
+        // Wrong length
+        let err = parse(&checksum[1..]).unwrap_err();
+        assert!(err.to_string().contains("expected 64 hex characters"));
+        assert!(parse("").is_err());
+        // Non-hex characters
+        assert!(parse(&checksum.replace('e', "g")).is_err());
+    }
+
+    #[test]
+    fn verify_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("maa_cli.zip");
+        std::fs::write(&path, b"content").unwrap();
+
+        let asset = |sha256sum: &str| Asset {
+            name: "maa_cli.zip".to_owned(),
+            size: 7,
+            sha256sum: sha256sum.to_owned(),
+        };
+
+        asset("ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73")
+            .verify_checksum(&path)
+            .unwrap();
+        asset("sha256:ED7002B439E9AC845F22357D822BAC1444730FBDB6016D3EC9432297B9EC9F73")
+            .verify_checksum(&path)
+            .unwrap();
+        assert_eq!(
+            asset(&"0".repeat(64))
+                .verify_checksum(&path)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Checksum mismatch: expected sha256 {}, got {}",
+                "0".repeat(64),
+                "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73"
+            )
         );
     }
 }
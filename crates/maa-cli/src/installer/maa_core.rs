@@ -8,15 +8,18 @@ use std::{
 };
 
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
 use log::debug;
 use semver::Version;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::runtime::Runtime;
 
 use super::{
-    download::{check_file_exists, download_mirrors},
+    asset::DownloadableAsset,
+    download::{download_mirrors, human_size},
     extract::Archive,
-    version_json::VersionJSON,
+    pin::{CertPins, Pinned},
+    version_json::{deserialize_timestamp, VersionJSON},
 };
 use crate::{
     config::cli::{
@@ -79,6 +82,7 @@ pub fn version() -> Result<Version> {
 
 pub fn install(force: bool, args: &CommonArgs) -> Result<()> {
     let config = CLI_CONFIG.core_config().apply_args(args);
+    let _lock = crate::acquire_lock()?;
 
     let lib_dir = dirs::library();
     let lib_name = format!("{}MaaCore{}", DLL_PREFIX, DLL_SUFFIX);
@@ -93,36 +97,27 @@ pub fn install(force: bool, args: &CommonArgs) -> Result<()> {
     );
     let version_json = get_version_json(&config)?;
     let asset_version = version_json.version();
-    let asset_name = name(asset_version)?;
-    let asset = version_json.details().asset(&asset_name)?;
+    let details = version_json.details();
+    if let Some(released_at) = details.released_at() {
+        debug!("MaaCore {} was released at {}", asset_version, released_at);
+    }
+    let asset = details.select_for_current()?;
 
-    println!("Downloading MaaCore {}...", asset_version);
+    println!(
+        "Downloading MaaCore {} ({})...",
+        asset_version,
+        asset.human_size()
+    );
     let cache_dir = dirs::cache().ensure()?;
-    let archive = download(
-        cache_dir.join(asset_name).into(),
-        asset.size(),
-        asset.download_links(),
-        &config,
-    )?;
+    let archive = download(cache_dir.join(asset.name()).into(), asset, &config)?;
 
     println!("Installing MaaCore...");
-    let components = config.components();
-    if components.library {
-        debug!("Cleaning library directory");
-        lib_dir.ensure_clean()?;
-    }
-    let resource_dir = dirs::resource();
-    if components.resource {
-        debug!("Cleaning resource directory");
-        resource_dir.ensure_clean()?;
-    }
-    archive.extract(|path| extract_mapper(path, lib_dir, resource_dir, components))?;
-
-    Ok(())
+    install_components(&archive, config.components())
 }
 
 pub fn update(args: &CommonArgs) -> Result<()> {
     let config = CLI_CONFIG.core_config().apply_args(args);
+    let _lock = crate::acquire_lock()?;
 
     let components = config.components();
     // Check if any component is specified
@@ -157,41 +152,102 @@ pub fn update(args: &CommonArgs) -> Result<()> {
     if !version_json.can_update("MaaCore", &current_version)? {
         return Ok(());
     }
-    let asset_name = name(asset_version)?;
-    let asset = version_json.details().asset(&asset_name)?;
+    let details = version_json.details();
+    if let Some(released_at) = details.released_at() {
+        debug!("MaaCore {} was released at {}", asset_version, released_at);
+    }
+    let asset = details.select_for_current()?;
 
-    println!("Downloading MaaCore {}...", asset_version);
+    println!(
+        "Downloading MaaCore {} ({})...",
+        asset_version,
+        asset.human_size()
+    );
     let cache_dir = dirs::cache().ensure()?;
-    let asset_path = cache_dir.join(asset_name);
-    let archive = download(
-        asset_path.into(),
-        asset.size(),
-        asset.download_links(),
-        &config,
-    )?;
+    let asset_path = cache_dir.join(asset.name());
+    let archive = download(asset_path.into(), asset, &config)?;
 
     println!("Installing MaaCore...");
+    let backed_up = components.library
+        && dirs::backup_library().context("Failed to back up MaaCore library")?;
+    if let Err(err) = install_components(&archive, components) {
+        if backed_up {
+            match dirs::restore_library() {
+                Ok(_) => println!("Update aborted, restored the previous MaaCore library"),
+                Err(restore_err) => log::error!("Failed to restore MaaCore library: {restore_err}"),
+            }
+        }
+        return Err(err);
+    }
+    if backed_up {
+        if let Err(err) = dirs::discard_library_backup() {
+            log::warn!("Failed to remove backup of MaaCore library: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Extract components from the archive and install them.
+///
+/// Components are extracted into staging dirs first, and then swapped into place, so an
+/// interrupted installation does not leave a half-installed MaaCore.
+fn install_components(archive: &Archive, components: &Components) -> Result<()> {
+    let lib_dir = dirs::library();
+    let resource_dir = dirs::resource();
+    let staging_for = |dir: &Path| {
+        dirs::staging_for(dir)
+            .with_context(|| format!("Failed to get staging directory of {}", dir.display()))
+    };
+    let lib_staging = staging_for(lib_dir)?;
+    let resource_staging = staging_for(resource_dir)?;
+
+    let mut staged = Vec::new();
     if components.library {
-        debug!("Cleaning library directory");
-        lib_dir.ensure_clean()?;
+        staged.push((lib_staging.as_path(), lib_dir));
     }
     if components.resource {
-        debug!("Cleaning resource directory");
-        resource_dir.ensure_clean()?;
+        staged.push((resource_staging.as_path(), resource_dir));
+    }
+    for (staging, _) in &staged {
+        debug!("Cleaning staging directory {}", staging.display());
+        staging.ensure_clean()?;
+    }
+
+    let extracted =
+        archive.extract(|path| extract_mapper(path, &lib_staging, &resource_staging, components));
+    if let Err(err) = extracted {
+        for (staging, _) in &staged {
+            let _ = std::fs::remove_dir_all(staging);
+        }
+        return Err(err);
+    }
+
+    for (staging, target) in staged {
+        debug!("Installing {} => {}", staging.display(), target.display());
+        dirs::install_atomic(staging, target)
+            .with_context(|| format!("Failed to install {}", target.display()))?;
     }
-    archive.extract(|path| extract_mapper(path, lib_dir, resource_dir, components))?;
 
     Ok(())
 }
 
 fn get_version_json(config: &Config) -> Result<VersionJSON<Details>> {
     let url = config.api_url();
-    let version_json = reqwest::blocking::get(&url)
-        .with_context(|| format!("Failed to fetch version info from {}", url))?
-        .json()
-        .with_context(|| "Failed to parse version info")?;
-
-    Ok(version_json)
+    let pins = CertPins::new(config.cert_pins());
+    let client = pins
+        .configure(reqwest::Client::builder())
+        .build()
+        .context("Failed to build reqwest client")?;
+    Runtime::new()
+        .context("Failed to create tokio runtime")?
+        .block_on(VersionJSON::fetch(
+            &Pinned {
+                client: &client,
+                pins: &pins,
+            },
+            &url,
+        ))
 }
 
 /// Get the name of the asset for the current platform
@@ -212,22 +268,79 @@ pub fn name(version: &Version) -> Result<String> {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Details {
+    #[serde(
+        rename = "published_at",
+        default,
+        deserialize_with = "deserialize_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    released_at: Option<DateTime<Utc>>,
     assets: Vec<Asset>,
 }
 
 impl Details {
-    pub fn asset(&self, name: &str) -> Result<&Asset> {
-        self.assets
-            .iter()
-            .find(|asset| name == asset.name())
-            .ok_or_else(|| anyhow!("Asset not found"))
+    /// Time when the release was published, if given in the version json.
+    pub fn released_at(&self) -> Option<DateTime<Utc>> {
+        self.released_at
+    }
+
+    /// Select the asset for given target, in the form of `<os>-<arch>`, e.g. `linux-x86_64`.
+    ///
+    /// The names of os and arch are the same as `std::env::consts::{OS, ARCH}`. The platform of an
+    /// asset is parsed from its name, e.g. `MAA-v4.26.1-win-x64.zip` is for `windows-x86_64`.
+    /// An asset for the exact arch is preferred, otherwise a universal one is selected.
+    pub fn select_asset(&self, target: &str) -> Option<&Asset> {
+        let (os, arch) = target.split_once('-')?;
+        let candidates = || {
+            self.assets.iter().filter_map(move |asset| {
+                let (asset_os, asset_arch) = asset_platform(asset.name())?;
+                (asset_os == os).then_some((asset, asset_arch))
+            })
+        };
+
+        candidates()
+            .find(|(_, asset_arch)| *asset_arch == Some(arch))
+            .or_else(|| candidates().find(|(_, asset_arch)| asset_arch.is_none()))
+            .map(|(asset, _)| asset)
+    }
+
+    /// Select the asset for the current platform.
+    pub fn select_for_current(&self) -> Result<&Asset> {
+        self.select_asset(&format!("{OS}-{ARCH}"))
+            .ok_or_else(|| anyhow!("No MaaCore asset for platform: {OS}-{ARCH}"))
     }
 }
 
+/// Parse the os and arch of an asset from its name.
+///
+/// The arch is `None` if the asset is a universal binary.
+fn asset_platform(name: &str) -> Option<(&'static str, Option<&'static str>)> {
+    let stem = name
+        .strip_suffix(".tar.gz")
+        .or_else(|| name.strip_suffix(".zip"))
+        .unwrap_or(name);
+    let tokens = || stem.split('-');
+
+    let os = tokens().find_map(|token| match token {
+        "linux" => Some("linux"),
+        "win" | "windows" => Some("windows"),
+        "macos" => Some("macos"),
+        _ => None,
+    })?;
+    let arch = tokens().find_map(|token| match token {
+        "x86_64" | "x64" | "amd64" => Some(Some("x86_64")),
+        "aarch64" | "arm64" => Some(Some("aarch64")),
+        "universal" => Some(None),
+        _ => None,
+    })?;
+
+    Some((os, arch))
+}
+
 #[cfg_attr(test, derive(Debug, PartialEq))]
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct Asset {
     name: String,
     size: u64,
@@ -235,34 +348,87 @@ pub struct Asset {
     mirrors: Vec<String>,
 }
 
-impl Asset {
-    pub fn name(&self) -> &str {
+impl DownloadableAsset for Asset {
+    fn name(&self) -> &str {
         &self.name
     }
 
-    pub fn size(&self) -> u64 {
+    fn size(&self) -> u64 {
         self.size
     }
 
-    pub fn download_links(&self) -> Vec<String> {
-        let mut links = self.mirrors.clone();
-        links.insert(0, self.browser_download_url.clone());
-        links
+    fn urls(&self) -> Vec<&str> {
+        self.download_candidates(MirrorPolicy::SourceFirst)
+            .collect()
+    }
+
+    /// Assets of MaaCore have no checksum.
+    fn checksum(&self) -> Option<&str> {
+        None
     }
 }
 
-pub fn download<'p>(
-    path: Cow<'p, Path>,
-    size: u64,
-    links: Vec<String>,
-    config: &Config,
-) -> Result<Archive<'p>> {
-    if check_file_exists(&path, size) {
+impl Asset {
+    /// Links to download the asset from, in the order given by `policy`.
+    pub fn download_candidates(&self, policy: MirrorPolicy) -> impl Iterator<Item = &str> {
+        let source = self.browser_download_url.as_str();
+        let (first, last) = match policy {
+            MirrorPolicy::MirrorsFirst => (None, Some(source)),
+            MirrorPolicy::SourceFirst => (Some(source), None),
+        };
+        first
+            .into_iter()
+            .chain(self.mirrors.iter().map(String::as_str))
+            .chain(last)
+    }
+}
+
+/// Order of the links to download an asset from.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum MirrorPolicy {
+    /// Try mirrors in order, and fall back to the source url at last
+    MirrorsFirst,
+    /// Try the source url first, then mirrors in order
+    SourceFirst,
+}
+
+impl MirrorPolicy {
+    /// Policy used by the downloader with given speed test duration.
+    ///
+    /// Without speed test, the first link is used, which should be the source url. Otherwise,
+    /// mirrors are tried first, so a mirror is used if none of the links passed the test.
+    pub fn for_test_time(test_time: u64) -> Self {
+        if test_time == 0 {
+            MirrorPolicy::SourceFirst
+        } else {
+            MirrorPolicy::MirrorsFirst
+        }
+    }
+}
+
+/// Free space to keep besides the archive and the extracted files.
+const FREE_SPACE_MARGIN: u64 = 64 * 1024 * 1024;
+
+pub fn download<'p>(path: Cow<'p, Path>, asset: &Asset, config: &Config) -> Result<Archive<'p>> {
+    if path.is_file() && asset.verify_size(&path).is_ok() {
         println!("Already downloaded, skip downloading");
         return Archive::new(path);
     }
 
-    let client = reqwest::Client::builder()
+    // The archive is extracted to the data directory, which is usually on the same file system
+    let required = asset.size() * 2 + FREE_SPACE_MARGIN;
+    if !dirs::check_free_space(&path, required)? {
+        bail!(
+            "Not enough free space to download and install MaaCore, {} required",
+            human_size(required)
+        );
+    }
+
+    debug!("Download links of {}: {:?}", asset.name(), asset.urls());
+    let pins = CertPins::new(config.cert_pins());
+    let client = pins
+        .configure(reqwest::Client::builder())
         .connect_timeout(Duration::from_secs(3))
         .build()
         .context("Failed to build reqwest client")?;
@@ -270,11 +436,14 @@ pub fn download<'p>(
         .context("Failed to create tokio runtime")?
         .block_on(download_mirrors(
             &client,
-            links,
+            &pins,
+            asset
+                .download_candidates(MirrorPolicy::for_test_time(config.test_time()))
+                .map(str::to_owned)
+                .collect(),
             &path,
-            size,
             config.test_time(),
-            None,
+            asset,
         ))
         .context("Failed to download asset")?;
 
@@ -286,6 +455,7 @@ mod tests {
     use serde_json;
 
     use super::*;
+    use crate::installer::verify::SizeError;
 
     #[test]
     fn deserialize_version_json() {
@@ -377,26 +547,38 @@ mod tests {
         );
 
         let details = version_json.details();
-        let asset_name = name(version_json.version()).unwrap();
-        let asset = details.asset(&asset_name).unwrap();
+        assert_eq!(
+            details.released_at(),
+            Some("2023-11-02T16:50:51Z".parse().unwrap())
+        );
+        let asset = details.select_for_current().unwrap();
 
         // Test asset name, size and download links
         match OS {
             "macos" => {
                 assert_eq!(asset.name(), "MAA-v4.26.1-macos-runtime-universal.zip");
                 assert_eq!(asset.size(), 164012486);
-                assert_eq!(asset.download_links().len(), 4);
+                assert_eq!(
+                    asset.download_candidates(MirrorPolicy::SourceFirst).count(),
+                    4
+                );
             }
             "linux" => match ARCH {
                 "x86_64" => {
                     assert_eq!(asset.name(), "MAA-v4.26.1-linux-x86_64.tar.gz");
                     assert_eq!(asset.size(), 155241185);
-                    assert_eq!(asset.download_links().len(), 4);
+                    assert_eq!(
+                        asset.download_candidates(MirrorPolicy::SourceFirst).count(),
+                        4
+                    );
                 }
                 "aarch64" => {
                     assert_eq!(asset.name(), "MAA-v4.26.1-linux-aarch64.tar.gz");
                     assert_eq!(asset.size(), 152067668);
-                    assert_eq!(asset.download_links().len(), 4);
+                    assert_eq!(
+                        asset.download_candidates(MirrorPolicy::SourceFirst).count(),
+                        4
+                    );
                 }
                 _ => (),
             },
@@ -404,12 +586,18 @@ mod tests {
                 "x86_64" => {
                     assert_eq!(asset.name(), "MAA-v4.26.1-win-x64.zip");
                     assert_eq!(asset.size(), 150092421);
-                    assert_eq!(asset.download_links().len(), 4);
+                    assert_eq!(
+                        asset.download_candidates(MirrorPolicy::SourceFirst).count(),
+                        4
+                    );
                 }
                 "aarch64" => {
                     assert_eq!(asset.name(), "MAA-v4.26.1-win-arm64.zip");
                     assert_eq!(asset.size(), 148806502);
-                    assert_eq!(asset.download_links().len(), 4);
+                    assert_eq!(
+                        asset.download_candidates(MirrorPolicy::SourceFirst).count(),
+                        4
+                    );
                 }
                 _ => (),
             },
@@ -417,6 +605,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn released_at() {
+        let parse = |json: &str| serde_json::from_str::<Details>(json).unwrap();
+
+        let details = parse(r#"{ "published_at": "2023-11-02T16:50:51Z", "assets": [] }"#);
+        assert_eq!(
+            details.released_at(),
+            Some("2023-11-02T16:50:51Z".parse().unwrap())
+        );
+        assert_eq!(
+            serde_json::to_value(&details).unwrap(),
+            serde_json::json!({ "published_at": "2023-11-02T16:50:51Z", "assets": [] })
+        );
+
+        // Older manifests without the field
+        let details = parse(r#"{ "assets": [] }"#);
+        assert_eq!(details.released_at(), None);
+        assert_eq!(
+            serde_json::to_value(&details).unwrap(),
+            serde_json::json!({ "assets": [] })
+        );
+
+        // Invalid timestamps are ignored
+        assert_eq!(
+            parse(r#"{ "published_at": "yesterday", "assets": [] }"#).released_at(),
+            None
+        );
+        assert_eq!(
+            parse(r#"{ "published_at": null, "assets": [] }"#).released_at(),
+            None
+        );
+    }
+
+    #[test]
+    fn select_asset() {
+        let asset = |name: &str| Asset {
+            name: name.to_owned(),
+            size: 0,
+            browser_download_url: String::new(),
+            mirrors: Vec::new(),
+        };
+        let details = Details {
+            released_at: None,
+            assets: [
+                "MAA-v5.0.0-beta.1-linux-aarch64.tar.gz",
+                "MAA-v5.0.0-beta.1-linux-x86_64.tar.gz",
+                "MAA-v5.0.0-beta.1-win-arm64.zip",
+                "MAA-v5.0.0-beta.1-win-x64.zip",
+                "MAA-v5.0.0-beta.1-macos-runtime-universal.zip",
+                "MAA-v5.0.0-beta.1-android-arm64.zip",
+            ]
+            .into_iter()
+            .map(asset)
+            .collect(),
+        };
+        let select = |target| details.select_asset(target).map(Asset::name);
+
+        assert_eq!(
+            select("linux-x86_64"),
+            Some("MAA-v5.0.0-beta.1-linux-x86_64.tar.gz")
+        );
+        assert_eq!(
+            select("linux-aarch64"),
+            Some("MAA-v5.0.0-beta.1-linux-aarch64.tar.gz")
+        );
+        assert_eq!(
+            select("windows-x86_64"),
+            Some("MAA-v5.0.0-beta.1-win-x64.zip")
+        );
+        assert_eq!(
+            select("windows-aarch64"),
+            Some("MAA-v5.0.0-beta.1-win-arm64.zip")
+        );
+        // The universal binary is selected for all architectures of macOS
+        assert_eq!(
+            select("macos-x86_64"),
+            Some("MAA-v5.0.0-beta.1-macos-runtime-universal.zip")
+        );
+        assert_eq!(
+            select("macos-aarch64"),
+            Some("MAA-v5.0.0-beta.1-macos-runtime-universal.zip")
+        );
+        assert_eq!(select("linux-riscv64"), None);
+        assert_eq!(select("freebsd-x86_64"), None);
+        assert_eq!(select("linux"), None);
+
+        assert_eq!(asset_platform("MAA-v4.26.1.zip"), None);
+    }
+
+    #[test]
+    fn download_candidates() {
+        let asset = |mirrors: &[&str]| Asset {
+            name: "MAA-v4.26.1-linux-x86_64.tar.gz".to_owned(),
+            size: 0,
+            browser_download_url: "https://github.com/MAA.tar.gz".to_owned(),
+            mirrors: mirrors.iter().map(|&m| m.to_owned()).collect(),
+        };
+        fn candidates(asset: &Asset, policy: MirrorPolicy) -> Vec<&str> {
+            asset.download_candidates(policy).collect()
+        }
+
+        let with_mirrors = asset(&["https://a.com/MAA.tar.gz", "https://b.com/MAA.tar.gz"]);
+        assert_eq!(candidates(&with_mirrors, MirrorPolicy::MirrorsFirst), [
+            "https://a.com/MAA.tar.gz",
+            "https://b.com/MAA.tar.gz",
+            "https://github.com/MAA.tar.gz",
+        ]);
+        assert_eq!(candidates(&with_mirrors, MirrorPolicy::SourceFirst), [
+            "https://github.com/MAA.tar.gz",
+            "https://a.com/MAA.tar.gz",
+            "https://b.com/MAA.tar.gz",
+        ]);
+
+        let without_mirrors = asset(&[]);
+        for policy in [MirrorPolicy::MirrorsFirst, MirrorPolicy::SourceFirst] {
+            assert_eq!(candidates(&without_mirrors, policy), [
+                "https://github.com/MAA.tar.gz"
+            ]);
+        }
+
+        // Links of the trait are in the order of source first
+        assert_eq!(with_mirrors.urls(), [
+            "https://github.com/MAA.tar.gz",
+            "https://a.com/MAA.tar.gz",
+            "https://b.com/MAA.tar.gz",
+        ]);
+        assert_eq!(with_mirrors.name(), "MAA-v4.26.1-linux-x86_64.tar.gz");
+        assert_eq!(with_mirrors.checksum(), None);
+
+        assert_eq!(MirrorPolicy::for_test_time(0), MirrorPolicy::SourceFirst);
+        assert_eq!(MirrorPolicy::for_test_time(3), MirrorPolicy::MirrorsFirst);
+    }
+
+    #[test]
+    fn verify_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("MAA-v4.26.1-linux-x86_64.tar.gz");
+        std::fs::write(&path, b"content").unwrap();
+
+        let asset = |size| Asset {
+            name: "MAA-v4.26.1-linux-x86_64.tar.gz".to_owned(),
+            size,
+            browser_download_url: String::new(),
+            mirrors: Vec::new(),
+        };
+
+        asset(7).verify_size(&path).unwrap();
+        // Too small
+        assert!(matches!(
+            asset(8).verify_size(&path),
+            Err(SizeError::Mismatch {
+                expected: 8,
+                actual: 7
+            })
+        ));
+        // Too large
+        assert!(matches!(
+            asset(6).verify_size(&path),
+            Err(SizeError::Mismatch {
+                expected: 6,
+                actual: 7
+            })
+        ));
+    }
+
     #[test]
     fn test_extract_mapper() {
         let config = Components::default();
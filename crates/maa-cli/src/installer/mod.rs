@@ -1,8 +1,14 @@
 #[cfg(feature = "__installer")]
+mod asset;
+#[cfg(feature = "__installer")]
 mod download;
 #[cfg(feature = "__installer")]
 mod extract;
 #[cfg(feature = "__installer")]
+mod pin;
+#[cfg(feature = "__installer")]
+mod verify;
+#[cfg(feature = "__installer")]
 mod version_json;
 
 #[cfg(feature = "cli_installer")]
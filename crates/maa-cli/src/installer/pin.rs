@@ -0,0 +1,156 @@
+use anyhow::Result;
+use digest::Digest;
+use reqwest::{tls::TlsInfo, Client, ClientBuilder, Response};
+use sha2::Sha256;
+
+#[derive(Debug, PartialEq)]
+pub enum PinError {
+    /// The server did not present a certificate, e.g. the url is not https
+    Missing,
+    /// The fingerprint of the presented certificate is not pinned
+    Mismatch(String),
+}
+
+impl std::fmt::Display for PinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PinError::Missing => write!(f, "Server did not present a certificate to verify"),
+            PinError::Mismatch(fingerprint) => write!(
+                f,
+                "Certificate with SHA-256 fingerprint {fingerprint} does not match any pin"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PinError {}
+
+/// Source of the DER encoded certificate presented by a server.
+pub trait PeerCertificate {
+    fn peer_certificate(&self) -> Option<&[u8]>;
+}
+
+impl PeerCertificate for Response {
+    fn peer_certificate(&self) -> Option<&[u8]> {
+        self.extensions()
+            .get::<TlsInfo>()
+            .and_then(TlsInfo::peer_certificate)
+    }
+}
+
+/// Pinned SHA-256 fingerprints of certificates allowed to be presented by update servers.
+///
+/// Pinning is disabled when there is no pin, and every certificate trusted by the system
+/// is accepted.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Default, Clone)]
+pub struct CertPins(Vec<String>);
+
+impl CertPins {
+    /// Create pins from hex encoded fingerprints.
+    ///
+    /// Fingerprints are case insensitive and may be separated by colons, which is the format
+    /// printed by `openssl x509 -fingerprint -sha256`.
+    pub fn new(pins: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self(
+            pins.into_iter()
+                .map(|pin| pin.as_ref().replace(':', "").to_ascii_lowercase())
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Enable collecting certificates of servers in the client if pinning is enabled.
+    pub fn configure(&self, builder: ClientBuilder) -> ClientBuilder {
+        builder.tls_info(!self.is_empty())
+    }
+
+    /// Verify the certificate presented by the server against the pins.
+    pub fn verify(&self, peer: &impl PeerCertificate) -> Result<(), PinError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let cert = peer.peer_certificate().ok_or(PinError::Missing)?;
+        let fingerprint = format!("{:x}", Sha256::digest(cert));
+        if self.0.contains(&fingerprint) {
+            Ok(())
+        } else {
+            Err(PinError::Mismatch(fingerprint))
+        }
+    }
+}
+
+/// A client which verifies the certificate of every response against the pins.
+pub struct Pinned<'a> {
+    pub client: &'a Client,
+    pub pins: &'a CertPins,
+}
+
+#[async_trait::async_trait]
+impl super::version_json::Transport for Pinned<'_> {
+    async fn get_text(&self, url: &str) -> Result<String> {
+        let resp = self.client.get(url).send().await?;
+        self.pins.verify(&resp)?;
+        Ok(resp.error_for_status()?.text().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MockPeer(Option<&'static [u8]>);
+
+    impl PeerCertificate for MockPeer {
+        fn peer_certificate(&self) -> Option<&[u8]> {
+            self.0
+        }
+    }
+
+    const CERT: &[u8] = b"certificate";
+    const OTHER: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+    fn fingerprint() -> String {
+        format!("{:x}", Sha256::digest(CERT))
+    }
+
+    #[test]
+    fn new() {
+        let colons = fingerprint()
+            .to_ascii_uppercase()
+            .as_bytes()
+            .chunks(2)
+            .map(|c| std::str::from_utf8(c).unwrap())
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(CertPins::new([colons]).0, vec![fingerprint()]);
+    }
+
+    #[test]
+    fn disabled() {
+        let pins = CertPins::default();
+        assert!(pins.is_empty());
+        assert_eq!(pins.verify(&MockPeer(None)), Ok(()));
+        assert_eq!(pins.verify(&MockPeer(Some(CERT))), Ok(()));
+    }
+
+    #[test]
+    fn pin_match() {
+        let pins = CertPins::new([OTHER, &fingerprint()]);
+        assert_eq!(pins.verify(&MockPeer(Some(CERT))), Ok(()));
+    }
+
+    #[test]
+    fn pin_mismatch() {
+        let pins = CertPins::new([OTHER]);
+        assert_eq!(
+            pins.verify(&MockPeer(Some(CERT))),
+            Err(PinError::Mismatch(fingerprint()))
+        );
+        assert_eq!(pins.verify(&MockPeer(None)), Err(PinError::Missing));
+    }
+}
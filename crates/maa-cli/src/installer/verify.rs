@@ -0,0 +1,179 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use digest::Digest;
+use sha2::Sha256;
+
+#[derive(Debug)]
+pub enum ChecksumError {
+    Io(io::Error),
+    /// The checksum of the file does not match the expected one
+    Mismatch {
+        expected: String,
+        actual: String,
+    },
+}
+
+impl From<io::Error> for ChecksumError {
+    fn from(e: io::Error) -> Self {
+        ChecksumError::Io(e)
+    }
+}
+
+impl std::fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ChecksumError::Io(e) => e.fmt(f),
+            ChecksumError::Mismatch { expected, actual } => write!(
+                f,
+                "Checksum mismatch: expected sha256 {expected}, got {actual}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ChecksumError {}
+
+#[derive(Debug)]
+pub enum SizeError {
+    Io(io::Error),
+    /// The size of the file does not match the expected one
+    Mismatch {
+        expected: u64,
+        actual: u64,
+    },
+}
+
+impl From<io::Error> for SizeError {
+    fn from(e: io::Error) -> Self {
+        SizeError::Io(e)
+    }
+}
+
+impl std::fmt::Display for SizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SizeError::Io(e) => e.fmt(f),
+            SizeError::Mismatch { expected, actual } => write!(
+                f,
+                "Size mismatch: expected {expected} bytes, got {actual} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SizeError {}
+
+/// Verify the size of the file at `path`.
+///
+/// This is much cheaper than verifying the checksum, so it can be used to detect truncated
+/// downloads early.
+pub fn verify_size(path: &Path, expected: u64) -> Result<(), SizeError> {
+    let actual = path.metadata()?.len();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(SizeError::Mismatch { expected, actual })
+    }
+}
+
+/// Normalize a sha256 checksum, which is case insensitive and may have a `sha256:` prefix.
+pub fn normalize_sha256(checksum: &str) -> String {
+    let checksum = checksum.trim();
+    let checksum = checksum
+        .get(..7)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("sha256:"))
+        .map_or(checksum, |_| &checksum[7..]);
+    checksum.to_ascii_lowercase()
+}
+
+/// Verify the sha256 checksum of the file at `path`.
+///
+/// The file is read in chunks, so it is not loaded into memory at once.
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<(), ChecksumError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    let actual = format!("{:x}", hasher.finalize());
+    let expected = normalize_sha256(expected);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(ChecksumError::Mismatch { expected, actual })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sha256 of "content"
+    const CONTENT_SHA256: &str = "ed7002b439e9ac845f22357d822bac1444730fbdb6016d3ec9432297b9ec9f73";
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_sha256("ABCdef"), "abcdef");
+        assert_eq!(normalize_sha256(" sha256:ABCdef\n"), "abcdef");
+        assert_eq!(normalize_sha256("SHA256:abcdef"), "abcdef");
+        assert_eq!(normalize_sha256("sha25"), "sha25");
+    }
+
+    #[test]
+    fn size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asset.zip");
+        std::fs::write(&path, b"content").unwrap();
+
+        verify_size(&path, 7).unwrap();
+        assert!(matches!(
+            verify_size(&path, 8),
+            Err(SizeError::Mismatch {
+                expected: 8,
+                actual: 7
+            })
+        ));
+        assert_eq!(
+            verify_size(&path, 6).unwrap_err().to_string(),
+            "Size mismatch: expected 6 bytes, got 7 bytes"
+        );
+        assert!(matches!(
+            verify_size(&dir.path().join("not_exist"), 7),
+            Err(SizeError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn sha256() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asset.zip");
+        std::fs::write(&path, b"content").unwrap();
+
+        verify_sha256(&path, CONTENT_SHA256).unwrap();
+        verify_sha256(&path, &format!("sha256:{}", CONTENT_SHA256.to_uppercase())).unwrap();
+
+        let other = "0".repeat(64);
+        match verify_sha256(&path, &other) {
+            Err(ChecksumError::Mismatch { expected, actual }) => {
+                assert_eq!(expected, other);
+                assert_eq!(actual, CONTENT_SHA256);
+            }
+            _ => panic!("expected checksum mismatch"),
+        }
+
+        assert!(matches!(
+            verify_sha256(&dir.path().join("not_exist"), CONTENT_SHA256),
+            Err(ChecksumError::Io(_))
+        ));
+    }
+}
@@ -1,5 +1,29 @@
-use semver::Version;
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use semver::{Version, VersionReq};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+/// Transport used to fetch the raw content of a version json.
+///
+/// This is implemented for [`reqwest::Client`], and can be replaced by other implementations
+/// (e.g. in tests) to fetch the version json without network access.
+#[async_trait::async_trait]
+pub trait Transport: Sync {
+    async fn get_text(&self, url: &str) -> Result<String>;
+}
+
+#[async_trait::async_trait]
+impl Transport for reqwest::Client {
+    async fn get_text(&self, url: &str) -> Result<String> {
+        Ok(self
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
 
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub struct VersionJSON<D> {
@@ -33,17 +57,52 @@ impl<'de, A: Deserialize<'de>> Deserialize<'de> for VersionJSON<A> {
     }
 }
 
+impl<D: DeserializeOwned> VersionJSON<D> {
+    /// Fetch and parse the version json from given url without blocking.
+    pub async fn fetch(transport: &impl Transport, url: &str) -> Result<Self> {
+        let text = transport
+            .get_text(url)
+            .await
+            .with_context(|| format!("Failed to fetch version info from {}", url))?;
+        serde_json::from_str(&text).context("Failed to parse version info")
+    }
+}
+
 impl<D> VersionJSON<D> {
     pub fn version(&self) -> &Version {
         &self.version
     }
 
+    /// Check if the version is newer than the given version.
+    ///
+    /// Versions are compared by precedence defined by semver, so a prerelease is older than the
+    /// release of the same version, and build metadata is ignored.
+    pub fn is_newer_than(&self, other: &Version) -> bool {
+        self.version.cmp_precedence(other).is_gt()
+    }
+
+    /// Check if the version is a prerelease, e.g. `5.0.0-beta.1`.
+    pub fn is_prerelease(&self) -> bool {
+        !self.version.pre.is_empty()
+    }
+
     pub fn can_update(&self, name: &str, current_version: &Version) -> Result<bool, semver::Error> {
         let version = self.version();
-        if version > current_version {
+        let newer = self.is_newer_than(current_version)
+            // Nightly builds of the same version are only distinguished by build metadata
+            || (version.cmp_precedence(current_version).is_eq()
+                && version.build > current_version.build);
+        if newer {
             println!(
-                "Found newer {} version: v{} (current: v{})",
-                name, version, current_version
+                "Found newer {} {}version: v{} (current: v{})",
+                name,
+                if self.is_prerelease() {
+                    "prerelease "
+                } else {
+                    ""
+                },
+                version,
+                current_version
             );
             Ok(true)
         } else {
@@ -57,10 +116,75 @@ impl<D> VersionJSON<D> {
     }
 }
 
+/// Deserialize an optional RFC 3339 timestamp, e.g. `published_at` of a release.
+///
+/// An invalid timestamp is treated as missing, so a manifest is not rejected only because of it.
+pub fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?
+        .and_then(|timestamp| DateTime::parse_from_rfc3339(&timestamp).ok())
+        .map(|timestamp| timestamp.with_timezone(&Utc)))
+}
+
+/// Find the latest version satisfying the requirement.
+///
+/// Versions are compared by precedence, so build metadata is ignored. As defined by semver,
+/// a prerelease is only matched if the requirement has a prerelease of the same
+/// `major.minor.patch`, e.g. `>=5.5.0-beta.1` matches `5.5.0-beta.2` but not `5.6.0-beta.1`.
+#[cfg_attr(
+    not(test),
+    expect(
+        dead_code,
+        reason = "Version requirements of installation are not supported yet"
+    )
+)]
+pub fn latest_matching(
+    versions: impl IntoIterator<Item = Version>,
+    req: &VersionReq,
+) -> Option<Version> {
+    versions
+        .into_iter()
+        .filter(|version| req.matches(version))
+        .max_by(|a, b| a.cmp_precedence(b))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_latest_matching() {
+        fn latest(versions: &[&str], req: &str) -> Option<String> {
+            latest_matching(
+                versions.iter().map(|v| Version::parse(v).unwrap()),
+                &VersionReq::parse(req).unwrap(),
+            )
+            .map(|v| v.to_string())
+        }
+
+        let versions = [
+            "5.4.2",
+            "5.5.0",
+            "5.5.3",
+            "5.6.0-beta.1",
+            "5.5.4-beta.2",
+            "6.0.0",
+        ];
+        assert_eq!(latest(&versions, "^5.5"), Some("5.5.3".to_owned()));
+        assert_eq!(latest(&versions, "~5.4"), Some("5.4.2".to_owned()));
+        assert_eq!(latest(&versions, "*"), Some("6.0.0".to_owned()));
+        assert_eq!(latest(&versions, "^7"), None);
+        assert_eq!(latest(&[], "*"), None);
+
+        // Prereleases are excluded unless the requirement allows them explicitly
+        assert_eq!(
+            latest(&versions, ">=5.5.4-beta.1, <5.6"),
+            Some("5.5.4-beta.2".to_owned())
+        );
+        assert_eq!(latest(&["5.6.0-beta.1"], ">=5.5.4-beta.1"), None);
+    }
+
     #[test]
     fn test_can_update() {
         fn can_update(remote: &str, current: &str, expected: bool) {
@@ -100,4 +224,91 @@ mod tests {
         can_update("0.1.0-beta.2.alpha.1", "0.1.0-beta.1.alpha.2", true);
         can_update("0.1.0-alpha.1+sha.1da7b3d", "0.1.0-alpha.1", true);
     }
+
+    #[test]
+    fn is_newer_than() {
+        fn version_json(version: &str) -> VersionJSON<()> {
+            VersionJSON {
+                version: Version::parse(version).unwrap(),
+                details: (),
+            }
+        }
+
+        fn is_newer_than(remote: &str, current: &str) -> bool {
+            version_json(remote).is_newer_than(&Version::parse(current).unwrap())
+        }
+
+        // Stable
+        assert!(is_newer_than("5.1.0", "5.0.9"));
+        assert!(!is_newer_than("5.1.0", "5.1.0"));
+        assert!(!is_newer_than("5.1.0", "5.1.1"));
+
+        // Stable vs. prerelease
+        assert!(is_newer_than("5.1.0", "5.1.0-beta.1"));
+        assert!(!is_newer_than("5.1.0-beta.1", "5.1.0"));
+        assert!(is_newer_than("5.1.0-beta.1", "5.0.0"));
+
+        // Prerelease
+        assert!(is_newer_than("5.1.0-beta.2", "5.1.0-beta.1"));
+        assert!(is_newer_than("5.1.0-beta.1", "5.1.0-alpha.3"));
+        assert!(is_newer_than("5.1.0-beta.10", "5.1.0-beta.9"));
+
+        // Build metadata only
+        assert!(!is_newer_than("5.1.0+sha.1da7b3d", "5.1.0"));
+        assert!(!is_newer_than("5.1.0-alpha.1+sha.2", "5.1.0-alpha.1+sha.1"));
+
+        assert!(!version_json("5.1.0").is_prerelease());
+        assert!(!version_json("5.1.0+sha.1da7b3d").is_prerelease());
+        assert!(version_json("5.1.0-beta.1").is_prerelease());
+    }
+
+    #[test]
+    fn fetch() {
+        struct MockTransport;
+
+        #[async_trait::async_trait]
+        impl Transport for MockTransport {
+            async fn get_text(&self, url: &str) -> Result<String> {
+                match url {
+                    "https://example.com/stable.json" => {
+                        Ok(r#"{ "version": "v0.1.0", "details": "stable" }"#.to_owned())
+                    }
+                    "https://example.com/invalid.json" => Ok(r#"{ "version": 1 }"#.to_owned()),
+                    _ => anyhow::bail!("not found"),
+                }
+            }
+        }
+
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        let version_json: VersionJSON<String> = rt
+            .block_on(VersionJSON::fetch(
+                &MockTransport,
+                "https://example.com/stable.json",
+            ))
+            .unwrap();
+        assert_eq!(version_json, VersionJSON {
+            version: Version::new(0, 1, 0),
+            details: "stable".to_owned(),
+        });
+
+        assert_eq!(
+            rt.block_on(VersionJSON::<String>::fetch(
+                &MockTransport,
+                "https://example.com/invalid.json",
+            ))
+            .unwrap_err()
+            .to_string(),
+            "Failed to parse version info"
+        );
+        assert_eq!(
+            rt.block_on(VersionJSON::<String>::fetch(
+                &MockTransport,
+                "https://example.com/beta.json",
+            ))
+            .unwrap_err()
+            .to_string(),
+            "Failed to fetch version info from https://example.com/beta.json"
+        );
+    }
 }
@@ -42,27 +42,37 @@ fn main() -> Result<()> {
             command::SelfCommand::Update { common } => installer::maa_cli::update(&common)?,
         },
         Command::HotUpdate => installer::resource::update(false)?,
-        Command::Dir { dir } => match dir {
-            Dir::Data => println!("{}", dirs::data().display()),
-            Dir::Library => {
-                println!(
-                    "{}",
-                    dirs::find_library().context("Library not found")?.display()
-                )
+        Command::Dir { dir: None, .. } => {
+            let sizes = dirs::sizes()?;
+            for (name, path, size) in [
+                ("data", dirs::data(), sizes.data),
+                ("cache", dirs::cache(), sizes.cache),
+                ("log", dirs::log(), sizes.log),
+                ("resource", dirs::resource(), sizes.resource),
+                ("copilot", dirs::copilot(), sizes.copilot),
+            ] {
+                println!("{size}\t{name}\t{}", path.display());
             }
-            Dir::Resource => {
-                println!(
-                    "{}",
-                    dirs::find_resource()
-                        .context("Resource not found")?
-                        .display()
-                )
+        }
+        Command::Dir {
+            dir: Some(dir),
+            size,
+        } => {
+            let path = match dir {
+                Dir::Data => dirs::data().into(),
+                Dir::Library => dirs::find_library().context("Library not found")?,
+                Dir::Resource => dirs::find_resource().context("Resource not found")?,
+                Dir::HotUpdate => dirs::hot_update().into(),
+                Dir::Config => dirs::config().into(),
+                Dir::Cache => dirs::cache().into(),
+                Dir::Log => dirs::log().into(),
+            };
+            if size {
+                println!("{}\t{}", dirs::dir_size(&path)?, path.display());
+            } else {
+                println!("{}", path.display());
             }
-            Dir::HotUpdate => println!("{}", dirs::hot_update().display()),
-            Dir::Config => println!("{}", dirs::config().display()),
-            Dir::Cache => println!("{}", dirs::cache().display()),
-            Dir::Log => println!("{}", dirs::log().display()),
-        },
+        }
         Command::Version { component } => match component {
             Component::All => {
                 println!("maa-cli v{}", env!("MAA_VERSION"));
@@ -83,6 +93,7 @@ fn main() -> Result<()> {
         Command::Copilot { params, common } => run::run_preset(params, common)?,
         Command::SSSCopilot { params, common } => run::run_preset(params, common)?,
         Command::Reclamation { params, common } => run::run_preset(params, common)?,
+        Command::Raw { params, common } => run::run_raw(params, common)?,
         Command::Convert {
             input,
             output,
@@ -134,3 +145,19 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Acquire the lock of data directories and migrate the legacy directory layout under it
+///
+/// Commands which use the data directories should hold the returned guard until they finish,
+/// so the migration never races with another process or runs for unrelated commands.
+fn acquire_lock() -> Result<dirs::LockGuard> {
+    let lock = dirs::acquire_lock().context("Failed to acquire lock")?;
+
+    match dirs::migrate_legacy_layout() {
+        Ok(true) => ::log::info!("Migrated files from the legacy directory layout"),
+        Ok(false) => {}
+        Err(err) => ::log::warn!("Failed to migrate the legacy directory layout: {err}"),
+    }
+
+    Ok(lock)
+}
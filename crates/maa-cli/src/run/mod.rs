@@ -11,13 +11,14 @@ pub mod preset;
 use std::{
     path::Path,
     sync::{atomic, Arc},
+    time::Duration,
 };
 
 use anyhow::{bail, Context, Result};
 use clap::Args;
 use log::{debug, warn};
 use maa_dirs::{self as dirs, Ensure, MAA_CORE_LIB};
-use maa_sys::Assistant;
+use maa_sys::{Assistant, LoadGuard};
 use signal_hook::consts::TERM_SIGNALS;
 
 use crate::{
@@ -111,10 +112,26 @@ fn find_profile(root: impl AsRef<Path>, profile: Option<&str>) -> Result<AsstCon
     }
 }
 
-fn run_core<F>(f: F, args: CommonArgs) -> Result<()>
+/// Keys of task params whose values are masked in logs and error messages
+const SENSITIVE_KEYS: &[&str] = &["token", "password", "address", "account_name"];
+
+/// Maximum time to wait for each attempt to connect to the device
+///
+/// The assistant is created without an observable callback, so a failed connection is only
+/// detected when it is still not `connected` after this time.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Number of retries when connecting to the device
+const CONNECT_RETRIES: u32 = 2;
+/// Wait before the first retry of connection, doubled for each following retry
+const CONNECT_BACKOFF: Duration = Duration::from_secs(1);
+
+fn run_core<F>(f: F, raw_tasks: Vec<preset::RawTask>, args: CommonArgs) -> Result<()>
 where
     F: FnOnce(&AsstConfig) -> Result<TaskConfig>,
 {
+    // Hold the lock during the whole run, so MaaCore and resources are not changed under us
+    let _lock = crate::acquire_lock()?;
+
     // Auto update hot update resource
     resource::update(true)?;
 
@@ -130,7 +147,8 @@ where
     }
 
     // Load and setup MaaCore
-    load_core().context("Failed to load MaaCore!")?;
+    // Declared before the assistant, so MaaCore is unloaded after the assistant is dropped
+    let _core = load_core().context("Failed to load MaaCore!")?;
     setup_core(&asst_config)?;
 
     // Register signal handlers
@@ -150,16 +168,16 @@ where
     let mut task_summary = (!args.no_summary).then(summary::Summary::new);
     for task in task_config.tasks {
         let task_type = task.task_type;
-        let params = serde_json::to_string_pretty(&task.params)?;
+        let redacted = task.params.redacted(SENSITIVE_KEYS);
         debug!(
-            "Adding task [{}] with params: {params}",
+            "Adding task [{}] with params: {redacted}",
             task.name_or_default(),
         );
         let id = asst
-            .append_task(task_type, params.as_str())
+            .append_task_value(task_type, &task.params)
             .with_context(|| {
                 format!(
-                    "Failed to add task {} with params: {params}",
+                    "Failed to add task {} with params: {redacted}",
                     task.name_or_default(),
                 )
             })?;
@@ -168,6 +186,16 @@ where
             s.insert(id, task.name, task_type);
         }
     }
+    for task in raw_tasks {
+        debug!("Adding raw task [{}]", task.task_type);
+        let id = asst
+            .append_task(task.task_type, task.params.as_str())
+            .with_context(|| format!("Failed to add raw task {}", task.task_type))?;
+
+        if let Some(s) = task_summary.as_mut() {
+            s.insert(id, None, task.task_type);
+        }
+    }
     if let Some(s) = task_summary {
         summary::init(s);
     }
@@ -196,7 +224,14 @@ where
         }
 
         // Connect to game or emulator
-        asst.async_connect(adb_path, address.as_ref(), config, true)?;
+        asst.reconnect(
+            adb_path,
+            address.as_ref(),
+            config,
+            CONNECT_TIMEOUT,
+            CONNECT_RETRIES,
+            CONNECT_BACKOFF,
+        )?;
 
         asst.start()?;
 
@@ -228,7 +263,14 @@ pub fn run<F>(f: F, args: CommonArgs) -> Result<()>
 where
     F: FnOnce(&AsstConfig) -> Result<TaskConfig>,
 {
-    let ret = run_core(f, args);
+    run_with_raw(f, Vec::new(), args)
+}
+
+fn run_with_raw<F>(f: F, raw_tasks: Vec<preset::RawTask>, args: CommonArgs) -> Result<()>
+where
+    F: FnOnce(&AsstConfig) -> Result<TaskConfig>,
+{
+    let ret = run_core(f, raw_tasks, args);
 
     summary::display();
 
@@ -245,6 +287,12 @@ pub fn run_preset(params: impl preset::IntoTaskConfig, args: CommonArgs) -> Resu
     run(|config| params.into_task_config(config), args)
 }
 
+/// Run a task with params passed to MaaCore without any processing
+pub fn run_raw(params: preset::RawTaskParams, args: CommonArgs) -> Result<()> {
+    let task = preset::RawTask::try_from(params)?;
+    run_with_raw(|_| Ok(TaskConfig::new()), vec![task], args)
+}
+
 pub fn run_custom(path: impl AsRef<Path>, args: CommonArgs) -> Result<()> {
     run(
         |_| {
@@ -261,22 +309,23 @@ pub fn run_custom(path: impl AsRef<Path>, args: CommonArgs) -> Result<()> {
 }
 
 pub fn core_version() -> Result<String> {
-    load_core()?;
+    let _core = load_core()?;
 
     let v_str = Assistant::get_version().context("Failed to get MaaCore version!")?;
 
-    maa_sys::binding::unload();
-
     Ok(v_str)
 }
 
-fn load_core() -> Result<()> {
+/// Load MaaCore, which will be unloaded when the returned guard is dropped
+///
+/// Return `None` if MaaCore is already loaded, so the outer guard keeps it loaded.
+fn load_core() -> Result<Option<LoadGuard>> {
     if maa_sys::binding::loaded() {
         debug!("MaaCore already loaded");
-        return Ok(());
+        return Ok(None);
     }
 
-    if let Some(lib_dir) = dirs::find_library() {
+    let guard = if let Some(lib_dir) = dirs::find_library() {
         debug!("Loading MaaCore from: {}", lib_dir.display());
         // Set DLL directory on Windows
         #[cfg(target_os = "windows")]
@@ -290,19 +339,20 @@ fn load_core() -> Result<()> {
                     .context("Failed to set DLL directory!"));
             }
         }
-        maa_sys::binding::load(lib_dir.join(MAA_CORE_LIB))
+        maa_sys::load_guard(lib_dir.join(MAA_CORE_LIB))
     } else {
         debug!("MaaCore not found, trying to load from system library path");
-        maa_sys::binding::load(MAA_CORE_LIB)
+        maa_sys::load_guard(MAA_CORE_LIB)
     }
     .context("Failed to load MaaCore!")?;
 
-    Ok(())
+    Ok(Some(guard))
 }
 
 fn setup_core(config: &AsstConfig) -> Result<()> {
     debug!("Setting user directory: {}", dirs::state().display());
-    Assistant::set_user_dir(dirs::state().ensure()?).context("Failed to set user directory!")?;
+    Assistant::set_user_dir(dirs::state().ensure_private()?)
+        .context("Failed to set user directory!")?;
 
     config.static_options.apply()?;
     config.resource.load()?;
@@ -1,7 +1,7 @@
-use std::{borrow::Cow, fs, io::Write, path::Path};
+use std::{borrow::Cow, fs, path::Path};
 
 use anyhow::{bail, Context, Result};
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use maa_sys::TaskType;
 use prettytable::{format, row, Table};
 use serde_json::Value as JsonValue;
@@ -64,7 +64,7 @@ pub struct CopilotParams {
 
 impl IntoTaskConfig for CopilotParams {
     fn into_task_config(self, config: &super::AsstConfig) -> Result<TaskConfig> {
-        let copilot_dir = dirs::copilot().ensure()?;
+        prepare_copilot_dir()?;
         let base_dirs = config.resource.base_dirs();
         let default = MAAValue::find_file_or_default(super::default_file(TaskType::Copilot))
             .context("Failed to load default copilot task config")?;
@@ -73,7 +73,7 @@ impl IntoTaskConfig for CopilotParams {
         for uri in &self.uri_list {
             let copilot_file = CopilotFile::from_uri(uri)?;
 
-            copilot_file.push_path_to(&mut copilot_files, copilot_dir)?;
+            copilot_file.push_path_to(&mut copilot_files)?;
         }
 
         let is_task_list = copilot_files.len() > 1;
@@ -213,11 +213,11 @@ impl TryFrom<SSSCopilotParams> for MAAValue {
     type Error = anyhow::Error;
 
     fn try_from(params: SSSCopilotParams) -> std::result::Result<Self, Self::Error> {
-        let copilot_dir = dirs::copilot().ensure()?;
+        prepare_copilot_dir()?;
 
         let copilot_file = CopilotFile::from_uri(&params.uri)?;
         let mut paths = Vec::new();
-        copilot_file.push_path_to(&mut paths, copilot_dir)?;
+        copilot_file.push_path_to(&mut paths)?;
 
         if paths.len() != 1 {
             bail!("SSS Copilot don't support task set");
@@ -292,27 +292,24 @@ impl<'a> CopilotFile<'a> {
         }
     }
 
-    pub fn push_path_to(
-        self,
-        paths: &mut Vec<Cow<'a, Path>>,
-        base_dir: impl AsRef<Path>,
-    ) -> Result<()> {
-        let base_dir = base_dir.as_ref();
+    /// Push the path of the copilot file, or of each file in a copilot set, to `paths`
+    ///
+    /// Remote files are downloaded into the copilot cache directory if not cached yet, and a
+    /// relative local file is resolved against the copilot directory.
+    pub fn push_path_to(self, paths: &mut Vec<Cow<'a, Path>>) -> Result<()> {
         match self {
             CopilotFile::Remote(code) => {
-                let code = code.to_string();
-                let json_file = base_dir.join(&code).with_extension("json");
+                const COPILOT_API: &str = "https://prts.maa.plus/copilot/get/";
+                let url = format!("{}{}", COPILOT_API, code);
 
-                if json_file.is_file() {
+                if let Some(json_file) = dirs::cached_copilot(&url) {
                     debug!("Cache hit, using cached json file {}", json_file.display());
                     paths.push(json_file.into());
                     return Ok(());
                 }
 
-                const COPILOT_API: &str = "https://prts.maa.plus/copilot/get/";
-                let url = format!("{}{}", COPILOT_API, code);
                 debug!("Cache miss, downloading copilot from {url}");
-                let resp: JsonValue = reqwest::blocking::get(url)
+                let resp: JsonValue = reqwest::blocking::get(&url)
                     .context("Failed to send request")?
                     .json()
                     .context("Failed to parse response")?;
@@ -327,9 +324,7 @@ impl<'a> CopilotFile<'a> {
                         .context("Content is not a string")?;
 
                     // Save json file
-                    fs::File::create(&json_file)
-                        .context("Failed to create json file")?
-                        .write_all(content.as_bytes())
+                    let json_file = dirs::store_copilot(&url, content.as_bytes())
                         .context("Failed to write json file")?;
 
                     paths.push(json_file.into());
@@ -359,7 +354,7 @@ impl<'a> CopilotFile<'a> {
 
                     for id in ids {
                         let id = id.as_i64().context("copilot_id is not an integer")?;
-                        CopilotFile::Remote(id).push_path_to(paths, base_dir)?;
+                        CopilotFile::Remote(id).push_path_to(paths)?;
                     }
 
                     Ok(())
@@ -371,7 +366,7 @@ impl<'a> CopilotFile<'a> {
                 if file.is_absolute() {
                     paths.push(file.into());
                 } else {
-                    paths.push(base_dir.join(file).into());
+                    paths.push(dirs::copilot().join(file).into());
                 }
                 Ok(())
             }
@@ -379,6 +374,17 @@ impl<'a> CopilotFile<'a> {
     }
 }
 
+/// Maximum total size of cached copilot files, least recently used ones are evicted beyond it.
+const COPILOT_CACHE_LIMIT: u64 = 16 * 1024 * 1024;
+
+fn prepare_copilot_dir() -> Result<()> {
+    if let Err(err) = dirs::evict_copilot(COPILOT_CACHE_LIMIT) {
+        warn!("Failed to evict copilot cache: {err}");
+    }
+    dirs::copilot().ensure()?;
+    Ok(())
+}
+
 fn json_from_file(path: impl AsRef<Path>) -> Result<JsonValue> {
     Ok(serde_json::from_reader(fs::File::open(path)?)?)
 }
@@ -430,7 +436,7 @@ fn get_str_key(value: &JsonValue, key: impl AsRef<str>) -> Result<&str> {
 
 #[cfg(test)]
 mod tests {
-    use std::{env::temp_dir, path::PathBuf};
+    use std::path::PathBuf;
 
     use super::*;
     use crate::config::asst::AsstConfig;
@@ -467,6 +473,11 @@ mod tests {
         panic!("Failed to run test after {times} retries");
     }
 
+    /// Name of the cached file of the remote copilot file `id`
+    fn cached_file_name(id: i64) -> String {
+        dirs::copilot_file_name(&format!("https://prts.maa.plus/copilot/get/{id}"))
+    }
+
     mod copilot_params {
         use super::*;
 
@@ -537,7 +548,7 @@ mod tests {
             assert_params!(
                 tasks[0].params,
                 object!(
-                    "filename" => "40051.json",
+                    "filename" => cached_file_name(40051),
                     "formation" => false,
                     "need_navigate" => false,
                     "use_sanity_potion" => false,
@@ -574,7 +585,7 @@ mod tests {
             assert_params!(
                 tasks_no_default[0].params,
                 object!(
-                    "filename" => "40051.json",
+                    "filename" => cached_file_name(40051),
                     "need_navigate" => true,
                     "formation" => true,
                     "use_sanity_potion" => true,
@@ -611,7 +622,7 @@ mod tests {
             assert_params!(
                 tasks_raid_2[0].params,
                 object!(
-                    "filename" => "40051.json",
+                    "filename" => cached_file_name(40051),
                     "formation" => true,
                     "need_navigate" => false,
                     "use_sanity_potion" => false,
@@ -623,7 +634,7 @@ mod tests {
             assert_params!(
                 tasks_raid_2[1].params,
                 object!(
-                    "filename" => "40051.json",
+                    "filename" => cached_file_name(40051),
                     "formation" => false,
                     "need_navigate" => true,
                     "use_sanity_potion" => false,
@@ -651,7 +662,7 @@ mod tests {
             assert_params!(
                 tasks_multiple[0].params,
                 object!(
-                    "filename" => "40051.json",
+                    "filename" => cached_file_name(40051),
                     "formation" => true,
                     "need_navigate" => true,
                     "use_sanity_potion" => false,
@@ -663,7 +674,7 @@ mod tests {
             assert_params!(
                 tasks_multiple[1].params,
                 object!(
-                    "filename" => "40052.json",
+                    "filename" => cached_file_name(40052),
                     "formation" => true,
                     "need_navigate" => true,
                     "use_sanity_potion" => false,
@@ -727,7 +738,7 @@ mod tests {
             assert!(parse(["maa", "ssscopilot", "maa://40051"]).is_err());
             assert_params!(
                 retry(3, || parse(["maa", "ssscopilot", "maa://40451"])),
-                object!("filename" => "40451.json", "loop_times" => 1)
+                object!("filename" => cached_file_name(40451), "loop_times" => 1)
             );
             assert_params!(
                 retry(3, || parse([
@@ -737,7 +748,7 @@ mod tests {
                     "--loop-times",
                     "2"
                 ])),
-                object!("filename" => "40451.json", "loop_times" => 2)
+                object!("filename" => cached_file_name(40451), "loop_times" => 2)
             );
         }
     }
@@ -773,8 +784,9 @@ mod tests {
         #[test]
         #[ignore = "need to download from internet"]
         fn push_path_to() {
-            let test_root = temp_dir().join("maa-test-push-path-to");
-            fs::create_dir_all(&test_root).unwrap();
+            let test_root = tempfile::tempdir().unwrap();
+            let test_root = test_root.path();
+            let _guard = dirs::DirsOverrideGuard::new(test_root);
 
             let test_file = test_root.join("123234.json");
             let test_content = serde_json::json!({
@@ -795,10 +807,10 @@ mod tests {
                     let mut paths = Vec::new();
                     CopilotFile::from_uri("maa://40051")
                         .unwrap()
-                        .push_path_to(&mut paths, &test_root)?;
+                        .push_path_to(&mut paths)?;
                     Ok(paths)
                 }),
-                &[test_root.join("40051.json")],
+                &[dirs::copilot().join(cached_file_name(40051))],
             );
 
             // RemoteSet
@@ -807,16 +819,13 @@ mod tests {
                     let mut paths = Vec::new();
                     CopilotFile::from_uri("maa://23125s")
                         .unwrap()
-                        .push_path_to(&mut paths, &test_root)?;
+                        .push_path_to(&mut paths)?;
                     Ok(paths)
                 }),
-                {
-                    let ids = [40051, 40052, 40053, 40055, 40056, 40057, 40058, 40059];
-
-                    ids.iter()
-                        .map(|id| test_root.join(format!("{}.json", id)))
-                        .collect::<Vec<PathBuf>>()
-                }
+                [40051, 40052, 40053, 40055, 40056, 40057, 40058, 40059]
+                    .into_iter()
+                    .map(|id| dirs::copilot().join(cached_file_name(id)))
+                    .collect::<Vec<PathBuf>>()
             );
 
             // Local file (absolute)
@@ -825,7 +834,7 @@ mod tests {
                     let mut paths = Vec::new();
                     CopilotFile::from_uri(test_file.to_str().unwrap())
                         .unwrap()
-                        .push_path_to(&mut paths, &test_root)
+                        .push_path_to(&mut paths)
                         .unwrap();
                     paths
                 },
@@ -838,14 +847,12 @@ mod tests {
                     let mut paths = Vec::new();
                     CopilotFile::from_uri("file.json")
                         .unwrap()
-                        .push_path_to(&mut paths, &test_root)
+                        .push_path_to(&mut paths)
                         .unwrap();
                     paths
                 },
-                &[test_root.join("file.json")]
+                &[dirs::copilot().join("file.json")]
             );
-
-            fs::remove_dir_all(&test_root).unwrap();
         }
     }
 
@@ -108,6 +108,9 @@ pub use roguelike::RoguelikeParams;
 mod reclamation;
 pub use reclamation::ReclamationParams;
 
+mod raw;
+pub use raw::{RawTask, RawTaskParams};
+
 #[cfg(test)]
 mod tests {
     use maa_dirs::Ensure;
@@ -0,0 +1,151 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use super::{TaskType, ToTaskType};
+
+#[derive(clap::Args)]
+pub struct RawTaskParams {
+    /// Path to a JSON file containing the params of the task
+    path: PathBuf,
+    /// Run the task as a `SingleStep` task instead of a `Custom` task
+    #[arg(long)]
+    single_step: bool,
+}
+
+impl ToTaskType for RawTaskParams {
+    fn to_task_type(&self) -> TaskType {
+        if self.single_step {
+            TaskType::SingleStep
+        } else {
+            TaskType::Custom
+        }
+    }
+}
+
+/// A task whose params are passed to MaaCore as is
+pub struct RawTask {
+    pub task_type: TaskType,
+    /// Params of the task as a JSON string
+    pub params: String,
+}
+
+impl TryFrom<RawTaskParams> for RawTask {
+    type Error = anyhow::Error;
+
+    fn try_from(params: RawTaskParams) -> Result<Self> {
+        Ok(RawTask {
+            task_type: params.to_task_type(),
+            params: load_task_json(&params.path)?.to_string(),
+        })
+    }
+}
+
+/// Load the params of a task from a JSON file
+///
+/// The content must be a JSON object, which is passed to MaaCore as is.
+fn load_task_json(path: &Path) -> Result<serde_json::Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read task file {}", path.display()))?;
+    parse_task_json(&content).with_context(|| format!("Invalid task file {}", path.display()))
+}
+
+fn parse_task_json(content: &str) -> Result<serde_json::Value> {
+    // Parse to a JSON value first, so syntax errors are reported with line and column
+    let value: serde_json::Value = serde_json::from_str(content)?;
+    if !value.is_object() {
+        bail!("params of task must be a JSON object");
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_raw_task_params() {
+        fn parse<I, T>(args: I) -> RawTaskParams
+        where
+            I: IntoIterator<Item = T>,
+            T: Into<std::ffi::OsString> + Clone,
+        {
+            match crate::command::parse_from(args).command {
+                crate::Command::Raw { params, .. } => params,
+                _ => panic!("Not a Raw command"),
+            }
+        }
+
+        let params = parse(["maa", "raw", "task.json"]);
+        assert_eq!(params.path, PathBuf::from("task.json"));
+        assert_eq!(params.to_task_type(), TaskType::Custom);
+
+        let params = parse(["maa", "raw", "task.json", "--single-step"]);
+        assert_eq!(params.to_task_type(), TaskType::SingleStep);
+    }
+
+    #[test]
+    fn load_valid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.json");
+        std::fs::write(
+            &path,
+            r#"{ "task_names": ["StartUp"], "params": { "threshold": 0.8 } }"#,
+        )
+        .unwrap();
+
+        let task = RawTask::try_from(RawTaskParams {
+            path,
+            single_step: true,
+        })
+        .unwrap();
+        assert_eq!(task.task_type, TaskType::SingleStep);
+        assert_eq!(
+            task.params,
+            r#"{"params":{"threshold":0.8},"task_names":["StartUp"]}"#
+        );
+    }
+
+    #[test]
+    fn load_without_processing() {
+        // Keys with special meanings in task configs and big integers are kept as is
+        let content = r#"{
+            "id": 4294967296,
+            "stage": { "default": "1-7", "description": "stage" },
+            "drops": { "ref": "drops" },
+            "report": { "conditions": [] }
+        }"#;
+        assert_eq!(
+            parse_task_json(content).unwrap().to_string(),
+            concat!(
+                r#"{"drops":{"ref":"drops"},"id":4294967296,"#,
+                r#""report":{"conditions":[]},"#,
+                r#""stage":{"default":"1-7","description":"stage"}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn load_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("malformed.json");
+        std::fs::write(&path, "{\n  \"task_names\": [\"StartUp\",]\n}").unwrap();
+        let err = load_task_json(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Invalid task file {}", path.display())
+        );
+        assert_eq!(
+            err.root_cause().to_string(),
+            "trailing comma at line 2 column 28"
+        );
+
+        assert_eq!(
+            parse_task_json("[1, 2]").unwrap_err().to_string(),
+            "params of task must be a JSON object"
+        );
+
+        assert!(load_task_json(&dir.path().join("not_exist.json")).is_err());
+    }
+}
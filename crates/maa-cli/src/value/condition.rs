@@ -0,0 +1,310 @@
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+use super::{MAAPrimate, MAAValue, Map};
+
+/// Operator to compare the value of a dependency with the expected value
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// A condition of an optional value on a dependency
+///
+/// A condition can be deserialized from a primate value, which is the expected value compared
+/// with `Eq`, or a table with a single operator key, e.g. `{ gt = 3 }`.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(from = "ConditionRepr")]
+pub struct Condition {
+    op: Operator,
+    expected: MAAPrimate,
+}
+
+impl Condition {
+    pub fn new(op: Operator, expected: impl Into<MAAPrimate>) -> Self {
+        Self {
+            op,
+            expected: expected.into(),
+        }
+    }
+
+    pub fn op(&self) -> Operator {
+        self.op
+    }
+
+    pub fn expected(&self) -> &MAAPrimate {
+        &self.expected
+    }
+
+    /// Check if the initialized value of the dependency satisfies the condition
+    ///
+    /// `Eq` and `Ne` work for all primate values, while other operators only work for numbers.
+    /// Integers and floats are compared as numbers. If the types of values mismatch,
+    /// e.g. a string compared with a number, the condition is unsatisfied.
+    pub fn is_satisfied_by(&self, value: &MAAValue) -> bool {
+        let MAAValue::Primate(value) = value else {
+            return false;
+        };
+        let Some(ord) = compare(value, &self.expected) else {
+            return false;
+        };
+
+        use Operator::*;
+        match self.op {
+            Eq => ord == Ordering::Equal,
+            Ne => ord != Ordering::Equal,
+            _ if !is_number(value) => false,
+            Lt => ord == Ordering::Less,
+            Le => ord != Ordering::Greater,
+            Gt => ord == Ordering::Greater,
+            Ge => ord != Ordering::Less,
+        }
+    }
+}
+
+impl<T: Into<MAAPrimate>> From<T> for Condition {
+    fn from(expected: T) -> Self {
+        Self::new(Operator::Eq, expected)
+    }
+}
+
+/// Dependencies of an optional value
+///
+/// All the conditions in `conditions` (alias `deps`) should be satisfied, and at least one group
+/// of `any_of` should be satisfied if there is any group. Each group is a map of conditions, all of
+/// which should be satisfied. At least one of `conditions` and `any_of` should be given, otherwise
+/// the value is not an optional value.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Deserialize, Clone, Default)]
+#[serde(try_from = "ConditionsRepr")]
+pub struct Conditions {
+    all: Map<String, Condition>,
+    any_of: Vec<Map<String, Condition>>,
+}
+
+impl Conditions {
+    pub fn new(all: Map<String, Condition>) -> Self {
+        Self {
+            all,
+            any_of: Vec::new(),
+        }
+    }
+
+    /// Add a group of conditions, at least one group should be satisfied
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "conditions are only built by deserialization")
+    )]
+    pub(crate) fn with_any_of(mut self, group: Map<String, Condition>) -> Self {
+        self.any_of.push(group);
+        self
+    }
+
+    /// Conditions which should be all satisfied
+    pub fn all(&self) -> &Map<String, Condition> {
+        &self.all
+    }
+
+    /// Groups of conditions, one of which should be satisfied
+    pub fn any_of(&self) -> &[Map<String, Condition>] {
+        &self.any_of
+    }
+
+    /// Keys of all the dependencies, including those in `any_of`
+    pub(super) fn keys(&self) -> impl Iterator<Item = &String> {
+        self.all
+            .keys()
+            .chain(self.any_of.iter().flat_map(Map::keys))
+    }
+
+    /// Check if the conditions are satisfied by the initialized values
+    ///
+    /// If a dependency does not exist in `values`, its condition is unsatisfied.
+    pub(super) fn is_satisfied(&self, values: &Map<String, MAAValue>) -> bool {
+        let all_satisfied = |conditions: &Map<String, Condition>| {
+            conditions.iter().all(|(key, condition)| {
+                values
+                    .get(key)
+                    .is_some_and(|v| condition.is_satisfied_by(v))
+            })
+        };
+
+        all_satisfied(&self.all)
+            && (self.any_of.is_empty() || self.any_of.iter().any(all_satisfied))
+    }
+}
+
+#[derive(Deserialize)]
+struct ConditionsRepr {
+    #[serde(alias = "deps")]
+    conditions: Option<Map<String, Condition>>,
+    any_of: Option<Vec<Map<String, Condition>>>,
+}
+
+impl TryFrom<ConditionsRepr> for Conditions {
+    type Error = &'static str;
+
+    fn try_from(repr: ConditionsRepr) -> Result<Self, Self::Error> {
+        if repr.conditions.is_none() && repr.any_of.is_none() {
+            return Err("missing field `conditions` or `any_of`");
+        }
+        Ok(Self {
+            all: repr.conditions.unwrap_or_default(),
+            any_of: repr.any_of.unwrap_or_default(),
+        })
+    }
+}
+
+fn is_number(value: &MAAPrimate) -> bool {
+    matches!(value, MAAPrimate::Int(_) | MAAPrimate::Float(_))
+}
+
+fn compare(a: &MAAPrimate, b: &MAAPrimate) -> Option<Ordering> {
+    use MAAPrimate::*;
+    match (a, b) {
+        (Null, Null) => Some(Ordering::Equal),
+        (Bool(a), Bool(b)) => Some(a.cmp(b)),
+        (Int(a), Int(b)) => Some(a.cmp(b)),
+        (Int(a), Float(b)) => (*a as f64).partial_cmp(&(*b as f64)),
+        (Float(a), Int(b)) => (*a as f64).partial_cmp(&(*b as f64)),
+        (Float(a), Float(b)) => a.partial_cmp(b),
+        (String(a), String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConditionRepr {
+    Eq(MAAPrimate),
+    Op(OperatorRepr),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OperatorRepr {
+    Eq(MAAPrimate),
+    Ne(MAAPrimate),
+    Lt(MAAPrimate),
+    Le(MAAPrimate),
+    Gt(MAAPrimate),
+    Ge(MAAPrimate),
+}
+
+impl From<ConditionRepr> for Condition {
+    fn from(repr: ConditionRepr) -> Self {
+        use OperatorRepr::*;
+        match repr {
+            ConditionRepr::Eq(v) => Self::new(Operator::Eq, v),
+            ConditionRepr::Op(Eq(v)) => Self::new(Operator::Eq, v),
+            ConditionRepr::Op(Ne(v)) => Self::new(Operator::Ne, v),
+            ConditionRepr::Op(Lt(v)) => Self::new(Operator::Lt, v),
+            ConditionRepr::Op(Le(v)) => Self::new(Operator::Le, v),
+            ConditionRepr::Op(Gt(v)) => Self::new(Operator::Gt, v),
+            ConditionRepr::Op(Ge(v)) => Self::new(Operator::Ge, v),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let conditions: Vec<Condition> =
+            serde_json::from_str(r#"[1, "1-7", { "ne": true }, { "le": 1.5 }, { "gt": 3 }]"#)
+                .unwrap();
+        assert_eq!(conditions, [
+            Condition::new(Operator::Eq, 1),
+            Condition::new(Operator::Eq, "1-7"),
+            Condition::new(Operator::Ne, true),
+            Condition::new(Operator::Le, 1.5),
+            Condition::new(Operator::Gt, 3),
+        ]);
+
+        assert!(serde_json::from_str::<Condition>(r#"{ "gt": 1, "lt": 3 }"#).is_err());
+        assert!(serde_json::from_str::<Condition>(r#"{ "in": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn conditions() {
+        let values = Map::from([
+            ("client_type".to_owned(), MAAValue::from("Bilibili")),
+            ("stage".to_owned(), MAAValue::from("1-7")),
+        ]);
+        let group = |key: &str, expected: &str| Map::from([(key.to_owned(), expected.into())]);
+
+        let conditions = Conditions::new(group("stage", "1-7"));
+        assert!(conditions.is_satisfied(&values));
+
+        let conditions = conditions
+            .with_any_of(group("client_type", "Official"))
+            .with_any_of(group("client_type", "Bilibili"));
+        assert!(conditions.is_satisfied(&values));
+        assert_eq!(conditions.keys().collect::<Vec<_>>(), [
+            "stage",
+            "client_type",
+            "client_type"
+        ]);
+
+        let conditions = Conditions::default().with_any_of(group("client_type", "Official"));
+        assert!(!conditions.is_satisfied(&values));
+
+        let conditions =
+            Conditions::new(group("stage", "CE-6")).with_any_of(group("client_type", "Bilibili"));
+        assert!(!conditions.is_satisfied(&values));
+
+        assert!(serde_json::from_str::<Conditions>("{}").is_err());
+    }
+
+    #[test]
+    fn is_satisfied_by() {
+        use Operator::*;
+
+        let cases: [(Operator, MAAValue, MAAValue, bool); 22] = [
+            (Eq, 3.into(), 3.into(), true),
+            (Eq, 3.into(), 4.into(), false),
+            (Eq, 3.into(), 3.0.into(), true),
+            (Eq, "1-7".into(), "1-7".into(), true),
+            (Eq, "1-7".into(), 1.into(), false),
+            (Ne, 3.into(), 4.into(), true),
+            (Ne, 3.into(), 3.into(), false),
+            (Ne, true.into(), false.into(), true),
+            (Ne, "1-7".into(), "CE-6".into(), true),
+            (Ne, "1-7".into(), 1.into(), false),
+            (Lt, 3.into(), 2.into(), true),
+            (Lt, 3.into(), 3.into(), false),
+            (Le, 3.into(), 3.into(), true),
+            (Le, 3.into(), 3.5.into(), false),
+            (Gt, 3.into(), 4.into(), true),
+            (Gt, 3.into(), 3.into(), false),
+            (Gt, 3.0.into(), 3.5.into(), true),
+            (Ge, 3.into(), 3.into(), true),
+            (Ge, 3.into(), 2.into(), false),
+            (Gt, 3.into(), "4".into(), false),
+            (Gt, "a".into(), "b".into(), false),
+            (Gt, 3.into(), MAAValue::new(), false),
+        ];
+
+        for (op, expected, value, satisfied) in cases {
+            let MAAValue::Primate(expected) = expected else {
+                unreachable!()
+            };
+            let condition = Condition::new(op, expected);
+            assert_eq!(
+                condition.is_satisfied_by(&value),
+                satisfied,
+                "{condition:?} on {value:?}"
+            );
+        }
+    }
+}
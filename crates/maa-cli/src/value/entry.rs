@@ -0,0 +1,83 @@
+use std::collections::btree_map;
+
+use super::MAAValue;
+
+/// A view into a single entry of an object, which may be vacant or occupied
+///
+/// This is constructed by [`MAAValue::entry`].
+pub struct Entry<'a>(btree_map::Entry<'a, String, MAAValue>);
+
+#[cfg_attr(not(test), expect(dead_code, reason = "reserved for merging defaults"))]
+impl<'a> Entry<'a> {
+    /// Key of this entry
+    pub fn key(&self) -> &str {
+        self.0.key()
+    }
+
+    /// Insert the default value if the entry is vacant, and return a mutable reference to the
+    /// value in the entry
+    pub fn or_insert(self, default: impl Into<MAAValue>) -> &'a mut MAAValue {
+        self.0.or_insert_with(|| default.into())
+    }
+
+    /// Insert the result of the function if the entry is vacant, and return a mutable reference
+    /// to the value in the entry
+    pub fn or_insert_with<V: Into<MAAValue>>(self, f: impl FnOnce() -> V) -> &'a mut MAAValue {
+        self.0.or_insert_with(|| f().into())
+    }
+
+    /// Modify the value in place if the entry is occupied
+    pub fn and_modify(self, f: impl FnOnce(&mut MAAValue)) -> Self {
+        Self(self.0.and_modify(f))
+    }
+}
+
+impl MAAValue {
+    /// Get the entry of given key for in-place manipulation
+    ///
+    /// Return `None` if the value is not an object.
+    #[cfg_attr(not(test), expect(dead_code, reason = "reserved for merging defaults"))]
+    pub(crate) fn entry(&mut self, key: impl Into<String>) -> Option<Entry<'_>> {
+        self.as_object_mut().map(|map| Entry(map.entry(key.into())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object;
+
+    #[test]
+    fn entry() {
+        let mut value = object!("medicine" => 1);
+
+        // Vacant
+        let entry = value.entry("stage").unwrap();
+        assert_eq!(entry.key(), "stage");
+        assert_eq!(entry.or_insert("1-7").as_str(), Some("1-7"));
+        value
+            .entry("stone")
+            .unwrap()
+            .and_modify(|_| unreachable!())
+            .or_insert_with(|| 0);
+
+        // Occupied
+        assert_eq!(
+            value.entry("stage").unwrap().or_insert("CE-6").as_str(),
+            Some("1-7")
+        );
+        value
+            .entry("medicine")
+            .unwrap()
+            .and_modify(|v| *v = (v.as_int().unwrap() + 1).into())
+            .or_insert_with(|| -> i32 { unreachable!() });
+
+        assert_eq!(
+            value,
+            object!("medicine" => 2, "stage" => "1-7", "stone" => 0)
+        );
+
+        // Non-object
+        assert!(MAAValue::from(1).entry("stage").is_none());
+    }
+}
@@ -0,0 +1,147 @@
+use std::io;
+
+use super::{MAAPrimate, MAAValue};
+
+impl MAAValue {
+    /// Initialize the value and interpolate environment variables in strings
+    ///
+    /// Same as `init`, but every `${VAR}` in a string value is replaced by the value of the
+    /// environment variable `VAR`, and `$$` is replaced by a literal `$`. A `$` followed by other
+    /// characters is kept as is.
+    ///
+    /// # Errors
+    ///
+    /// Besides errors of `init`, return an `InvalidData` error if a referenced variable is not set
+    /// or not valid unicode, or a reference is not terminated by `}`.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "task configs are not interpolated yet")
+    )]
+    pub(crate) fn init_with_env(self) -> io::Result<Self> {
+        let mut value = self.init()?;
+        value.interpolate(&|name| std::env::var(name).ok())?;
+        Ok(value)
+    }
+
+    fn interpolate(&mut self, lookup: &impl Fn(&str) -> Option<String>) -> io::Result<()> {
+        match self {
+            Self::Primate(MAAPrimate::String(s)) if s.contains('$') => {
+                *s = interpolate_str(s, lookup)?;
+            }
+            Self::Array(array) => {
+                for value in array {
+                    value.interpolate(lookup)?;
+                }
+            }
+            Self::Object(map) => {
+                for value in map.values_mut() {
+                    value.interpolate(lookup)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn interpolate_str(s: &str, lookup: &impl Fn(&str) -> Option<String>) -> io::Result<String> {
+    fn invalid(msg: String) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| invalid(format!("unterminated variable reference in `{s}`")))?;
+            let name = &after[..end];
+            let value = lookup(name)
+                .ok_or_else(|| invalid(format!("environment variable `{name}` is not set")))?;
+            out.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "MAA_TOKEN" => Some("secret".to_owned()),
+            "MAA_DIR" => Some("/opt/maa".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate() {
+        let mut value = object!(
+            "token" => "${MAA_TOKEN}",
+            "path" => "${MAA_DIR}/resource/${MAA_TOKEN}.json",
+            "price" => "$$5 and $5",
+            "plain" => "1-7",
+            "nested" => object!("paths" => ["${MAA_DIR}", "$${MAA_DIR}"]),
+            "int" => 1,
+        );
+        value.interpolate(&lookup).unwrap();
+        assert_eq!(
+            value,
+            object!(
+                "token" => "secret",
+                "path" => "/opt/maa/resource/secret.json",
+                "price" => "$5 and $5",
+                "plain" => "1-7",
+                "nested" => object!("paths" => ["/opt/maa", "${MAA_DIR}"]),
+                "int" => 1,
+            )
+        );
+    }
+
+    #[test]
+    fn interpolate_errors() {
+        let err = MAAValue::from("${MAA_MISSING}")
+            .interpolate(&lookup)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "environment variable `MAA_MISSING` is not set"
+        );
+
+        let err = MAAValue::from("${MAA_TOKEN")
+            .interpolate(&lookup)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unterminated variable reference in `${MAA_TOKEN`"
+        );
+    }
+
+    #[test]
+    fn init_with_env() {
+        let value = object!("path" => "${PATH}");
+        let path = std::env::var("PATH").unwrap();
+
+        // init does not interpolate
+        assert_eq!(value.clone().init().unwrap(), value);
+        assert_eq!(value.init_with_env().unwrap(), object!("path" => path));
+        assert!(object!("path" => "${MAA_CLI_UNDEFINED_VARIABLE}")
+            .init_with_env()
+            .is_err());
+    }
+}
@@ -35,6 +35,57 @@ impl MAAInput {
             SelectString(v) => Ok(String(v.value()?)),
         }
     }
+
+    /// Name of the JSON type this input will be resolved to
+    pub(super) fn type_name(&self) -> &'static str {
+        use MAAInput::*;
+        match self {
+            InputBool(_) => "boolean",
+            InputInt(_) | SelectInt(_) => "integer",
+            InputFloat(_) | SelectFloat(_) => "number",
+            InputString(_) | SelectString(_) => "string",
+        }
+    }
+
+    /// Default value of this input without querying the user
+    pub(super) fn default_primate(&self) -> Option<MAAPrimate> {
+        use MAAInput::*;
+        match self {
+            InputBool(v) => v.default_value().map(Into::into),
+            InputInt(v) => v.default_value().copied().map(Into::into),
+            InputFloat(v) => v.default_value().copied().map(Into::into),
+            InputString(v) => v.default_value().cloned().map(Into::into),
+            SelectInt(v) => v.default_value().map(|v| (*v.as_value()).into()),
+            SelectFloat(v) => v.default_value().map(|v| (*v.as_value()).into()),
+            SelectString(v) => v.default_value().map(|v| v.as_value().clone().into()),
+        }
+    }
+
+    pub(super) fn help(&self) -> Option<&str> {
+        use MAAInput::*;
+        match self {
+            InputBool(v) => v.help(),
+            InputInt(v) => v.help(),
+            InputFloat(v) => v.help(),
+            InputString(v) => v.help(),
+            SelectInt(v) => v.help(),
+            SelectFloat(v) => v.help(),
+            SelectString(v) => v.help(),
+        }
+    }
+
+    pub(super) fn description(&self) -> Option<&str> {
+        use MAAInput::*;
+        match self {
+            InputBool(v) => v.description(),
+            InputInt(v) => v.description(),
+            InputFloat(v) => v.description(),
+            InputString(v) => v.description(),
+            SelectInt(v) => v.description(),
+            SelectFloat(v) => v.description(),
+            SelectString(v) => v.description(),
+        }
+    }
 }
 
 impl From<BoolInput> for MAAInput {
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+
+use super::{Condition, MAAPrimate, MAAValue, Map, Operator};
+
+impl MAAValue {
+    /// Render the value as a Markdown table of its parameters
+    ///
+    /// The value is treated as a template: every leaf becomes a row with its key, type,
+    /// whether it is required, its default value and its description. Keys of nested objects are
+    /// joined with `.`, so `{ "a": { "b": 1 } }` produces a row for `a.b`.
+    ///
+    /// An input without a default value is required, while a primate value is never required
+    /// because the value itself is the default. Optional values are never required and the
+    /// conditions are appended to the description.
+    ///
+    /// Rows are sorted by key, so the output is stable for the same value.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "no command renders task docs yet")
+    )]
+    pub(crate) fn to_markdown(&self) -> String {
+        let mut out = String::from(
+            "| Key | Type | Required | Default | Description |\n\
+             | --- | --- | --- | --- | --- |\n",
+        );
+        match self {
+            MAAValue::Object(map) => write_object(&mut out, "", map, None),
+            value => write_row(&mut out, "", value, None),
+        }
+        out
+    }
+}
+
+fn write_object(out: &mut String, prefix: &str, map: &Map<String, MAAValue>, note: Option<&str>) {
+    for (key, value) in map {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        write_row(out, &key, value, note);
+    }
+}
+
+fn write_row(out: &mut String, key: &str, value: &MAAValue, note: Option<&str>) {
+    use MAAValue::*;
+
+    let required = |r: bool| if r && note.is_none() { "yes" } else { "no" };
+
+    match value {
+        Object(map) => write_object(out, key, map, note),
+        Optional { conditions, value } => {
+            let mut desc = note.map(str::to_owned).unwrap_or_default();
+            for (cond_key, condition) in conditions.all() {
+                if !desc.is_empty() {
+                    desc.push(' ');
+                }
+                let _ = write!(desc, "Only when `{cond_key}` {}.", describe(condition));
+            }
+            let any_of = conditions.any_of();
+            if !any_of.is_empty() {
+                if !desc.is_empty() {
+                    desc.push(' ');
+                }
+                let groups: Vec<String> = any_of
+                    .iter()
+                    .map(|group| {
+                        group
+                            .iter()
+                            .map(|(cond_key, condition)| {
+                                format!("`{cond_key}` {}", describe(condition))
+                            })
+                            .collect::<Vec<_>>()
+                            .join(" and ")
+                    })
+                    .collect();
+                let _ = write!(desc, "Only when {}.", groups.join(" or "));
+            }
+            write_row(out, key, &value.0, Some(&desc));
+        }
+        Input(input) => {
+            let default = input.default_primate();
+            push_row(
+                out,
+                key,
+                input.type_name(),
+                required(default.is_none()),
+                default.as_ref().map(code).as_deref(),
+                join_desc(
+                    join_desc(input.description(), input.help()).as_deref(),
+                    note,
+                )
+                .as_deref(),
+            );
+        }
+        Primate(v) => push_row(
+            out,
+            key,
+            v.type_name(),
+            required(false),
+            Some(&code(v)),
+            note,
+        ),
+        Array(_) => push_row(
+            out,
+            key,
+            "array",
+            required(false),
+            serde_json::to_string(value)
+                .ok()
+                .map(|s| format!("`{s}`"))
+                .as_deref(),
+            note,
+        ),
+    }
+}
+
+fn push_row(
+    out: &mut String,
+    key: &str,
+    ty: &str,
+    required: &str,
+    default: Option<&str>,
+    description: Option<&str>,
+) {
+    let _ = writeln!(
+        out,
+        "| `{key}` | {ty} | {required} | {} | {} |",
+        default.unwrap_or(""),
+        escape(description.unwrap_or(""))
+    );
+}
+
+fn join_desc(desc: Option<&str>, note: Option<&str>) -> Option<String> {
+    match (desc, note) {
+        (Some(d), Some(n)) => Some(format!("{d} {n}")),
+        (Some(s), None) | (None, Some(s)) => Some(s.to_owned()),
+        (None, None) => None,
+    }
+}
+
+fn describe(condition: &Condition) -> String {
+    let op = match condition.op() {
+        Operator::Eq => "is",
+        Operator::Ne => "is not",
+        Operator::Lt => "is less than",
+        Operator::Le => "is at most",
+        Operator::Gt => "is greater than",
+        Operator::Ge => "is at least",
+    };
+    format!("{op} {}", code(condition.expected()))
+}
+
+fn code(v: &MAAPrimate) -> String {
+    format!("`{}`", serde_json::to_string(v).unwrap_or_default())
+}
+
+/// Escape characters that would break a Markdown table cell
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        object,
+        value::userinput::{BoolInput, Input, SelectD},
+    };
+
+    #[test]
+    fn to_markdown() {
+        let value = object!(
+            "medicine" => Input::<i32>::new(Some(0), Some("medicine to use"))
+                .with_help("Expiring first."),
+            "stage" => Input::<String>::new(None, Some("a stage | to fight")),
+            "client_type" => "Official",
+            "series" => SelectD::<i32>::new([1, 2, 3], Some(2), None, false).unwrap(),
+            "drops" => object!(
+                "30011" => 10,
+                "report" => BoolInput::new(None, None),
+            ),
+            "expiring" if "medicine" == 0 => BoolInput::new(Some(true), Some("use expiring")),
+            "stages" => ["1-7", "CE-6"],
+        );
+
+        assert_eq!(
+            value.to_markdown(),
+            "| Key | Type | Required | Default | Description |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | `client_type` | string | no | `\"Official\"` |  |\n\
+             | `drops.30011` | integer | no | `10` |  |\n\
+             | `drops.report` | boolean | yes |  |  |\n\
+             | `expiring` | boolean | no | `true` | use expiring Only when `medicine` is `0`. |\n\
+             | `medicine` | integer | no | `0` | medicine to use Expiring first. |\n\
+             | `series` | integer | no | `2` |  |\n\
+             | `stage` | string | yes |  | a stage \\| to fight |\n\
+             | `stages` | array | no | `[\"1-7\",\"CE-6\"]` |  |\n"
+        );
+
+        let value: MAAValue = serde_json::from_str(
+            r#"{
+                "medicine": {
+                    "any_of": [
+                        { "client_type": "Official" },
+                        { "client_type": "Bilibili", "stage": { "ne": "1-7" } }
+                    ],
+                    "default": 0
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            value.to_markdown(),
+            "| Key | Type | Required | Default | Description |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | `medicine` | integer | no | `0` | Only when `client_type` is `\"Official\"` or \
+             `client_type` is `\"Bilibili\"` and `stage` is not `\"1-7\"`. |\n"
+        );
+
+        assert_eq!(
+            MAAValue::from(1).to_markdown(),
+            "| Key | Type | Required | Default | Description |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | `` | integer | no | `1` |  |\n"
+        );
+    }
+}
@@ -3,9 +3,17 @@ pub mod userinput;
 mod primate;
 pub use primate::MAAPrimate;
 
+mod condition;
+mod entry;
+mod env;
+pub use condition::{Condition, Conditions, Operator};
+
 mod input;
+mod markdown;
+mod schema;
+mod walk;
 pub use std::collections::BTreeMap as Map;
-use std::io;
+use std::{borrow::Cow, io};
 
 pub use input::MAAInput;
 use serde::{Deserialize, Serialize};
@@ -21,17 +29,19 @@ pub enum MAAValue {
     Input(MAAInput),
     /// A optional value
     ///
-    /// A optional value will be initialized only if all the dependencies are satisfied.
-    /// If one of the dependencies is not exist or the value is not equal to the expected value,
+    /// A optional value will be initialized only if all the dependencies are satisfied,
+    /// and at least one group of `any_of` is satisfied if there is any group.
+    /// If one of the dependencies is not exist or the value does not satisfy the condition,
     /// the optional value will be dropped after initialization.
     ///
     /// Note: Circular dependencies will cause panic.
     Optional {
         /// A map of dependencies
         ///
-        /// Keys are the keys of the dependencies in the sam object and values are the expected
-        #[serde(alias = "deps")]
-        conditions: Map<String, MAAPrimate>,
+        /// Keys are the keys of the dependencies in the sam object and values are the conditions
+        /// on them, which are expected values or comparisons like `{ gt = 3 }`
+        #[serde(flatten)]
+        conditions: Conditions,
         /// Input value query from user when all the dependencies are satisfied
         #[serde(alias = "input", flatten)]
         value: BoxedMAAValue,
@@ -42,6 +52,53 @@ pub enum MAAValue {
     Primate(MAAPrimate),
 }
 
+/// Strategy to merge an array into another array
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum ArrayMergeStrategy {
+    /// Replace the array with the other one
+    #[default]
+    Replace,
+    /// Append all elements of the other array
+    Append,
+    /// Append elements of the other array which are not in the array
+    ConcatUnique,
+}
+
+/// Options to merge a value into another value
+///
+/// By default, arrays are replaced and null values are stored like other values.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, Copy, Default)]
+pub struct MergeOptions {
+    array_strategy: ArrayMergeStrategy,
+    null_as_delete: bool,
+}
+
+impl MergeOptions {
+    /// Set the strategy to merge arrays
+    pub fn with_array_strategy(mut self, array_strategy: ArrayMergeStrategy) -> Self {
+        self.array_strategy = array_strategy;
+        self
+    }
+
+    /// Treat a null value in an object of other as deletion of the key in self
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "no config layer opts into deletion yet")
+    )]
+    pub(crate) fn with_null_as_delete(mut self, null_as_delete: bool) -> Self {
+        self.null_as_delete = null_as_delete;
+        self
+    }
+}
+
+impl From<ArrayMergeStrategy> for MergeOptions {
+    fn from(array_strategy: ArrayMergeStrategy) -> Self {
+        Self::default().with_array_strategy(array_strategy)
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq, Debug))]
 #[derive(Deserialize, Clone)]
 #[serde(transparent)]
@@ -179,19 +236,8 @@ impl MAAValue {
                 for key in sorted_keys {
                     let value = map.remove(&key).unwrap();
                     if let Optional { conditions, value } = value {
-                        let mut satisfied = true;
-                        // Check if all the dependencies are satisfied
-                        for (cond_key, expected) in conditions {
-                            // If the dependency is not exist or the value is not equal to the
-                            // expected values break the loop and mark
-                            // status as unsatisfied
-                            if !initialized.get(&cond_key).is_some_and(|v| v == &expected) {
-                                satisfied = false;
-                                break;
-                            }
-                        }
                         // if all the dependencies are satisfied, initialize the value
-                        if satisfied {
+                        if conditions.is_satisfied(&initialized) {
                             initialized.insert(key, value.init()?);
                         }
                     } else {
@@ -225,6 +271,28 @@ impl MAAValue {
         }
     }
 
+    /// Iterate over key-value pairs of the object in the order of keys
+    ///
+    /// If the value is not an object, the iterator will be empty.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for logging task params")
+    )]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &Self)> {
+        self.as_object().into_iter().flatten()
+    }
+
+    /// Iterate over key-value pairs of the object with mutable values
+    ///
+    /// Same as `iter`, but yield mutable references to values.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for logging task params")
+    )]
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Self)> {
+        self.as_object_mut().into_iter().flatten()
+    }
+
     /// Get value of given key
     ///
     /// If the value is an object and the key exists, the value will be returned.
@@ -240,6 +308,244 @@ impl MAAValue {
         self.as_object_mut().and_then(|map| map.get_mut(key))
     }
 
+    /// Size of the value
+    ///
+    /// Unlike the length of a JSON value, every value has a size:
+    /// - the number of entries for an object;
+    /// - the number of elements for an array;
+    /// - `1` for a primate, input or optional value, which is a single value.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Object(map) => map.len(),
+            Self::Array(array) => array.len(),
+            _ => 1,
+        }
+    }
+
+    /// Check if the value is an empty object or an empty array
+    ///
+    /// A primate, input or optional value is never empty, even if it is an empty string.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of leaf values in the value
+    ///
+    /// Objects and arrays are traversed recursively, and a primate, input or optional value is
+    /// a leaf. Empty objects and arrays have no leaf.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for validating config size")
+    )]
+    pub(crate) fn leaf_count(&self) -> usize {
+        fn count(value: &MAAValue) -> usize {
+            match value {
+                MAAValue::Object(map) => map.values().map(count).sum(),
+                MAAValue::Array(array) => array.iter().map(count).sum(),
+                _ => 1,
+            }
+        }
+
+        count(self)
+    }
+
+    /// Maximum depth of nesting in the value
+    ///
+    /// The depth of a leaf value or an empty object or array is `1`, and the depth of other
+    /// objects and arrays is one more than the maximum depth of their values.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for validating config size")
+    )]
+    pub(crate) fn depth(&self) -> usize {
+        fn depth(value: &MAAValue) -> usize {
+            let max_depth = match value {
+                MAAValue::Object(map) => map.values().map(depth).max(),
+                MAAValue::Array(array) => array.iter().map(depth).max(),
+                _ => None,
+            };
+            max_depth.map_or(1, |depth| depth + 1)
+        }
+
+        depth(self)
+    }
+
+    /// Get value of given dotted path, e.g. `connection.address`
+    ///
+    /// The path is split by `.` and each segment is looked up in nested objects.
+    /// Return `None` if the path is empty, a segment is missing or a non-object value is met
+    /// before the last segment. Keys containing `.` can not be accessed by this method, use
+    /// `get` for them.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for reading nested config fields")
+    )]
+    pub(crate) fn get_path(&self, path: &str) -> Option<&Self> {
+        if path.is_empty() {
+            return None;
+        }
+        path.split('.').try_fold(self, |value, key| value.get(key))
+    }
+
+    /// Get mutable value of given dotted path
+    ///
+    /// Same as `get_path`, but return mutable reference.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for reading nested config fields")
+    )]
+    pub(crate) fn get_path_mut(&mut self, path: &str) -> Option<&mut Self> {
+        if path.is_empty() {
+            return None;
+        }
+        path.split('.')
+            .try_fold(self, |value, key| value.get_mut(key))
+    }
+
+    /// Set value of given dotted path
+    ///
+    /// Missing objects on the path are created, and the value at the last segment is replaced
+    /// if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Return an `InvalidInput` error if the path is empty, or an `InvalidData` error if the value
+    /// itself or a value before the last segment exists but is not an object.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for --set style overrides")
+    )]
+    pub(crate) fn set_path(&mut self, path: &str, value: impl Into<Self>) -> io::Result<()> {
+        if path.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "path is empty"));
+        }
+
+        let segments: Vec<&str> = path.split('.').collect();
+        let not_object = |depth: usize| {
+            let msg = if depth == 0 {
+                "value is not an object".to_owned()
+            } else {
+                format!(
+                    "value of `{}` is not an object",
+                    segments[..depth].join(".")
+                )
+            };
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        };
+
+        let (last, parents) = segments.split_last().unwrap();
+        let mut current = self;
+        for (depth, key) in parents.iter().enumerate() {
+            current = current
+                .as_object_mut()
+                .ok_or_else(|| not_object(depth))?
+                .entry((*key).to_owned())
+                .or_insert_with(Self::new);
+        }
+        current
+            .as_object_mut()
+            .ok_or_else(|| not_object(parents.len()))?
+            .insert((*last).to_owned(), value.into());
+
+        Ok(())
+    }
+
+    /// Get value by a JSON Pointer (RFC 6901), e.g. `/tasks/0/type`
+    ///
+    /// Unlike `get_path`, both objects and arrays can be traversed, and `~1` and `~0` in a
+    /// reference token are unescaped to `/` and `~`. An empty pointer refers to the value itself.
+    /// Return `None` if the pointer is malformed, a key is missing, an index is out of range or
+    /// the type of a value does not match the token.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for JSON Pointer interop")
+    )]
+    pub(crate) fn pointer(&self, ptr: &str) -> Option<&Self> {
+        pointer_tokens(ptr)?.try_fold(self, |value, token| match value {
+            Self::Object(map) => map.get(token.as_ref()),
+            Self::Array(array) => array.get(parse_index(&token)?),
+            _ => None,
+        })
+    }
+
+    /// Get mutable value by a JSON Pointer (RFC 6901)
+    ///
+    /// Same as `pointer`, but return mutable reference.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for JSON Pointer interop")
+    )]
+    pub(crate) fn pointer_mut(&mut self, ptr: &str) -> Option<&mut Self> {
+        pointer_tokens(ptr)?.try_fold(self, |value, token| match value {
+            Self::Object(map) => map.get_mut(token.as_ref()),
+            Self::Array(array) => array.get_mut(parse_index(&token)?),
+            _ => None,
+        })
+    }
+
+    /// Flatten the value into a single-level map of leaf values
+    ///
+    /// Keys of nested objects are joined with `.` and elements of arrays are indexed with `[n]`,
+    /// e.g. `{ "a": { "b": [1, 2] } }` is flattened to `{ "a.b[0]": 1, "a.b[1]": 2 }`. Empty
+    /// objects and arrays have no leaf, so they are not present in the result.
+    ///
+    /// # Errors
+    ///
+    /// Return an `InvalidData` error if there is an uninitialized input or optional value,
+    /// call `init` first to initialize them.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for a flat config report")
+    )]
+    pub(crate) fn flatten(&self) -> io::Result<Map<String, MAAPrimate>> {
+        fn visit(
+            value: &MAAValue,
+            prefix: String,
+            out: &mut Map<String, MAAPrimate>,
+        ) -> io::Result<()> {
+            match value {
+                MAAValue::Primate(v) => {
+                    out.insert(prefix, v.clone());
+                }
+                MAAValue::Array(array) => {
+                    for (i, value) in array.iter().enumerate() {
+                        visit(value, format!("{prefix}[{i}]"), out)?;
+                    }
+                }
+                MAAValue::Object(map) => {
+                    for (key, value) in map {
+                        let key = if prefix.is_empty() {
+                            key.clone()
+                        } else {
+                            format!("{prefix}.{key}")
+                        };
+                        visit(value, key, out)?;
+                    }
+                }
+                MAAValue::Input(_) | MAAValue::Optional { .. } => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("value of `{prefix}` is not initialized, call `init` first"),
+                    ));
+                }
+            }
+            Ok(())
+        }
+
+        let mut out = Map::new();
+        visit(self, String::new(), &mut out)?;
+        Ok(out)
+    }
+
+    /// Check if the value is an object and contains the given key
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "keys are only looked up by `get` so far")
+    )]
+    pub(crate) fn contains_key(&self, key: &str) -> bool {
+        self.as_object().is_some_and(|map| map.contains_key(key))
+    }
+
     /// Get value of given key or return default value
     ///
     /// If the value is an object and the key exists, get the value and try to convert it to type of
@@ -274,6 +580,140 @@ impl MAAValue {
         }
     }
 
+    /// Remove a key from the object and return its value
+    ///
+    /// If the value is an object and the key exists, the value will be removed and returned.
+    /// Otherwise, return `None`.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for building params dynamically")
+    )]
+    pub(crate) fn remove(&mut self, key: &str) -> Option<Self> {
+        self.as_object_mut().and_then(|map| map.remove(key))
+    }
+
+    /// Rename a key of the object
+    ///
+    /// The value is moved to `to` only if `from` exists and `to` does not, and `true` is returned.
+    /// Otherwise, including when the value is not an object, nothing is changed and `false` is
+    /// returned.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for config migrations")
+    )]
+    pub(crate) fn rename_key(&mut self, from: &str, to: &str) -> bool {
+        let Some(map) = self.as_object_mut() else {
+            return false;
+        };
+        if map.contains_key(to) {
+            return false;
+        }
+        match map.remove(from) {
+            Some(value) => {
+                map.insert(to.to_owned(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Convert the value to a JSON value, skipping uninitialized values
+    ///
+    /// Unlike serialization, which fails on uninitialized values, input and optional values are
+    /// omitted from objects and arrays. If the value itself is uninitialized, `null` is returned.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for a config dump command")
+    )]
+    pub(crate) fn to_json_lenient(&self) -> serde_json::Value {
+        use serde_json::Value as JsonValue;
+
+        fn convert(value: &MAAValue) -> JsonValue {
+            match value {
+                MAAValue::Primate(v) => serde_json::to_value(v).unwrap_or_default(),
+                MAAValue::Array(array) => array
+                    .iter()
+                    .filter(|v| !v.is_uninitialized())
+                    .map(convert)
+                    .collect(),
+                MAAValue::Object(map) => JsonValue::Object(
+                    map.iter()
+                        .filter(|(_, v)| !v.is_uninitialized())
+                        .map(|(k, v)| (k.clone(), convert(v)))
+                        .collect(),
+                ),
+                MAAValue::Input(_) | MAAValue::Optional { .. } => JsonValue::Null,
+            }
+        }
+
+        convert(self)
+    }
+
+    /// Clone the value with sensitive string values masked, e.g. before logging it
+    ///
+    /// Every string value under a key matching one of `keys` case-insensitively is replaced with
+    /// `"***"`, including strings in nested objects and arrays under the key. Values of other
+    /// types are kept as is.
+    pub fn redacted(&self, keys: &[&str]) -> Self {
+        let is_sensitive = |segment: &walk::PathSegment| match segment {
+            walk::PathSegment::Key(key) => keys.iter().any(|k| k.eq_ignore_ascii_case(key)),
+            walk::PathSegment::Index(_) => false,
+        };
+
+        let mut value = self.clone();
+        value.walk_mut(&mut |path, value| {
+            if let Self::Primate(MAAPrimate::String(s)) = value {
+                if path.iter().any(is_sensitive) {
+                    *s = "***".to_owned();
+                }
+            }
+        });
+        value
+    }
+
+    fn is_uninitialized(&self) -> bool {
+        matches!(self, Self::Input(_) | Self::Optional { .. })
+    }
+
+    /// Retain only the entries of the object specified by the predicate
+    ///
+    /// Remove all entries for which `f` returns `false`. Nested objects are not filtered unless
+    /// the predicate does so. If the value is not an object, do nothing.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for stripping internal keys")
+    )]
+    pub(crate) fn retain(&mut self, mut f: impl FnMut(&str, &Self) -> bool) {
+        if let Some(map) = self.as_object_mut() {
+            map.retain(|key, value| f(key, value));
+        }
+    }
+
+    /// Transform all primate values in the value by given function
+    ///
+    /// Objects and arrays are traversed recursively. Input and optional values are left
+    /// untouched, so call `init` first if they should be transformed as well.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for normalizing params")
+    )]
+    pub(crate) fn map_values(self, mut f: impl FnMut(MAAPrimate) -> MAAPrimate) -> Self {
+        fn apply(value: MAAValue, f: &mut impl FnMut(MAAPrimate) -> MAAPrimate) -> MAAValue {
+            match value {
+                MAAValue::Primate(v) => MAAValue::Primate(f(v)),
+                MAAValue::Array(array) => {
+                    MAAValue::Array(array.into_iter().map(|v| apply(v, f)).collect())
+                }
+                MAAValue::Object(map) => {
+                    MAAValue::Object(map.into_iter().map(|(k, v)| (k, apply(v, f))).collect())
+                }
+                value => value,
+            }
+        }
+
+        apply(self, &mut f)
+    }
+
     /// Get the value if the value is primate
     ///
     /// A primate value can be a bool, int, float or string.
@@ -295,6 +735,25 @@ impl MAAValue {
         self.as_primate().and_then(MAAPrimate::as_int)
     }
 
+    /// Convert the value to `i64` if the value is primate int
+    ///
+    /// Note: integers are stored as `i32`, this only widens the stored value, so values out of
+    /// range of `i32` can not be represented.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_primate().and_then(MAAPrimate::as_i64)
+    }
+
+    /// Convert the value to `u64` if the value is primate int and not negative
+    ///
+    /// Same as `as_i64`, integers are stored as `i32`.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for params beyond the range of i32")
+    )]
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        self.as_primate().and_then(MAAPrimate::as_u64)
+    }
+
     /// Convert the value to float if the value is primate float
     pub fn as_float(&self) -> Option<f32> {
         self.as_primate().and_then(MAAPrimate::as_float)
@@ -307,21 +766,336 @@ impl MAAValue {
 
     /// Merge other value into self
     ///
-    /// Both self and other should be an object.
+    /// Both self and other should be an object. Arrays are replaced instead of merged,
+    /// see `merge_with` for other strategies.
     pub fn merge_mut(&mut self, other: &Self) {
+        self.merge_with(other, ArrayMergeStrategy::Replace)
+    }
+
+    /// Merge other value into self with given options
+    ///
+    /// Objects are merged recursively, and the array strategy is applied to arrays at any depth.
+    /// Other values in self are replaced by the values in other.
+    ///
+    /// If `null_as_delete` is enabled, a null value of a key in an object of other removes the
+    /// key from the corresponding object of self, or does nothing if the key is not in self,
+    /// and this applies to objects in other which are not in self as well. Null values in
+    /// arrays, or other itself being null, are still stored as values.
+    pub fn merge_with(&mut self, other: &Self, options: impl Into<MergeOptions>) {
+        self.merge_with_options(other, options.into())
+    }
+
+    fn merge_with_options(&mut self, other: &Self, options: MergeOptions) {
         match (self, other) {
             (Self::Object(self_map), Self::Object(other_map)) => {
                 for (key, value) in other_map {
-                    if let Some(self_value) = self_map.get_mut(key) {
-                        self_value.merge_mut(value);
-                    } else {
-                        self_map.insert(key.clone(), value.clone());
+                    if options.null_as_delete {
+                        if let Self::Primate(MAAPrimate::Null) = value {
+                            self_map.remove(key);
+                            continue;
+                        }
+                    }
+
+                    match self_map.get_mut(key) {
+                        Some(self_value) => self_value.merge_with_options(value, options),
+                        None if options.null_as_delete && value.as_object().is_some() => self_map
+                            .entry(key.clone())
+                            .or_insert_with(Self::new)
+                            .merge_with_options(value, options),
+                        None => {
+                            self_map.insert(key.clone(), value.clone());
+                        }
                     }
                 }
             }
+            (Self::Array(self_array), Self::Array(other_array)) => match options.array_strategy {
+                ArrayMergeStrategy::Replace => self_array.clone_from(other_array),
+                ArrayMergeStrategy::Append => self_array.extend(other_array.iter().cloned()),
+                ArrayMergeStrategy::ConcatUnique => {
+                    for value in other_array {
+                        // Uninitialized inputs are never considered equal
+                        if !self_array.iter().any(|v| v.eq_default(value)) {
+                            self_array.push(value.clone());
+                        }
+                    }
+                }
+            },
             (s, o) => *s = o.clone(),
         }
     }
+
+    /// Resolve references to named fragments
+    ///
+    /// An object with a `ref` key, e.g. `{ "ref": "my_fight" }`, is a reference to the fragment
+    /// with the given name in `definitions`. The reference is replaced by the fragment, and other
+    /// keys in the object are merged into the fragment, so they can override values in it.
+    /// Fragments can reference other fragments, references are resolved recursively.
+    ///
+    /// # Errors
+    ///
+    /// Return an `InvalidData` error if the value of `ref` is not a string, the referenced
+    /// fragment is not defined, or a circular reference is found.
+    pub fn resolve_refs(&mut self, definitions: &Map<String, MAAValue>) -> io::Result<()> {
+        self.resolve_refs_inner(definitions, &mut Vec::new())
+    }
+
+    fn resolve_refs_inner(
+        &mut self,
+        definitions: &Map<String, MAAValue>,
+        stack: &mut Vec<String>,
+    ) -> io::Result<()> {
+        fn invalid(msg: String) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, msg)
+        }
+
+        match self {
+            Self::Object(map) => {
+                let Some(reference) = map.remove("ref") else {
+                    for value in map.values_mut() {
+                        value.resolve_refs_inner(definitions, stack)?;
+                    }
+                    return Ok(());
+                };
+
+                let name = reference
+                    .as_str()
+                    .ok_or_else(|| invalid("value of ref must be a string".to_owned()))?
+                    .to_owned();
+                if stack.contains(&name) {
+                    return Err(invalid(format!(
+                        "circular reference: {} -> {name}",
+                        stack.join(" -> ")
+                    )));
+                }
+                let mut resolved = definitions
+                    .get(&name)
+                    .ok_or_else(|| invalid(format!("reference to undefined fragment `{name}`")))?
+                    .clone();
+
+                let mut overrides = Self::Object(std::mem::take(map));
+                overrides.resolve_refs_inner(definitions, stack)?;
+
+                stack.push(name);
+                resolved.resolve_refs_inner(definitions, stack)?;
+                stack.pop();
+
+                resolved.merge_mut(&overrides);
+                *self = resolved;
+            }
+            Self::Array(array) => {
+                for value in array {
+                    value.resolve_refs_inner(definitions, stack)?;
+                }
+            }
+            Self::Optional { value, .. } => value.0.resolve_refs_inner(definitions, stack)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Get the value without keys whose values equal to the defaults in schema
+    ///
+    /// The schema is a template value, where the default of a primate value is itself, and the
+    /// default of an input value is its default value (an input without default value has no
+    /// default). Optional values in schema are treated as their inner values.
+    ///
+    /// Objects are compared recursively, and a nested object whose keys are all default is
+    /// omitted. Keys not in schema are always kept. If self or schema is not an object, self is
+    /// returned as is.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "configs are not written back yet")
+    )]
+    pub(crate) fn minus_defaults(&self, schema: &Self) -> Self {
+        fn minus(value: &MAAValue, schema: &MAAValue) -> MAAValue {
+            let (Some(map), Some(schema_map)) =
+                (value.as_object(), schema.skip_optional().as_object())
+            else {
+                return value.clone();
+            };
+
+            let mut ret = Map::new();
+            for (key, value) in map {
+                let Some(schema_value) = schema_map.get(key) else {
+                    ret.insert(key.clone(), value.clone());
+                    continue;
+                };
+                let schema_value = schema_value.skip_optional();
+
+                if value.as_object().is_some() && schema_value.as_object().is_some() {
+                    let diff = minus(value, schema_value);
+                    if diff.as_object().is_some_and(|m| !m.is_empty()) {
+                        ret.insert(key.clone(), diff);
+                    }
+                } else if !value.eq_default(schema_value) {
+                    ret.insert(key.clone(), value.clone());
+                }
+            }
+
+            MAAValue::Object(ret)
+        }
+
+        minus(self, schema)
+    }
+
+    /// Compute the changes from self to other
+    ///
+    /// Return an object containing only keys whose values differ: keys added or changed in other
+    /// have the values in other, and keys removed in other are `null`. Nested objects are
+    /// compared recursively, while arrays and primate values are compared as a whole. Merging
+    /// the diff into self results in other, except that removed keys are `null` instead of absent.
+    ///
+    /// If self or other is not an object, other is returned as is.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for showing profile changes")
+    )]
+    pub(crate) fn diff(&self, other: &Self) -> Self {
+        fn diff(value: &MAAValue, other: &MAAValue) -> MAAValue {
+            let (Some(map), Some(other_map)) = (value.as_object(), other.as_object()) else {
+                return other.clone();
+            };
+
+            let mut ret = Map::new();
+            for (key, value) in map {
+                match other_map.get(key) {
+                    None => {
+                        ret.insert(key.clone(), MAAPrimate::Null.into());
+                    }
+                    Some(other_value)
+                        if value.as_object().is_some() && other_value.as_object().is_some() =>
+                    {
+                        let diff = diff(value, other_value);
+                        if !diff.is_empty() {
+                            ret.insert(key.clone(), diff);
+                        }
+                    }
+                    Some(other_value) if !other_value.eq_default(value) => {
+                        ret.insert(key.clone(), other_value.clone());
+                    }
+                    Some(_) => {}
+                }
+            }
+            for (key, value) in other_map {
+                if !map.contains_key(key) {
+                    ret.insert(key.clone(), value.clone());
+                }
+            }
+
+            MAAValue::Object(ret)
+        }
+
+        diff(self, other)
+    }
+
+    /// Check if every key-value pair in subset is present in self
+    ///
+    /// Objects are matched recursively and extra keys in self are ignored, while arrays and other
+    /// values should be equal. Both values should be initialized first.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "reserved for validating user configs")
+    )]
+    pub(crate) fn contains_subset(&self, subset: &Self) -> bool {
+        fn contains(value: &MAAValue, subset: &MAAValue) -> bool {
+            match (value, subset) {
+                (MAAValue::Object(map), MAAValue::Object(subset)) => subset
+                    .iter()
+                    .all(|(key, value)| map.get(key).is_some_and(|v| contains(v, value))),
+                _ => value.eq_default(subset),
+            }
+        }
+
+        contains(self, subset)
+    }
+
+    /// Get the inner value if the value is optional, otherwise return self
+    fn skip_optional(&self) -> &Self {
+        let mut value = self;
+        while let Self::Optional { value: inner, .. } = value {
+            value = &inner.0;
+        }
+        value
+    }
+
+    /// Check if the value equals to the default value in schema
+    fn eq_default(&self, schema: &Self) -> bool {
+        use MAAValue::*;
+        match (self, schema.skip_optional()) {
+            (Primate(v), Primate(d)) => v == d,
+            (Primate(v), Input(input)) => input.default_primate().is_some_and(|d| v == &d),
+            (Array(v), Array(d)) => {
+                v.len() == d.len() && v.iter().zip(d).all(|(v, d)| v.eq_default(d))
+            }
+            (Object(v), Object(d)) => {
+                v.len() == d.len()
+                    && v.iter()
+                        .all(|(k, v)| d.get(k).is_some_and(|d| v.eq_default(d)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Normalize the case of keys to the canonical ones in schema
+    ///
+    /// Keys are case-sensitive by default, this method is an optional step to fix keys whose
+    /// casing differs from the schema (e.g. `Stage` instead of `stage`). A key is renamed only if
+    /// it matches exactly one key in schema case-insensitively and the canonical key is not
+    /// already present. Nested objects are normalized recursively. Every correction is logged as
+    /// a warning.
+    ///
+    /// Both self and schema should be objects, otherwise nothing will be done.
+    ///
+    /// # Returns
+    ///
+    /// Keys not found in schema, nested keys are joined with `.`.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "params are not normalized by the CLI yet")
+    )]
+    pub(crate) fn normalize_keys(&mut self, schema: &Self) -> Vec<String> {
+        let mut unknown = Vec::new();
+        self.normalize_keys_inner(schema, "", &mut unknown);
+        unknown
+    }
+
+    fn normalize_keys_inner(&mut self, schema: &Self, prefix: &str, unknown: &mut Vec<String>) {
+        let (Some(map), Some(schema_map)) = (self.as_object_mut(), schema.as_object()) else {
+            return;
+        };
+
+        let keys: Vec<String> = map.keys().cloned().collect();
+        for key in keys {
+            let canonical = if schema_map.contains_key(&key) {
+                Some(key.clone())
+            } else {
+                let mut candidates = schema_map
+                    .keys()
+                    .filter(|k| k.eq_ignore_ascii_case(&key) && !map.contains_key(*k));
+                match (candidates.next(), candidates.next()) {
+                    (Some(k), None) => {
+                        log::warn!("Key `{prefix}{key}` is corrected to `{prefix}{k}`");
+                        let value = map.remove(&key).unwrap();
+                        map.insert(k.clone(), value);
+                        Some(k.clone())
+                    }
+                    _ => None,
+                }
+            };
+
+            match canonical {
+                Some(key) => {
+                    let schema_value = schema_map[&key].skip_optional();
+                    let prefix = format!("{prefix}{key}.");
+                    map.get_mut(&key)
+                        .unwrap()
+                        .normalize_keys_inner(schema_value, &prefix, unknown);
+                }
+                None => unknown.push(format!("{prefix}{key}")),
+            }
+        }
+    }
 }
 
 #[macro_export]
@@ -347,37 +1121,306 @@ impl MAAValue {
 ///     "optional_chian" if "optional" == true => 1,
 /// );
 /// ```
+///
+/// Besides `==`, conditions can compare the value of the dependency with `!=`, `<`, `<=`, `>` and
+/// `>=`, see [`Operator`] for details:
+///
+/// ```
+/// let object = object!("medicine" => 2, "expiring" if "medicine" > 0 => true);
+/// ```
+///
+/// Literal keys should be unique, a key appearing twice, even with different conditions, is a
+/// compile error because the last entry would silently override the former one.
+///
+/// Keys can be computed at runtime by wrapping an expression, which should be `Into<String>`, in
+/// brackets. Keys of conditions should always be string literals:
+///
+/// ```
+/// let stage = "1-7";
+/// let object = object!([stage] => 1, [format!("{stage}_times")] if "1-7" == 1 => 2);
+/// ```
+///
+/// Entries with `=>?` take an `Option` as value. By default, the entry is skipped if the value is
+/// `None`, and with `?? default`, the default value is inserted instead:
+///
+/// ```
+/// let medicine: Option<i32> = None;
+/// let object = object!("medicine" =>? medicine, "stone" =>? medicine ?? 0);
+/// ```
+///
+/// Entries can be produced from an iterator by `for pattern in iter => entry`, where `entry` is an
+/// expression of a tuple `(K, V)`, with `K: Into<String>` and `V: Into<MAAValue>`, evaluated for
+/// every item:
+///
+/// ```
+/// let drops = vec![("30011".to_owned(), 10), ("30012".to_owned(), 5)];
+/// let object = object!("stage" => "1-7", for (k, v) in drops => (k, v * 2));
+/// ```
+///
+/// An existing object can be spread into the new one with `..base`, which is merged into the
+/// object in order, so the entries after it override the entries of base:
+///
+/// ```
+/// let defaults = object!("stage" => "1-7", "medicine" => 0);
+/// let object = object!(..defaults, "stage" => "CE-6");
+/// ```
 macro_rules! object {
     () => {
         $crate::value::MAAValue::new()
     };
-    ($($key:literal $(if $($cond_key:literal == $expected:expr),*)? => $value:expr),* $(,)?) => {{
+    ($($entries:tt)+) => {{
         let mut object = $crate::value::MAAValue::new();
-        $(
-            let value = $value;
-            $(
-                let mut conditions = $crate::value::Map::new();
-                $(
-                    conditions.insert($cond_key.into(), $expected.into());
-                )*
-                let value = $crate::value::MAAValue::Optional { conditions, value: value.into() };
-            )?
-            object.insert($key, value);
-        )*
+        $crate::object_entries!(object []; $($entries)+);
         object
     }};
 }
 
-impl Default for MAAValue {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl<const N: usize, S: Into<String>, V: Into<MAAValue>> From<[(S, V); N]> for MAAValue {
-    fn from(value: [(S, V); N]) -> Self {
-        Self::Object(Map::from(value.map(|(k, v)| (k.into(), v.into()))))
-    }
+#[doc(hidden)]
+#[macro_export]
+/// Insert entries of `object!` into an object one by one
+macro_rules! object_entries {
+    (@insert $object:ident, $key:expr, $([$($cond_key:literal $op:tt $expected:expr),*])?, $value:expr) => {
+        let value = $value;
+        $(
+            let mut conditions = $crate::value::Map::new();
+            $(
+                conditions.insert(
+                    $cond_key.into(),
+                    $crate::value::Condition::new($crate::object_entries!(@op $op), $expected),
+                );
+            )*
+            let value = $crate::value::MAAValue::Optional {
+                conditions: $crate::value::Conditions::new(conditions),
+                value: value.into(),
+            };
+        )?
+        $object.insert($key, value);
+    };
+    (@op ==) => { $crate::value::Operator::Eq };
+    (@op !=) => { $crate::value::Operator::Ne };
+    (@op <) => { $crate::value::Operator::Lt };
+    (@op <=) => { $crate::value::Operator::Le };
+    (@op >) => { $crate::value::Operator::Gt };
+    (@op >=) => { $crate::value::Operator::Ge };
+    (@op $op:tt) => {
+        compile_error!(concat!("unsupported operator `", stringify!($op), "` in condition"))
+    };
+    // Collect tokens of an optional value until `??` or the end of the entry
+    (
+        @maybe $object:ident [$($seen:literal)*] ($key:expr) ($($conds:tt)*) [$($value:tt)+]
+        ?? $default:expr $(, $($rest:tt)*)?
+    ) => {
+        $crate::object_entries!(@insert $object, $key, $($conds)*, match $($value)+ {
+            Some(value) => Into::<$crate::value::MAAValue>::into(value),
+            None => Into::<$crate::value::MAAValue>::into($default),
+        });
+        $crate::object_entries!($object [$($seen)*]; $($($rest)*)?);
+    };
+    (
+        @maybe $object:ident [$($seen:literal)*] ($key:expr) ($($conds:tt)*) [$($value:tt)+]
+        $(, $($rest:tt)*)?
+    ) => {
+        if let Some(value) = $($value)+ {
+            $crate::object_entries!(@insert $object, $key, $($conds)*, value);
+        }
+        $crate::object_entries!($object [$($seen)*]; $($($rest)*)?);
+    };
+    (
+        @maybe $object:ident [$($seen:literal)*] ($key:expr) ($($conds:tt)*) [$($value:tt)*]
+        $next:tt $($rest:tt)*
+    ) => {
+        $crate::object_entries!(
+            @maybe $object [$($seen)*] ($key) ($($conds)*) [$($value)* $next] $($rest)*
+        );
+    };
+    // Literal keys are collected to check duplicates at compile time
+    ($object:ident [$($seen:literal)*];) => {
+        const _: () = $crate::value::assert_unique_keys(&[$($seen),*]);
+    };
+    ($object:ident [$($seen:literal)*]; .. $base:expr $(, $($rest:tt)*)?) => {
+        $object.merge_mut(&$base);
+        $crate::object_entries!($object [$($seen)*]; $($($rest)*)?);
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        for $pat:pat in $iter:expr => $entry:expr $(, $($rest:tt)*)?
+    ) => {
+        for $pat in $iter {
+            let (key, value) = $entry;
+            $object.insert(key, value);
+        }
+        $crate::object_entries!($object [$($seen)*]; $($($rest)*)?);
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        $key:literal $(if $($cond_key:literal $op:tt $expected:expr),*)? =>? $($rest:tt)+
+    ) => {
+        $crate::object_entries!(
+            @maybe $object [$($seen)* $key] ($key) ($([$($cond_key $op $expected),*])?) []
+            $($rest)+
+        );
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        [$key:expr] $(if $($cond_key:literal $op:tt $expected:expr),*)? =>? $($rest:tt)+
+    ) => {
+        $crate::object_entries!(
+            @maybe $object [$($seen)*] ($key) ($([$($cond_key $op $expected),*])?) []
+            $($rest)+
+        );
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        $key:literal $(if $($cond_key:literal $op:tt $expected:expr),*)? => $value:expr
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::object_entries!(
+            @insert $object, $key, $([$($cond_key $op $expected),*])?, $value
+        );
+        $crate::object_entries!($object [$($seen)* $key]; $($($rest)*)?);
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        [$key:expr] $(if $($cond_key:literal $op:tt $expected:expr),*)? => $value:expr
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::object_entries!(
+            @insert $object, $key, $([$($cond_key $op $expected),*])?, $value
+        );
+        $crate::object_entries!($object [$($seen)*]; $($($rest)*)?);
+    };
+    (
+        $object:ident [$($seen:literal)*];
+        $key:tt if $($cond_key:literal $op:tt $expected:expr,)* [$($computed:tt)*] $($rest:tt)*
+    ) => {
+        compile_error!("keys of conditions should be string literals, computed keys are not supported");
+    };
+    ($object:ident [$($seen:literal)*]; $($invalid:tt)*) => {
+        compile_error!(concat!(
+            "invalid entry of object!, expected `\"key\" => value`, `\"key\" =>? value`, `[key] => value`, `..base` or `for item in iter => (key, value)`, found: ",
+            stringify!($($invalid)*),
+        ));
+    };
+}
+
+/// Panic if there are duplicate keys, used by `object!` and `schema!` to check literal keys at
+/// compile time
+#[doc(hidden)]
+pub const fn assert_unique_keys(keys: &[&str]) {
+    const fn str_eq(a: &str, b: &str) -> bool {
+        let (a, b) = (a.as_bytes(), b.as_bytes());
+        if a.len() != b.len() {
+            return false;
+        }
+        let mut i = 0;
+        while i < a.len() {
+            if a[i] != b[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    let mut i = 0;
+    while i < keys.len() {
+        let mut j = i + 1;
+        while j < keys.len() {
+            if str_eq(keys[i], keys[j]) {
+                panic!("duplicate key in object literal");
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+#[macro_export]
+/// A convenient macro to create a MAAValue::Array
+///
+/// Unlike converting from an array, elements can be of different types, each of which is
+/// converted into MAAValue.
+///
+/// # Examples
+/// ```
+/// let array = array![1, "two", object!("k" => 3)];
+/// ```
+macro_rules! array {
+    ($($value:expr),* $(,)?) => {
+        $crate::value::MAAValue::Array(vec![$($value.into()),*])
+    };
+}
+
+#[macro_export]
+/// A convenient macro to merge a value into an existing object
+///
+/// The target is a place expression of a MAAValue, and the other value is merged into it by
+/// `merge_mut`, so nested objects are merged and other values are replaced.
+///
+/// # Examples
+/// ```
+/// let mut params = object!("stage" => "1-7");
+/// merge!(params, object!("medicine" => 1));
+/// ```
+macro_rules! merge {
+    ($target:expr, $other:expr $(,)?) => {
+        $target.merge_mut(&($other))
+    };
+}
+
+#[macro_export]
+/// A convenient macro to insert entries into an existing object only if a condition is true
+///
+/// The condition is evaluated once when the macro is executed, unlike the conditions of optional
+/// values which depend on other values of the object. Entries are in the same form as `object!`.
+///
+/// # Examples
+/// ```
+/// let mut params = object!("stage" => "1-7");
+/// insert_if!(params, use_medicine, "medicine" => 1, "expiring_medicine" => 1);
+/// ```
+macro_rules! insert_if {
+    ($target:expr, $cond:expr, $($entries:tt)+) => {
+        if $cond {
+            let object: &mut $crate::value::MAAValue = &mut $target;
+            $crate::object_entries!(object []; $($entries)+);
+        }
+    };
+}
+
+/// Split a JSON Pointer into unescaped reference tokens
+fn pointer_tokens(ptr: &str) -> Option<impl Iterator<Item = Cow<'_, str>>> {
+    if !ptr.is_empty() && !ptr.starts_with('/') {
+        return None;
+    }
+    // The first item is always empty, the part before the leading `/` or the empty pointer
+    Some(ptr.split('/').skip(1).map(|token| {
+        if token.contains('~') {
+            Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+        } else {
+            Cow::Borrowed(token)
+        }
+    }))
+}
+
+/// Parse an array index of a JSON Pointer, leading zeros are not allowed
+fn parse_index(token: &str) -> Option<usize> {
+    if token.starts_with('0') && token.len() > 1 || token.starts_with('+') {
+        return None;
+    }
+    token.parse().ok()
+}
+
+impl Default for MAAValue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, S: Into<String>, V: Into<MAAValue>> From<[(S, V); N]> for MAAValue {
+    fn from(value: [(S, V); N]) -> Self {
+        Self::Object(Map::from(value.map(|(k, v)| (k.into(), v.into()))))
+    }
 }
 
 impl<const N: usize, T: Into<MAAValue>> From<[T; N]> for MAAValue {
@@ -392,9 +1435,64 @@ impl<T: Into<MAAValue>> From<Vec<T>> for MAAValue {
     }
 }
 
+/// Convert a JSON value to a MAAValue
+///
+/// Numbers are converted to integers if they are integral and fit in `i32`, otherwise floats.
+impl From<serde_json::Value> for MAAValue {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value as JsonValue;
+        match value {
+            JsonValue::Null => MAAPrimate::Null.into(),
+            JsonValue::Bool(v) => v.into(),
+            JsonValue::Number(n) => match n.as_i64().and_then(|n| i32::try_from(n).ok()) {
+                Some(n) => n.into(),
+                None => (n.as_f64().unwrap_or_default() as f32).into(),
+            },
+            JsonValue::String(v) => v.into(),
+            JsonValue::Array(array) => array.into(),
+            JsonValue::Object(map) => {
+                Self::Object(map.into_iter().map(|(k, v)| (k, v.into())).collect())
+            }
+        }
+    }
+}
+
+/// Convert a MAAValue to a JSON value
+///
+/// Like serialization, the value should be initialized first.
+impl TryFrom<MAAValue> for serde_json::Value {
+    type Error = io::Error;
+
+    fn try_from(value: MAAValue) -> Result<Self, Self::Error> {
+        serde_json::to_value(&value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Display the value as pretty printed JSON
+///
+/// Keys of objects are sorted, so the output is stable. The value should be initialized first,
+/// otherwise the serialization error is displayed in angle brackets instead.
+impl std::fmt::Display for MAAValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_string_pretty(self) {
+            Ok(s) => f.write_str(&s),
+            Err(e) => write!(f, "<{e}>"),
+        }
+    }
+}
+
 /// Try to convert the value to given type
 ///
 /// If the value is not convertible to the type, None will be returned.
+///
+/// Integers are stored as `i32` and floats as `f32`, other numeric types are converted from them
+/// only when the conversion is lossless:
+/// - `i64` is widened from an integer;
+/// - `u32` is converted from a non-negative integer;
+/// - `f64` is widened from a float or converted from an integer, which is exact for any `i32`.
+///
+/// Unlike `f64`, `f32` is not converted from an integer, and integer types are never converted
+/// from a float.
 pub trait TryFromMAAValue<'a>: Sized {
     type Value;
 
@@ -425,6 +1523,33 @@ impl TryFromMAAValue<'_> for f32 {
     }
 }
 
+impl TryFromMAAValue<'_> for i64 {
+    type Value = Self;
+
+    fn try_from_value(value: &MAAValue) -> Option<Self::Value> {
+        value.as_i64()
+    }
+}
+
+impl TryFromMAAValue<'_> for u32 {
+    type Value = Self;
+
+    fn try_from_value(value: &MAAValue) -> Option<Self::Value> {
+        value.as_int().and_then(|v| u32::try_from(v).ok())
+    }
+}
+
+impl TryFromMAAValue<'_> for f64 {
+    type Value = Self;
+
+    fn try_from_value(value: &MAAValue) -> Option<Self::Value> {
+        value
+            .as_float()
+            .map(f64::from)
+            .or_else(|| value.as_int().map(f64::from))
+    }
+}
+
 impl<'a> TryFromMAAValue<'a> for &str {
     type Value = &'a str;
 
@@ -433,6 +1558,26 @@ impl<'a> TryFromMAAValue<'a> for &str {
     }
 }
 
+impl TryFromMAAValue<'_> for String {
+    type Value = Self;
+
+    fn try_from_value(value: &MAAValue) -> Option<Self::Value> {
+        value.as_str().map(str::to_owned)
+    }
+}
+
+/// Convert an array value, if any element is not convertible, None will be returned.
+impl<'a, T: TryFromMAAValue<'a>> TryFromMAAValue<'a> for Vec<T> {
+    type Value = Vec<T::Value>;
+
+    fn try_from_value(value: &'a MAAValue) -> Option<Self::Value> {
+        match value {
+            MAAValue::Array(array) => array.iter().map(T::try_from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use userinput::{BoolInput, Input, SelectD};
@@ -714,6 +1859,159 @@ mod tests {
         assert_eq!(value.init().unwrap_err().kind(), io::ErrorKind::InvalidData);
     }
 
+    #[test]
+    fn iter() {
+        let mut value = object!("b" => 2, "c" => 3, "a" => 1);
+
+        assert_eq!(
+            value
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_int().unwrap()))
+                .collect::<Vec<_>>(),
+            [("a", 1), ("b", 2), ("c", 3)]
+        );
+
+        for (_, v) in value.iter_mut() {
+            *v = (v.as_int().unwrap() * 10).into();
+        }
+        assert_eq!(value, object!("a" => 10, "b" => 20, "c" => 30));
+
+        assert_eq!(MAAValue::from(1).iter().count(), 0);
+        assert_eq!(MAAValue::from([1, 2]).iter_mut().count(), 0);
+    }
+
+    #[test]
+    fn init_with_operators() {
+        let value: MAAValue = serde_json::from_str(
+            r#"{
+                "stage_count": 5,
+                "stage": "1-7",
+                "gt": { "conditions": { "stage_count": { "gt": 3 } }, "default": 1 },
+                "le": { "conditions": { "stage_count": { "le": 3 } }, "default": 1 },
+                "ne": { "conditions": { "stage": { "ne": "CE-6" } }, "default": 1 },
+                "mismatch": { "conditions": { "stage": { "gt": 3 } }, "default": 1 }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value.init().unwrap(),
+            object!(
+                "stage_count" => 5,
+                "stage" => "1-7",
+                "gt" => 1,
+                "ne" => 1,
+            )
+        );
+    }
+
+    #[test]
+    fn object_macro_operators() {
+        let value = object!(
+            "stage_count" => 5,
+            "stage" => "1-7",
+            "eq" if "stage_count" == 5 => 1,
+            "ne" if "stage" != "CE-6" => 1,
+            "lt" if "stage_count" < 5 => 1,
+            "le" if "stage_count" <= 5 => 1,
+            "gt" if "stage_count" > 3, "stage" == "1-7" => 1,
+            "ge" if "stage_count" >= 6 => 1,
+        );
+
+        let MAAValue::Optional { conditions, .. } = value.get("gt").unwrap() else {
+            panic!("expected optional value")
+        };
+        assert_eq!(
+            conditions,
+            &Conditions::new(Map::from([
+                ("stage_count".to_owned(), Condition::new(Operator::Gt, 3)),
+                ("stage".to_owned(), Condition::new(Operator::Eq, "1-7")),
+            ]))
+        );
+
+        assert_eq!(
+            value.init().unwrap(),
+            object!(
+                "stage_count" => 5,
+                "stage" => "1-7",
+                "eq" => 1,
+                "ne" => 1,
+                "le" => 1,
+                "gt" => 1,
+            )
+        );
+    }
+
+    #[test]
+    fn init_with_any_of() {
+        let value: MAAValue = serde_json::from_str(
+            r#"{
+                "client_type": "Bilibili",
+                "stage": "1-7",
+                "or": {
+                    "any_of": [{ "client_type": "Official" }, { "client_type": "Bilibili" }],
+                    "default": 1
+                },
+                "and_or": {
+                    "conditions": { "stage": "1-7" },
+                    "any_of": [{ "client_type": "YoStarEN" }, { "client_type": "Bilibili" }],
+                    "default": 1
+                },
+                "and_unsatisfied": {
+                    "deps": { "stage": "CE-6" },
+                    "any_of": [{ "client_type": "Bilibili" }],
+                    "default": 1
+                },
+                "or_unsatisfied": {
+                    "any_of": [
+                        { "client_type": "Official" },
+                        { "client_type": "Bilibili", "stage": "CE-6" }
+                    ],
+                    "default": 1
+                },
+                "chained": {
+                    "any_of": [{ "or": 1 }],
+                    "default": 1
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value.init().unwrap(),
+            object!(
+                "client_type" => "Bilibili",
+                "stage" => "1-7",
+                "or" => 1,
+                "and_or" => 1,
+                "chained" => 1,
+            )
+        );
+
+        let circular: MAAValue = serde_json::from_str(
+            r#"{
+                "a": { "any_of": [{ "c": 1 }, { "b": 1 }], "default": 1 },
+                "b": { "any_of": [{ "a": 1 }], "default": 1 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            circular.init().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn as_integer() {
+        assert_eq!(MAAValue::from(i32::MAX).as_i64(), Some(i32::MAX as i64));
+        assert_eq!(MAAValue::from(i32::MIN).as_i64(), Some(i32::MIN as i64));
+        assert_eq!(MAAValue::from(i32::MAX).as_u64(), Some(i32::MAX as u64));
+        assert_eq!(MAAValue::from(0).as_u64(), Some(0));
+        assert_eq!(MAAValue::from(-1).as_u64(), None);
+        assert_eq!(MAAValue::from("1").as_i64(), None);
+        assert_eq!(MAAValue::new().as_u64(), None);
+    }
+
     #[test]
     fn get() {
         let value = MAAValue::from([("int", 1)]);
@@ -723,7 +2021,7 @@ mod tests {
         assert_eq!(MAAValue::from(1).get("int"), None);
 
         assert_eq!(value.get_or("int", 2), 1);
-        assert_eq!(value.get_or("int", 2.0), 2.0);
+        assert_eq!(value.get_or("int", 2.0f32), 2.0);
         assert_eq!(value.get_or("float", 2.0), 2.0);
 
         let mut value = object!("int" => 1);
@@ -736,98 +2034,835 @@ mod tests {
     }
 
     #[test]
-    fn insert() {
-        let mut value = MAAValue::new();
-        assert_eq!(value.get("int"), None);
-        value.insert("int", 1);
-        assert_eq!(value.get("int").unwrap().as_int().unwrap(), 1);
+    fn len() {
+        assert_eq!(MAAValue::new().len(), 0);
+        assert!(MAAValue::new().is_empty());
+        assert_eq!(MAAValue::Array(Vec::new()).len(), 0);
+        assert!(MAAValue::Array(Vec::new()).is_empty());
+
+        let value = object!("int" => 1, "array" => [1, 2, 3]);
+        assert_eq!(value.len(), 2);
+        assert!(!value.is_empty());
+        assert_eq!(value.get("array").unwrap().len(), 3);
+
+        assert_eq!(MAAValue::from("").len(), 1);
+        assert!(!MAAValue::from("").is_empty());
+        assert_eq!(MAAValue::from(BoolInput::new(None, None)).len(), 1);
     }
 
     #[test]
-    #[should_panic(expected = "value is not an object")]
-    fn insert_panics() {
-        let mut value = MAAValue::from(1);
-        value.insert("int", 1);
-    }
+    fn leaf_count_and_depth() {
+        // Flat
+        let value = object!("stage" => "1-7", "medicine" => 0, "report" => true);
+        assert_eq!(value.leaf_count(), 3);
+        assert_eq!(value.depth(), 2);
 
-    #[test]
-    fn maybe_insert() {
-        let mut value = MAAValue::new();
-        assert_eq!(value.get("int"), None);
-        value.maybe_insert("int", Some(1));
-        assert_eq!(value.get("int").unwrap().as_int().unwrap(), 1);
-        value.maybe_insert("float", None::<f32>);
-        assert_eq!(value.get("float"), None);
+        // Nested
+        let value = object!(
+            "stage" => "1-7",
+            "stages" => ["1-7", "CE-6"],
+            "connection" => object!(
+                "config" => object!("type" => "General", "extras" => MAAValue::new()),
+                "address" => Input::<String>::new(None, None),
+            ),
+        );
+        assert_eq!(value.leaf_count(), 5);
+        assert_eq!(value.depth(), 4);
+
+        let mut deep = MAAValue::from(1);
+        for _ in 0..10 {
+            deep = MAAValue::from([deep]);
+        }
+        assert_eq!(deep.leaf_count(), 1);
+        assert_eq!(deep.depth(), 11);
+
+        assert_eq!(MAAValue::from(1).leaf_count(), 1);
+        assert_eq!(MAAValue::from(1).depth(), 1);
+        assert_eq!(MAAValue::new().leaf_count(), 0);
+        assert_eq!(MAAValue::new().depth(), 1);
+        assert_eq!(MAAValue::Array(Vec::new()).depth(), 1);
     }
 
     #[test]
-    fn value_from_others() {
-        // Array
+    fn get_path() {
+        let mut value = object!(
+            "connection" => object!(
+                "config" => object!("type" => "General"),
+                "address" => "localhost:5555",
+            ),
+            "a.b" => 1,
+        );
+
         assert_eq!(
-            MAAValue::from([1, 2]),
-            MAAValue::Array(vec![1.into(), 2.into()])
+            value.get_path("connection.config.type").unwrap().as_str(),
+            Some("General")
         );
         assert_eq!(
-            MAAValue::from(vec![1, 2]),
-            MAAValue::Array(vec![1.into(), 2.into()])
+            value.get_path("connection.address").unwrap().as_str(),
+            Some("localhost:5555")
+        );
+        assert_eq!(value.get_path("connection.config.name"), None);
+        assert_eq!(value.get_path("connection.address.port"), None);
+        assert_eq!(value.get_path("a.b"), None);
+        assert_eq!(value.get_path(""), None);
+
+        *value.get_path_mut("connection.config.type").unwrap() = "ADB".into();
+        assert_eq!(
+            value.get_path("connection.config.type").unwrap().as_str(),
+            Some("ADB")
         );
+        assert_eq!(value.get_path_mut("connection.address.port"), None);
+        assert_eq!(value.get_path_mut(""), None);
     }
 
     #[test]
-    fn try_from_value() {
-        // Bool
-        assert_eq!(bool::try_from_value(&true.into()), Some(true));
-        assert_eq!(i32::try_from_value(&true.into()), None);
-        assert_eq!(
-            bool::try_from_value(&BoolInput::new(Some(true), None).into()),
-            None
+    fn set_path() {
+        let mut value = object!(
+            "connection" => object!("address" => "localhost:5555"),
+            "stage" => "1-7",
         );
 
-        // Int
-        assert_eq!(i32::try_from_value(&1.into()), Some(1));
-        assert_eq!(f32::try_from_value(&1.into()), None);
-        assert_eq!(i32::try_from_value(&Input::new(Some(1), None).into()), None);
+        // Overwrite a leaf
+        value
+            .set_path("connection.address", "127.0.0.1:5555")
+            .unwrap();
+        // Create new nesting
+        value.set_path("connection.config.type", "ADB").unwrap();
+        value.set_path("medicine", 1).unwrap();
+        assert_eq!(
+            value,
+            object!(
+                "connection" => object!(
+                    "address" => "127.0.0.1:5555",
+                    "config" => object!("type" => "ADB"),
+                ),
+                "stage" => "1-7",
+                "medicine" => 1,
+            )
+        );
 
-        // Float
-        assert_eq!(f32::try_from_value(&1.0.into()), Some(1.0));
-        assert_eq!(i32::try_from_value(&1.0.into()), None);
+        // Conflicting intermediate
+        let err = value.set_path("stage.name", "CE-6").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), "value of `stage` is not an object");
+        let err = value.set_path("connection.address.port", 5555).unwrap_err();
         assert_eq!(
-            f32::try_from_value(&Input::new(Some(1.0), None).into()),
-            None
+            err.to_string(),
+            "value of `connection.address` is not an object"
         );
+        assert_eq!(value.get("stage").unwrap().as_str(), Some("1-7"));
 
-        // String
-        assert_eq!(<&str>::try_from_value(&"string".into()), Some("string"));
-        assert_eq!(bool::try_from_value(&"string".into()), None);
+        let err = MAAValue::from(1).set_path("a", 1).unwrap_err();
+        assert_eq!(err.to_string(), "value is not an object");
+        let err = value.set_path("", 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     }
 
     #[test]
-    fn merge() {
-        let value = object!(
-            "bool" => true,
-            "int" => 1,
-            "float" => 1.0,
-            "string" => "string",
-            "array" => [1, 2],
-            "object" => object!(
-                "key1" => "value1",
-                "key2" => "value2",
-            ),
+    fn pointer() {
+        let mut value = object!(
+            "tasks" => MAAValue::Array(vec![
+                object!("type" => "StartUp", "params" => object!("client_type" => "Official")),
+                object!("type" => "Fight"),
+            ]),
+            "a/b" => 1,
+            "m~n" => 2,
+            "" => 3,
         );
 
-        let value2 = object!(
-            "bool" => false,
-            "int" => 2,
-            "array" => [3, 4],
-            "object" => object!(
-                "key2" => "value2_2",
-                "key3" => "value3",
-            ),
+        assert_eq!(
+            value.pointer("").unwrap().get("a/b").unwrap().as_int(),
+            Some(1)
         );
-
         assert_eq!(
-            value.merge(&value2),
-            object!(
+            value.pointer("/tasks/0/type").unwrap().as_str(),
+            Some("StartUp")
+        );
+        assert_eq!(
+            value
+                .pointer("/tasks/0/params/client_type")
+                .unwrap()
+                .as_str(),
+            Some("Official")
+        );
+        assert_eq!(
+            value.pointer("/tasks/1/type").unwrap().as_str(),
+            Some("Fight")
+        );
+        assert_eq!(value.pointer("/a~1b").unwrap().as_int(), Some(1));
+        assert_eq!(value.pointer("/m~0n").unwrap().as_int(), Some(2));
+        assert_eq!(value.pointer("/").unwrap().as_int(), Some(3));
+
+        assert_eq!(value.pointer("tasks"), None);
+        assert_eq!(value.pointer("/tasks/2"), None);
+        assert_eq!(value.pointer("/tasks/01"), None);
+        assert_eq!(value.pointer("/tasks/-"), None);
+        assert_eq!(value.pointer("/tasks/type"), None);
+        assert_eq!(value.pointer("/tasks/0/type/0"), None);
+        assert_eq!(value.pointer("/a/b"), None);
+
+        *value.pointer_mut("/tasks/1/type").unwrap() = "Recruit".into();
+        assert_eq!(
+            value.pointer("/tasks/1/type").unwrap().as_str(),
+            Some("Recruit")
+        );
+        assert_eq!(value.pointer_mut("/tasks/2"), None);
+    }
+
+    #[test]
+    fn flatten() {
+        let value = object!(
+            "stage" => "1-7",
+            "drops" => object!("30011" => 10, "report" => true),
+            "stages" => ["1-7", "CE-6"],
+            "tasks" => MAAValue::Array(vec![object!("type" => "Fight")]),
+            "empty" => MAAValue::new(),
+        );
+
+        assert_eq!(
+            value.flatten().unwrap(),
+            Map::from([
+                ("stage".to_owned(), "1-7".into()),
+                ("drops.30011".to_owned(), 10.into()),
+                ("drops.report".to_owned(), true.into()),
+                ("stages[0]".to_owned(), "1-7".into()),
+                ("stages[1]".to_owned(), "CE-6".into()),
+                ("tasks[0].type".to_owned(), "Fight".into()),
+            ])
+        );
+
+        assert_eq!(
+            MAAValue::from(1).flatten().unwrap(),
+            Map::from([(String::new(), 1.into())])
+        );
+
+        let err = object!("drops" => object!("report" => BoolInput::new(None, None)))
+            .flatten()
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "value of `drops.report` is not initialized, call `init` first"
+        );
+    }
+
+    #[test]
+    fn contains_key() {
+        let value = object!("int" => 1);
+
+        assert!(value.contains_key("int"));
+        assert!(!value.contains_key("float"));
+        assert!(!MAAValue::from(1).contains_key("int"));
+        assert!(!MAAValue::from([1, 2]).contains_key("0"));
+    }
+
+    #[test]
+    fn insert() {
+        let mut value = MAAValue::new();
+        assert_eq!(value.get("int"), None);
+        value.insert("int", 1);
+        assert_eq!(value.get("int").unwrap().as_int().unwrap(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "value is not an object")]
+    fn insert_panics() {
+        let mut value = MAAValue::from(1);
+        value.insert("int", 1);
+    }
+
+    #[test]
+    fn maybe_insert() {
+        let mut value = MAAValue::new();
+        assert_eq!(value.get("int"), None);
+        value.maybe_insert("int", Some(1));
+        assert_eq!(value.get("int").unwrap().as_int().unwrap(), 1);
+        value.maybe_insert("float", None::<f32>);
+        assert_eq!(value.get("float"), None);
+    }
+
+    #[test]
+    fn remove() {
+        let mut value = object!("int" => 1, "float" => 1.0);
+
+        assert_eq!(value.remove("int").unwrap().as_int().unwrap(), 1);
+        assert_eq!(value.get("int"), None);
+        assert_eq!(value, object!("float" => 1.0));
+
+        assert_eq!(value.remove("int"), None);
+        assert_eq!(value, object!("float" => 1.0));
+
+        assert_eq!(MAAValue::from(1).remove("int"), None);
+    }
+
+    #[test]
+    fn map_values() {
+        let value = object!(
+            "stage" => " 1-7 ",
+            "medicine" => 1,
+            "stages" => [" 1-7", "CE-6 "],
+            "drops" => object!("name" => "\tOrirock\n"),
+            "client_type" => Input::<String>::new(Some(" Official ".to_owned()), None),
+        );
+
+        let trimmed = value.map_values(|v| match v {
+            MAAPrimate::String(s) => MAAPrimate::String(s.trim().to_owned()),
+            v => v,
+        });
+        assert_eq!(
+            trimmed,
+            object!(
+                "stage" => "1-7",
+                "medicine" => 1,
+                "stages" => ["1-7", "CE-6"],
+                "drops" => object!("name" => "Orirock"),
+                "client_type" => Input::<String>::new(Some(" Official ".to_owned()), None),
+            )
+        );
+
+        let mut count = 0;
+        let value = MAAValue::from([1, 2, 3]).map_values(|v| {
+            count += 1;
+            v
+        });
+        assert_eq!(count, 3);
+        assert_eq!(value, MAAValue::from([1, 2, 3]));
+    }
+
+    #[test]
+    fn rename_key() {
+        let mut value = object!("stage" => "1-7", "medicine" => 1, "stone" => 0);
+
+        // Successful rename
+        assert!(value.rename_key("medicine", "use_medicine"));
+        assert_eq!(
+            value,
+            object!("stage" => "1-7", "use_medicine" => 1, "stone" => 0)
+        );
+
+        // Collision
+        assert!(!value.rename_key("stone", "stage"));
+        assert_eq!(
+            value,
+            object!("stage" => "1-7", "use_medicine" => 1, "stone" => 0)
+        );
+
+        // Missing source key
+        assert!(!value.rename_key("medicine", "expiring"));
+        assert!(!value.contains_key("expiring"));
+
+        assert!(!MAAValue::from(1).rename_key("stage", "stages"));
+    }
+
+    #[test]
+    fn redacted() {
+        let value = object!(
+            "address" => "127.0.0.1:5555",
+            "stage" => "1-7",
+            "report" => object!(
+                "Token" => "secret",
+                "servers" => ["https://penguin-stats.io"],
+            ),
+            "accounts" => [object!("name" => "Amiya", "password" => "secret")],
+            "token" => 42,
+        );
+
+        assert_eq!(
+            value.redacted(&["address", "TOKEN", "password", "servers"]),
+            object!(
+                "address" => "***",
+                "stage" => "1-7",
+                "report" => object!("Token" => "***", "servers" => ["***"]),
+                "accounts" => [object!("name" => "Amiya", "password" => "***")],
+                "token" => 42,
+            )
+        );
+        // The original value is untouched
+        assert_eq!(
+            value.get("address").unwrap().as_str(),
+            Some("127.0.0.1:5555")
+        );
+        assert_eq!(value.redacted(&[]), value);
+    }
+
+    #[test]
+    fn retain() {
+        let mut value = object!(
+            "_internal" => true,
+            "stage" => "1-7",
+            "medicine" => 1,
+            "drops" => object!("_internal" => true),
+        );
+
+        value.retain(|key, _| !key.starts_with('_'));
+        assert_eq!(
+            value,
+            object!(
+                "stage" => "1-7",
+                "medicine" => 1,
+                "drops" => object!("_internal" => true),
+            )
+        );
+
+        value.retain(|_, value| value.as_str().is_none());
+        assert_eq!(
+            value,
+            object!(
+                "medicine" => 1,
+                "drops" => object!("_internal" => true),
+            )
+        );
+
+        let mut value = MAAValue::from(1);
+        value.retain(|_, _| false);
+        assert_eq!(value, MAAValue::from(1));
+    }
+
+    #[test]
+    fn object_spread() {
+        let defaults = object!(
+            "stage" => "1-7",
+            "medicine" => 0,
+            "drops" => object!("30011" => 10),
+        );
+
+        // Later entries win
+        assert_eq!(
+            object!(..defaults, "stage" => "CE-6"),
+            object!(
+                "stage" => "CE-6",
+                "medicine" => 0,
+                "drops" => object!("30011" => 10),
+            )
+        );
+        // Spread is applied in order, so it overrides earlier entries
+        assert_eq!(
+            object!("stage" => "CE-6", "stone" => 1, ..defaults),
+            object!(
+                "stage" => "1-7",
+                "medicine" => 0,
+                "stone" => 1,
+                "drops" => object!("30011" => 10),
+            )
+        );
+        // Nested objects are merged
+        assert_eq!(
+            object!(
+                "medicine" => 1,
+                ..object!("drops" => object!("30012" => 5)),
+                ..defaults.clone(),
+            ),
+            object!(
+                "stage" => "1-7",
+                "medicine" => 0,
+                "drops" => object!("30011" => 10, "30012" => 5),
+            )
+        );
+        assert_eq!(object!(..defaults), defaults);
+    }
+
+    #[test]
+    fn object_computed_key() {
+        let stage = "1-7";
+        let times = String::from("times");
+
+        let value = object!(
+            "stage" => stage,
+            [stage] => 1,
+            [format!("{stage}_{times}")] if "stage" == "1-7" => 2,
+            [times.clone()] if "stage" == "CE-6" => 3,
+        );
+        assert_eq!(
+            value,
+            object!(
+                "stage" => "1-7",
+                "1-7" => 1,
+                "1-7_times" if "stage" == "1-7" => 2,
+                "times" if "stage" == "CE-6" => 3,
+            )
+        );
+        assert_eq!(
+            value.init().unwrap(),
+            object!("stage" => "1-7", "1-7" => 1, "1-7_times" => 2)
+        );
+    }
+
+    #[test]
+    fn unique_keys() {
+        assert_unique_keys(&[]);
+        assert_unique_keys(&["stage", "stages", "medicine", ""]);
+    }
+
+    #[test]
+    #[should_panic(expected = "duplicate key in object literal")]
+    fn duplicate_keys() {
+        assert_unique_keys(&["stage", "medicine", "stage"]);
+    }
+
+    #[test]
+    fn object_optional_entries() {
+        let some = Some("CE-6");
+        let none: Option<&str> = None;
+        let stage = "stage";
+
+        assert_eq!(
+            object!(
+                "some" =>? some,
+                "none" =>? none,
+                "some_or" =>? some ?? "1-7",
+                "none_or" =>? none ?? "1-7",
+                "none_or_int" =>? none ?? 0,
+                "call" =>? some.map(|s| s.len()).map(|n| n as i32) ?? 0,
+                [format!("{stage}_or")] =>? none ?? 1,
+            ),
+            object!(
+                "some" => "CE-6",
+                "some_or" => "CE-6",
+                "none_or" => "1-7",
+                "none_or_int" => 0,
+                "call" => 4,
+                "stage_or" => 1,
+            )
+        );
+
+        // Compose with conditions
+        let value = object!(
+            "medicine" => 1,
+            "stage" if "medicine" > 0 =>? none ?? "1-7",
+            "stages" if "medicine" > 0 =>? none,
+            "stone" if "medicine" > 1 =>? some,
+        );
+        assert_eq!(
+            value,
+            object!(
+                "medicine" => 1,
+                "stage" if "medicine" > 0 => "1-7",
+                "stone" if "medicine" > 1 => "CE-6",
+            )
+        );
+        assert_eq!(
+            value.init().unwrap(),
+            object!("medicine" => 1, "stage" => "1-7")
+        );
+    }
+
+    #[test]
+    fn object_for_entries() {
+        let drops: Vec<(String, i32)> = vec![("30011".to_owned(), 10), ("30012".to_owned(), 5)];
+
+        assert_eq!(
+            object!(
+                "stage" => "1-7",
+                for (k, v) in drops.iter() => (k.as_str(), *v),
+                "medicine" => 1,
+                for (i, stage) in ["CE-6", "SL-8"].into_iter().enumerate() => (
+                    format!("stage{i}"),
+                    object!("stage" => stage),
+                ),
+            ),
+            object!(
+                "stage" => "1-7",
+                "30011" => 10,
+                "30012" => 5,
+                "medicine" => 1,
+                "stage0" => object!("stage" => "CE-6"),
+                "stage1" => object!("stage" => "SL-8"),
+            )
+        );
+        assert_eq!(
+            object!(for (k, v) in drops => (k, v)),
+            object!("30011" => 10, "30012" => 5)
+        );
+    }
+
+    #[test]
+    fn array_macro() {
+        assert_eq!(array![], MAAValue::Array(vec![]));
+        assert_eq!(array![1, 2,], MAAValue::from([1, 2]));
+        assert_eq!(
+            array![1, "two", 3.0, object!("k" => 3), array![true]],
+            MAAValue::Array(vec![
+                1.into(),
+                "two".into(),
+                3.0.into(),
+                object!("k" => 3),
+                MAAValue::from([true]),
+            ])
+        );
+    }
+
+    #[test]
+    fn merge_macro() {
+        let mut params = object!(
+            "stage" => "1-7",
+            "drops" => object!("30011" => 10),
+            "config" => object!("drops" => object!("30011" => 10)),
+        );
+        merge!(
+            params,
+            object!("medicine" => 1, "drops" => object!("30012" => 5))
+        );
+        merge!(
+            params.get_mut("config").unwrap(),
+            object!("drops" => object!("30011" => 5)),
+        );
+
+        assert_eq!(
+            params,
+            object!(
+                "stage" => "1-7",
+                "medicine" => 1,
+                "drops" => object!("30011" => 10, "30012" => 5),
+                "config" => object!("drops" => object!("30011" => 5)),
+            )
+        );
+    }
+
+    #[test]
+    fn insert_if_macro() {
+        let mut params = object!("stage" => "1-7");
+        let mut evaluated = 0;
+        let mut cond = |value: bool| {
+            evaluated += 1;
+            value
+        };
+
+        insert_if!(params, cond(false), "medicine" => 1);
+        assert_eq!(params, object!("stage" => "1-7"));
+
+        insert_if!(
+            params,
+            cond(true),
+            "medicine" => 1,
+            "stone" if "medicine" > 0 => 1,
+            "drops" => object!("30011" => 10),
+        );
+        insert_if!(
+            params.get_mut("drops").unwrap(),
+            cond(true),
+            "30012" => 5,
+        );
+        assert_eq!(evaluated, 3);
+
+        assert_eq!(
+            params,
+            object!(
+                "stage" => "1-7",
+                "medicine" => 1,
+                "stone" if "medicine" > 0 => 1,
+                "drops" => object!("30011" => 10, "30012" => 5),
+            )
+        );
+    }
+
+    #[test]
+    fn value_from_others() {
+        // Array
+        assert_eq!(
+            MAAValue::from([1, 2]),
+            MAAValue::Array(vec![1.into(), 2.into()])
+        );
+        assert_eq!(
+            MAAValue::from(vec![1, 2]),
+            MAAValue::Array(vec![1.into(), 2.into()])
+        );
+    }
+
+    #[test]
+    fn json_value() {
+        use serde_json::{json, Value as JsonValue};
+
+        let json = json!({
+            "stage": "1-7",
+            "medicine": 2,
+            "ratio": 0.5,
+            "report": true,
+            "clear": null,
+            "stages": ["1-7", "CE-6"],
+            "drops": { "30011": 10 },
+        });
+
+        let value = MAAValue::from(json.clone());
+        assert_eq!(
+            value,
+            object!(
+                "stage" => "1-7",
+                "medicine" => 2,
+                "ratio" => 0.5,
+                "report" => true,
+                "clear" => MAAPrimate::Null,
+                "stages" => ["1-7", "CE-6"],
+                "drops" => object!("30011" => 10),
+            )
+        );
+        assert_eq!(JsonValue::try_from(value).unwrap(), json);
+
+        // Integers out of range of i32 are converted to floats
+        assert_eq!(
+            MAAValue::from(json!(4294967296u64)),
+            MAAValue::from(4294967296.0)
+        );
+
+        let err = JsonValue::try_from(object!("report" => BoolInput::new(None, None))).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn to_json_lenient() {
+        use serde_json::json;
+
+        let value = object!(
+            "stage" => "1-7",
+            "medicine" => Input::<i32>::new(Some(0), None),
+            "expiring" if "medicine" == 0 => true,
+            "stages" => MAAValue::Array(vec!["1-7".into(), BoolInput::new(None, None).into()]),
+            "drops" => object!("30011" => 10, "report" => BoolInput::new(None, None)),
+        );
+
+        assert!(serde_json::to_value(&value).is_err());
+        assert_eq!(
+            value.to_json_lenient(),
+            json!({
+                "stage": "1-7",
+                "stages": ["1-7"],
+                "drops": { "30011": 10 },
+            })
+        );
+        assert_eq!(
+            MAAValue::from(BoolInput::new(None, None)).to_json_lenient(),
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn display() {
+        let value = object!(
+            "stage" => "1-7",
+            "medicine" => 0,
+            "drops" => object!("30011" => 10),
+            "stages" => ["1-7", "CE-6"],
+        );
+        assert_eq!(
+            value.to_string(),
+            r#"{
+  "drops": {
+    "30011": 10
+  },
+  "medicine": 0,
+  "stage": "1-7",
+  "stages": [
+    "1-7",
+    "CE-6"
+  ]
+}"#
+        );
+        assert_eq!(MAAValue::from(1.5).to_string(), "1.5");
+
+        assert_eq!(
+            object!("report" => BoolInput::new(None, None)).to_string(),
+            "<cannot serialize input value, you should initialize it first>"
+        );
+    }
+
+    #[test]
+    fn try_from_value() {
+        // Bool
+        assert_eq!(bool::try_from_value(&true.into()), Some(true));
+        assert_eq!(i32::try_from_value(&true.into()), None);
+        assert_eq!(
+            bool::try_from_value(&BoolInput::new(Some(true), None).into()),
+            None
+        );
+
+        // Int
+        assert_eq!(i32::try_from_value(&1.into()), Some(1));
+        assert_eq!(f32::try_from_value(&1.into()), None);
+        assert_eq!(i32::try_from_value(&Input::new(Some(1), None).into()), None);
+
+        // Float
+        assert_eq!(f32::try_from_value(&1.0.into()), Some(1.0));
+        assert_eq!(i32::try_from_value(&1.0.into()), None);
+        assert_eq!(
+            f32::try_from_value(&Input::new(Some(1.0), None).into()),
+            None
+        );
+
+        // Widened numbers
+        assert_eq!(i64::try_from_value(&i32::MAX.into()), Some(i32::MAX as i64));
+        assert_eq!(i64::try_from_value(&i32::MIN.into()), Some(i32::MIN as i64));
+        assert_eq!(i64::try_from_value(&1.0.into()), None);
+        assert_eq!(u32::try_from_value(&0.into()), Some(0));
+        assert_eq!(u32::try_from_value(&i32::MAX.into()), Some(i32::MAX as u32));
+        assert_eq!(u32::try_from_value(&(-1).into()), None);
+        assert_eq!(u32::try_from_value(&1.0.into()), None);
+        assert_eq!(f64::try_from_value(&1.5.into()), Some(1.5));
+        assert_eq!(f64::try_from_value(&1.into()), Some(1.0));
+        assert_eq!(f64::try_from_value(&i32::MAX.into()), Some(i32::MAX as f64));
+        assert_eq!(f64::try_from_value(&i32::MIN.into()), Some(i32::MIN as f64));
+        assert_eq!(f64::try_from_value(&"1".into()), None);
+        assert_eq!(
+            MAAValue::from([("medicine", 999)]).get_or("medicine", 0u32),
+            999
+        );
+        assert_eq!(
+            MAAValue::from([("medicine", -1)]).get_or("medicine", 0u32),
+            0
+        );
+
+        // String
+        assert_eq!(<&str>::try_from_value(&"string".into()), Some("string"));
+        assert_eq!(bool::try_from_value(&"string".into()), None);
+        assert_eq!(
+            String::try_from_value(&"string".into()),
+            Some("string".to_owned())
+        );
+        assert_eq!(String::try_from_value(&1.into()), None);
+
+        // Array
+        let value = object!(
+            "stages" => ["1-7", "CE-6"],
+            "counts" => [1, 2, 3],
+            "mixed" => MAAValue::Array(vec![1.into(), "1-7".into()]),
+            "empty" => MAAValue::Array(Vec::new()),
+        );
+        assert_eq!(value.get_or("counts", Vec::<i32>::new()), [1, 2, 3]);
+        assert_eq!(value.get_or("stages", Vec::<String>::new()), [
+            "1-7", "CE-6"
+        ]);
+        assert_eq!(value.get_or("stages", Vec::<&str>::new()), ["1-7", "CE-6"]);
+        assert_eq!(value.get_or("mixed", vec![0]), [0]);
+        assert_eq!(value.get_or("empty", vec![0]), Vec::<i32>::new());
+        assert_eq!(Vec::<i32>::try_from_value(&1.into()), None);
+        assert_eq!(Vec::<i32>::try_from_value(&value), None);
+    }
+
+    #[test]
+    fn merge() {
+        let value = object!(
+            "bool" => true,
+            "int" => 1,
+            "float" => 1.0,
+            "string" => "string",
+            "array" => [1, 2],
+            "object" => object!(
+                "key1" => "value1",
+                "key2" => "value2",
+            ),
+        );
+
+        let value2 = object!(
+            "bool" => false,
+            "int" => 2,
+            "array" => [3, 4],
+            "object" => object!(
+                "key2" => "value2_2",
+                "key3" => "value3",
+            ),
+        );
+
+        assert_eq!(
+            value.merge(&value2),
+            object!(
                 "bool" => false,
                 "int" => 2,
                 "float" => 1.0,
@@ -840,5 +2875,352 @@ mod tests {
                 ),
             ),
         );
+
+        // null replaces the value instead of being skipped
+        let value3 = object!(
+            "string" => MAAPrimate::Null,
+            "object" => MAAPrimate::Null,
+        );
+        let merged = value.merge(&value3);
+        assert_eq!(merged.get("string").unwrap(), &MAAPrimate::Null);
+        assert_eq!(merged.get("object").unwrap(), &MAAPrimate::Null);
+        assert_eq!(
+            serde_json::to_string(&merged.get("string")).unwrap(),
+            "null"
+        );
+    }
+
+    #[test]
+    fn merge_with() {
+        let base = object!(
+            "stages" => ["1-7", "CE-6"],
+            "nested" => object!("stages" => ["1-7"]),
+            "int" => 1,
+        );
+        let other = object!(
+            "stages" => ["CE-6", "SL-8"],
+            "nested" => object!("stages" => ["1-7", "SL-8"]),
+            "int" => 2,
+        );
+
+        let merged = |strategy| {
+            let mut value = base.clone();
+            value.merge_with(&other, strategy);
+            value
+        };
+
+        assert_eq!(merged(ArrayMergeStrategy::Replace), base.merge(&other));
+        assert_eq!(
+            merged(ArrayMergeStrategy::Replace),
+            object!(
+                "stages" => ["CE-6", "SL-8"],
+                "nested" => object!("stages" => ["1-7", "SL-8"]),
+                "int" => 2,
+            )
+        );
+        assert_eq!(
+            merged(ArrayMergeStrategy::Append),
+            object!(
+                "stages" => ["1-7", "CE-6", "CE-6", "SL-8"],
+                "nested" => object!("stages" => ["1-7", "1-7", "SL-8"]),
+                "int" => 2,
+            )
+        );
+        assert_eq!(
+            merged(ArrayMergeStrategy::ConcatUnique),
+            object!(
+                "stages" => ["1-7", "CE-6", "SL-8"],
+                "nested" => object!("stages" => ["1-7", "SL-8"]),
+                "int" => 2,
+            )
+        );
+    }
+
+    #[test]
+    fn merge_null_as_delete() {
+        let base = object!(
+            "stage" => "1-7",
+            "stages" => ["1-7", "CE-6"],
+            "drops" => object!("30011" => 10, "30012" => 5),
+        );
+        let other = object!(
+            "stage" => MAAPrimate::Null,
+            "stages" => [MAAPrimate::Null],
+            "drops" => object!("30012" => MAAPrimate::Null, "30013" => 1),
+            "report" => MAAPrimate::Null,
+            "server" => object!("type" => MAAPrimate::Null, "name" => "CN"),
+        );
+
+        // Null is stored by default
+        let mut value = base.clone();
+        value.merge_with(&other, MergeOptions::default());
+        assert_eq!(value, base.merge(&other));
+        assert_eq!(value.get("report").unwrap(), &MAAPrimate::Null);
+
+        let mut value = base.clone();
+        value.merge_with(
+            &other,
+            MergeOptions::default()
+                .with_array_strategy(ArrayMergeStrategy::Append)
+                .with_null_as_delete(true),
+        );
+        assert_eq!(
+            value,
+            object!(
+                // null in arrays is stored
+                "stages" => MAAValue::Array(vec!["1-7".into(), "CE-6".into(), MAAPrimate::Null.into()]),
+                // nested key is deleted
+                "drops" => object!("30011" => 10, "30013" => 1),
+                // deleting non-existent keys is no-op
+                "server" => object!("name" => "CN"),
+            )
+        );
+
+        // Null itself is stored as a value
+        let mut value = base.clone();
+        value.merge_with(
+            &MAAPrimate::Null.into(),
+            MergeOptions::default().with_null_as_delete(true),
+        );
+        assert_eq!(value, MAAPrimate::Null);
+    }
+
+    #[test]
+    fn contains_subset() {
+        let value = object!(
+            "stage" => "1-7",
+            "medicine" => 0,
+            "stages" => ["1-7", "CE-6"],
+            "drops" => object!("30011" => 10, "30012" => 5),
+        );
+
+        // Matching subsets
+        assert!(value.contains_subset(&MAAValue::new()));
+        assert!(value.contains_subset(&value));
+        assert!(value.contains_subset(&object!("stage" => "1-7")));
+        assert!(value.contains_subset(&object!(
+            "medicine" => 0,
+            "drops" => object!("30011" => 10),
+        )));
+        assert!(value.contains_subset(&object!("drops" => MAAValue::new())));
+
+        // Missing keys
+        assert!(!value.contains_subset(&object!("report" => true)));
+        assert!(!value.contains_subset(&object!("drops" => object!("30013" => 1))));
+
+        // Mismatched values
+        assert!(!value.contains_subset(&object!("medicine" => 1)));
+        assert!(!value.contains_subset(&object!("drops" => object!("30011" => 5))));
+        assert!(!value.contains_subset(&object!("drops" => 10)));
+        // Arrays are compared as a whole
+        assert!(!value.contains_subset(&object!("stages" => ["1-7"])));
+
+        assert!(MAAValue::from(1).contains_subset(&1.into()));
+        assert!(!MAAValue::from(1).contains_subset(&MAAValue::new()));
+    }
+
+    #[test]
+    fn diff() {
+        let defaults = object!(
+            "stage" => "1-7",
+            "medicine" => 0,
+            "stages" => ["1-7", "CE-6"],
+            "drops" => object!("30011" => 10, "30012" => 5),
+            "report" => true,
+        );
+        let profile = object!(
+            "stage" => "1-7",
+            "medicine" => 2,
+            "stages" => ["1-7", "CE-6"],
+            "drops" => object!("30011" => 10, "30013" => 1),
+            "expiring" => true,
+        );
+
+        let diff = defaults.diff(&profile);
+        assert_eq!(
+            diff,
+            object!(
+                "medicine" => 2,
+                "drops" => object!("30012" => MAAPrimate::Null, "30013" => 1),
+                "report" => MAAPrimate::Null,
+                "expiring" => true,
+            )
+        );
+        assert_eq!(
+            defaults.merge(&diff),
+            object!(
+                "stage" => "1-7",
+                "medicine" => 2,
+                "stages" => ["1-7", "CE-6"],
+                "drops" => object!("30011" => 10, "30012" => MAAPrimate::Null, "30013" => 1),
+                "report" => MAAPrimate::Null,
+                "expiring" => true,
+            )
+        );
+
+        assert_eq!(defaults.diff(&defaults), MAAValue::new());
+        assert_eq!(
+            object!("stages" => ["1-7"]).diff(&object!("stages" => ["1-7", "CE-6"])),
+            object!("stages" => ["1-7", "CE-6"])
+        );
+        assert_eq!(
+            MAAValue::from(1).diff(&MAAValue::from(2)),
+            MAAValue::from(2)
+        );
+    }
+
+    #[test]
+    fn normalize_keys() {
+        let schema = object!(
+            "stage" => "1-7",
+            "medicine" => 0,
+            "drops" => object!("30011" => 0),
+            "expiring" if "medicine" == 1 => object!("enable" => true),
+        );
+
+        let mut value = object!(
+            "Stage" => "CE-6",
+            "medicine" => 1,
+            "DROPS" => object!("30011" => 10, "Extra" => 1),
+            "Expiring" => object!("ENABLE" => false),
+            "unknown" => 1,
+        );
+
+        assert_eq!(value.normalize_keys(&schema), vec![
+            "drops.Extra".to_string(),
+            "unknown".to_string(),
+        ]);
+        assert_eq!(
+            value,
+            object!(
+                "stage" => "CE-6",
+                "medicine" => 1,
+                "drops" => object!("30011" => 10, "Extra" => 1),
+                "expiring" => object!("enable" => false),
+                "unknown" => 1,
+            )
+        );
+
+        // Mis-cased key is not renamed if the canonical key is already present
+        let mut value = object!("stage" => "1-7", "Stage" => "CE-6");
+        assert_eq!(value.normalize_keys(&schema), vec!["Stage".to_string()]);
+        assert_eq!(value, object!("stage" => "1-7", "Stage" => "CE-6"));
+
+        // Non-object values are left untouched
+        let mut value = MAAValue::from(1);
+        assert!(value.normalize_keys(&schema).is_empty());
+        assert_eq!(value, MAAValue::from(1));
+    }
+
+    #[test]
+    fn resolve_refs() {
+        let definitions = Map::from([
+            (
+                "my_fight".to_string(),
+                object!("stage" => "1-7", "medicine" => 0),
+            ),
+            (
+                "my_fight_ce".to_string(),
+                object!("ref" => "my_fight", "stage" => "CE-6"),
+            ),
+            ("cycle_a".to_string(), object!("ref" => "cycle_b")),
+            ("cycle_b".to_string(), object!("ref" => "cycle_a")),
+        ]);
+
+        // simple ref
+        let mut value = object!("params" => object!("ref" => "my_fight"));
+        value.resolve_refs(&definitions).unwrap();
+        assert_eq!(
+            value,
+            object!("params" => object!("stage" => "1-7", "medicine" => 0))
+        );
+
+        // ref used twice, nested ref and override
+        let mut value = MAAValue::from([
+            object!("ref" => "my_fight", "medicine" => 1),
+            object!("ref" => "my_fight_ce"),
+        ]);
+        value.resolve_refs(&definitions).unwrap();
+        assert_eq!(
+            value,
+            MAAValue::from([
+                object!("stage" => "1-7", "medicine" => 1),
+                object!("stage" => "CE-6", "medicine" => 0),
+            ])
+        );
+
+        // missing ref
+        let err = object!("ref" => "not_exist")
+            .resolve_refs(&definitions)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "reference to undefined fragment `not_exist`"
+        );
+
+        // cyclic ref
+        let err = object!("ref" => "cycle_a")
+            .resolve_refs(&definitions)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "circular reference: cycle_a -> cycle_b -> cycle_a"
+        );
+
+        // invalid ref
+        assert!(object!("ref" => 1).resolve_refs(&definitions).is_err());
+    }
+
+    #[test]
+    fn minus_defaults() {
+        let schema = object!(
+            "stage" => Input::<String>::new(sstr("1-7"), None),
+            "medicine" => 0,
+            "series" => SelectD::<i32>::new([1, 2, 3], Some(1), None, false).unwrap(),
+            "report" => BoolInput::new(None, None),
+            "drops" => object!("30011" => 0, "30012" => 0),
+            "stages" => ["1-7"],
+            "expiring" if "medicine" == 1 => true,
+        );
+
+        assert_eq!(
+            object!(
+                "stage" => "1-7",
+                "medicine" => 0,
+                "series" => 1,
+                "drops" => object!("30011" => 0, "30012" => 0),
+                "stages" => ["1-7"],
+                "expiring" => true,
+            )
+            .minus_defaults(&schema),
+            object!()
+        );
+
+        assert_eq!(
+            object!(
+                "stage" => "CE-6",
+                "medicine" => 0,
+                "series" => 2,
+                "report" => false,
+                "drops" => object!("30011" => 10, "30012" => 0),
+                "stages" => ["1-7", "CE-6"],
+                "expiring" => false,
+                "unknown" => 1,
+            )
+            .minus_defaults(&schema),
+            object!(
+                "stage" => "CE-6",
+                "series" => 2,
+                "report" => false,
+                "drops" => object!("30011" => 10),
+                "stages" => ["1-7", "CE-6"],
+                "expiring" => false,
+                "unknown" => 1,
+            )
+        );
+
+        assert_eq!(MAAValue::from(1).minus_defaults(&schema), MAAValue::from(1));
     }
 }
@@ -6,6 +6,8 @@ use super::MAAValue;
 #[derive(Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum MAAPrimate {
+    /// An explicit `null`, e.g. to clear a field set before
+    Null,
     Bool(bool),
     Int(i32),
     Float(f32),
@@ -13,6 +15,17 @@ pub enum MAAPrimate {
 }
 
 impl MAAPrimate {
+    /// Name of the JSON type of this value
+    pub(super) fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "null",
+            Self::Bool(_) => "boolean",
+            Self::Int(_) => "integer",
+            Self::Float(_) => "number",
+            Self::String(_) => "string",
+        }
+    }
+
     pub(super) fn as_bool(&self) -> Option<bool> {
         match self {
             Self::Bool(v) => Some(*v),
@@ -27,6 +40,18 @@ impl MAAPrimate {
         }
     }
 
+    /// Widen the int to `i64`
+    ///
+    /// Integers are stored as `i32`, so the value is always in range of `i32`.
+    pub(super) fn as_i64(&self) -> Option<i64> {
+        self.as_int().map(i64::from)
+    }
+
+    /// Convert the int to `u64` if it is not negative
+    pub(super) fn as_u64(&self) -> Option<u64> {
+        self.as_int().and_then(|v| u64::try_from(v).ok())
+    }
+
     pub(super) fn as_float(&self) -> Option<f32> {
         match self {
             Self::Float(v) => Some(*v),
@@ -45,6 +70,7 @@ impl MAAPrimate {
 impl Serialize for MAAPrimate {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         match self {
+            Self::Null => serializer.serialize_unit(),
             Self::Bool(v) => serializer.serialize_bool(*v),
             Self::Int(v) => serializer.serialize_i32(*v),
             Self::Float(v) => serializer.serialize_f32(*v),
@@ -121,6 +147,7 @@ mod tests {
         use serde_test::{assert_de_tokens, Token};
 
         let values = vec![
+            MAAPrimate::Null,
             MAAPrimate::Bool(true),
             MAAPrimate::Int(1),
             MAAPrimate::Float(1.0),
@@ -128,7 +155,8 @@ mod tests {
         ];
 
         assert_de_tokens(&values, &[
-            Token::Seq { len: Some(4) },
+            Token::Seq { len: Some(5) },
+            Token::Unit,
             Token::Bool(true),
             Token::I32(1),
             Token::F32(1.0),
@@ -137,8 +165,20 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn serde_null() {
+        let value: MAAPrimate = serde_json::from_str("null").unwrap();
+        assert_eq!(value, MAAPrimate::Null);
+        assert_eq!(serde_json::to_string(&value).unwrap(), "null");
+    }
+
     #[test]
     fn as_type() {
+        assert_eq!(MAAPrimate::Null.as_bool(), None);
+        assert_eq!(MAAPrimate::Null.as_int(), None);
+        assert_eq!(MAAPrimate::Null.as_float(), None);
+        assert_eq!(MAAPrimate::Null.as_str(), None);
+
         assert_eq!(MAAPrimate::Bool(true).as_bool(), Some(true));
         assert_eq!(MAAPrimate::Bool(true).as_int(), None);
         assert_eq!(MAAPrimate::Bool(true).as_float(), None);
@@ -147,6 +187,12 @@ mod tests {
         assert_eq!(MAAPrimate::Int(1).as_bool(), None);
         assert_eq!(MAAPrimate::Int(1).as_int(), Some(1));
         assert_eq!(MAAPrimate::Int(1).as_float(), None);
+        assert_eq!(MAAPrimate::Int(i32::MAX).as_i64(), Some(i32::MAX as i64));
+        assert_eq!(MAAPrimate::Int(i32::MIN).as_i64(), Some(i32::MIN as i64));
+        assert_eq!(MAAPrimate::Int(i32::MAX).as_u64(), Some(i32::MAX as u64));
+        assert_eq!(MAAPrimate::Int(-1).as_u64(), None);
+        assert_eq!(MAAPrimate::Float(1.0).as_i64(), None);
+        assert_eq!(MAAPrimate::Float(1.0).as_u64(), None);
         assert_eq!(MAAPrimate::Int(1).as_str(), None);
 
         assert_eq!(MAAPrimate::Float(1.0).as_bool(), None);
@@ -0,0 +1,200 @@
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use super::MAAValue;
+
+impl MAAValue {
+    /// Infer a permissive JSON Schema describing the shape of the value
+    ///
+    /// Objects are described by the schemas of their keys, arrays by the schema of their first
+    /// element, and primate values by their JSON type. An empty array is described as an array
+    /// without constraint on its items. The schema is permissive: no key is required and extra keys
+    /// are allowed, so it can be used for autocompletion of values of the same shape.
+    ///
+    /// The value is expected to be initialized, an input value is described by the type it
+    /// will be resolved to, and an optional value by its inner value.
+    #[cfg_attr(not(test), expect(dead_code, reason = "no command emits schemas yet"))]
+    pub(crate) fn infer_schema(&self) -> JsonValue {
+        fn infer(value: &MAAValue) -> JsonValue {
+            match value {
+                MAAValue::Object(map) => {
+                    let properties: JsonMap<String, JsonValue> = map
+                        .iter()
+                        .map(|(key, value)| (key.clone(), infer(value)))
+                        .collect();
+                    json!({ "type": "object", "properties": properties })
+                }
+                MAAValue::Array(array) => match array.first() {
+                    Some(first) => json!({ "type": "array", "items": infer(first) }),
+                    None => json!({ "type": "array" }),
+                },
+                MAAValue::Primate(v) => json!({ "type": v.type_name() }),
+                MAAValue::Input(input) => json!({ "type": input.type_name() }),
+                MAAValue::Optional { value, .. } => infer(&value.0),
+            }
+        }
+
+        infer(self)
+    }
+}
+
+#[macro_export]
+/// A convenient macro to create a template of MAAValue::Object from types of keys
+///
+/// Each key is annotated with a type, which is one of `Bool`, `Int`, `Float`, `String` and `Null`,
+/// a nested object in braces, or an array of a type in brackets. Every value is the default of its
+/// type, so the template is only useful to infer a JSON Schema by `infer_schema`. Like `object!`,
+/// duplicate keys of the same object are rejected at compile time.
+///
+/// # Examples
+/// ```
+/// let schema = schema!(
+///     "stage": String,
+///     "times": Int,
+///     "drops": { "30011": Int },
+///     "stages": [String],
+/// )
+/// .infer_schema();
+/// ```
+macro_rules! schema {
+    (@type Bool) => { $crate::value::MAAValue::from(false) };
+    (@type Int) => { $crate::value::MAAValue::from(0) };
+    (@type Float) => { $crate::value::MAAValue::from(0.0) };
+    (@type String) => { $crate::value::MAAValue::from("") };
+    (@type Null) => { $crate::value::MAAValue::from($crate::value::MAAPrimate::Null) };
+    (@type { $($entries:tt)* }) => { $crate::schema!($($entries)*) };
+    (@type [$ty:tt]) => { $crate::value::MAAValue::Array(vec![$crate::schema!(@type $ty)]) };
+    (@type $ty:tt) => {
+        compile_error!(concat!(
+            "unknown type `", stringify!($ty),
+            "`, expected `Bool`, `Int`, `Float`, `String`, `Null`, `{ .. }` or `[..]`",
+        ))
+    };
+    () => {
+        $crate::value::MAAValue::new()
+    };
+    ($($key:literal : $ty:tt),+ $(,)?) => {{
+        const _: () = $crate::value::assert_unique_keys(&[$($key),+]);
+        let mut object = $crate::value::MAAValue::new();
+        $(
+            object.insert($key, $crate::schema!(@type $ty));
+        )*
+        object
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        object,
+        value::{userinput::Input, MAAPrimate},
+    };
+
+    #[test]
+    fn infer_schema() {
+        let value = object!(
+            "stage" => "1-7",
+            "medicine" => Input::<i32>::new(Some(0), None),
+            "drops" => object!("30011" => 10, "ratio" => 0.5),
+            "report" => true,
+            "null" => MAAPrimate::Null,
+        );
+
+        assert_eq!(
+            value.infer_schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "stage": { "type": "string" },
+                    "medicine": { "type": "integer" },
+                    "drops": {
+                        "type": "object",
+                        "properties": {
+                            "30011": { "type": "integer" },
+                            "ratio": { "type": "number" },
+                        },
+                    },
+                    "report": { "type": "boolean" },
+                    "null": { "type": "null" },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn schema_macro() {
+        let template = crate::schema!(
+            "stage": String,
+            "times": Int,
+            "ratio": Float,
+            "report": Bool,
+            "null": Null,
+            "drops": { "30011": Int, "nested": {} },
+            "stages": [String],
+            "matrix": [[Int]],
+        );
+
+        assert_eq!(
+            template,
+            object!(
+                "stage" => "",
+                "times" => 0,
+                "ratio" => 0.0,
+                "report" => false,
+                "null" => MAAPrimate::Null,
+                "drops" => object!("30011" => 0, "nested" => object!()),
+                "stages" => [""],
+                "matrix" => [MAAValue::from([0])],
+            )
+        );
+        assert_eq!(
+            template.infer_schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "stage": { "type": "string" },
+                    "times": { "type": "integer" },
+                    "ratio": { "type": "number" },
+                    "report": { "type": "boolean" },
+                    "null": { "type": "null" },
+                    "drops": {
+                        "type": "object",
+                        "properties": {
+                            "30011": { "type": "integer" },
+                            "nested": { "type": "object", "properties": {} },
+                        },
+                    },
+                    "stages": { "type": "array", "items": { "type": "string" } },
+                    "matrix": {
+                        "type": "array",
+                        "items": { "type": "array", "items": { "type": "integer" } },
+                    },
+                },
+            })
+        );
+        assert_eq!(crate::schema!(), MAAValue::new());
+    }
+
+    #[test]
+    fn infer_schema_array() {
+        assert_eq!(
+            MAAValue::Array(Vec::new()).infer_schema(),
+            json!({ "type": "array" })
+        );
+        assert_eq!(
+            MAAValue::from([object!("type" => "Fight")]).infer_schema(),
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": { "type": { "type": "string" } },
+                },
+            })
+        );
+        // Mixed arrays are typed by their first element
+        assert_eq!(
+            MAAValue::Array(vec![1.into(), "1-7".into()]).infer_schema(),
+            json!({ "type": "array", "items": { "type": "integer" } })
+        );
+    }
+}
@@ -13,6 +13,8 @@ pub struct BoolInput {
     default: Option<bool>,
     /// Description of this parameter
     description: Option<String>,
+    /// Help text printed above the prompt
+    help: Option<String>,
 }
 
 impl BoolInput {
@@ -20,8 +22,29 @@ impl BoolInput {
         Self {
             default,
             description: description.map(|s| s.to_string()),
+            help: None,
         }
     }
+
+    /// Set the help text printed above the prompt.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "help is only set by deserialization")
+    )]
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Get the default value of this input, if any.
+    pub fn default_value(&self) -> Option<bool> {
+        self.default
+    }
+
+    /// Get the description of this input, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
 }
 
 impl UserInput for BoolInput {
@@ -34,6 +57,10 @@ impl UserInput for BoolInput {
         }
     }
 
+    fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
     fn prompt(&self, writer: &mut impl Write) -> Result<(), io::Error> {
         write!(writer, "Whether to")?;
         if let Some(description) = &self.description {
@@ -113,6 +140,14 @@ mod tests {
             Token::MapEnd,
             Token::SeqEnd,
         ]);
+
+        assert_de_tokens(&BoolInput::new(None, None).with_help("help"), &[
+            Token::Map { len: Some(1) },
+            Token::Str("help"),
+            Token::Some,
+            Token::Str("help"),
+            Token::MapEnd,
+        ]);
     }
 
     #[test]
@@ -122,12 +157,14 @@ mod tests {
             BoolInput {
                 default: Some(true),
                 description: Some(description),
+                help: None,
             } if description == "do something"
         );
 
         assert_matches!(BoolInput::new(Some(true), None), BoolInput {
             default: Some(true),
             description: None,
+            help: None,
         });
 
         assert_matches!(
@@ -135,12 +172,14 @@ mod tests {
             BoolInput {
                 default: None,
                 description: Some(description),
+                help: None,
             } if description == "do something"
         );
 
         assert_matches!(BoolInput::new(None, None), BoolInput {
             default: None,
             description: None,
+            help: None,
         });
     }
 
@@ -23,6 +23,8 @@ pub struct Input<F> {
     default: Option<F>,
     /// Description of this parameter
     description: Option<String>,
+    /// Help text printed above the prompt
+    help: Option<String>,
 }
 
 impl<F> Input<F> {
@@ -30,8 +32,29 @@ impl<F> Input<F> {
         Self {
             default,
             description: description.map(|s| s.to_string()),
+            help: None,
         }
     }
+
+    /// Set the help text printed above the prompt.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "help is only set by deserialization")
+    )]
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Get the default value of this input, if any.
+    pub fn default_value(&self) -> Option<&F> {
+        self.default.as_ref()
+    }
+
+    /// Get the description of this input, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
 }
 
 impl<F: FromStr + Display + Clone> UserInput for Input<F> {
@@ -44,6 +67,10 @@ impl<F: FromStr + Display + Clone> UserInput for Input<F> {
         }
     }
 
+    fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
     fn prompt(&self, writer: &mut impl Write) -> io::Result<()> {
         write!(writer, "Please input")?;
         if let Some(description) = self.description.as_deref() {
@@ -123,29 +150,53 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn serde_help() {
+        assert_de_tokens(
+            &Input::new(Some(0), Some("medicine to use")).with_help("expiring first"),
+            &[
+                Token::Map { len: Some(3) },
+                Token::Str("default"),
+                Token::Some,
+                Token::I32(0),
+                Token::Str("description"),
+                Token::Some,
+                Token::Str("medicine to use"),
+                Token::Str("help"),
+                Token::Some,
+                Token::Str("expiring first"),
+                Token::MapEnd,
+            ],
+        );
+    }
+
     #[test]
     fn construct() {
         assert_matches!(
             Input::new(Some(0), Some("medicine to use")),
             Input::<i64> {
                 default: Some(0),
-                description: Some(s)
+                description: Some(s),
+                help: None,
             } if s == "medicine to use",
         );
         assert_matches!(
             Input::<i64>::new(None::<i64>, Some("medicine to use")),
             Input::<i64> {
                 default: None,
-                description: Some(s)
+                description: Some(s),
+                help: None,
             } if s == "medicine to use",
         );
         assert_matches!(Input::<i64>::new(Some(0), None::<&str>), Input::<i64> {
             default: Some(0),
             description: None,
+            help: None,
         },);
         assert_matches!(Input::<i64>::new(None::<i64>, None::<&str>), Input::<i64> {
             default: None,
             description: None,
+            help: None,
         },);
     }
 
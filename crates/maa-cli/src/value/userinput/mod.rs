@@ -54,8 +54,16 @@ pub trait UserInput: Sized {
         self.default()
     }
 
+    /// Help text of this parameter, printed above the prompt.
+    fn help(&self) -> Option<&str> {
+        None
+    }
+
     /// Prompt user to input a value for this parameter and return the value when success.
     fn ask(self, writer: &mut impl Write, reader: &mut impl BufRead) -> io::Result<Self::Value> {
+        if let Some(help) = self.help() {
+            writeln!(writer, "{}", help)?;
+        }
         self.prompt(writer)?;
         writer.write_all(b": ")?;
         writer.flush()?;
@@ -213,5 +221,47 @@ mod tests {
             String::from_utf8(output).unwrap(),
             "Whether to hello [y/n]: Invalid input, please input y/n: "
         );
+
+        // Test help text
+        let mut output = Vec::new();
+        let input = Input::<i32>::new(Some(0), Some("medicine to use"))
+            .with_help("Medicine expiring soon will be used first");
+        assert_eq!(input.ask(&mut output, input!("\n")).unwrap(), 0);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Medicine expiring soon will be used first\n\
+             Please input medicine to use [default: 0]: "
+        );
+
+        let mut output = Vec::new();
+        let input = Input::<i32>::new(Some(0), Some("medicine to use"));
+        assert_eq!(input.ask(&mut output, input!("\n")).unwrap(), 0);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Please input medicine to use [default: 0]: "
+        );
+
+        let mut output = Vec::new();
+        let bool_input =
+            BoolInput::new(Some(true), Some("use expedited plan")).with_help("Sanity is not used");
+        assert!(bool_input.ask(&mut output, input!("\n")).unwrap());
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Sanity is not used\n\
+             Whether to use expedited plan [Y/n]: "
+        );
+
+        let mut output = Vec::new();
+        let select = SelectD::<String>::new(["CE-5", "CE-6"], Some(2), Some("a stage"), false)
+            .unwrap()
+            .with_help("CE-6 drops more LMB");
+        assert_eq!(select.ask(&mut output, input!("1\n")).unwrap(), "CE-5");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "CE-6 drops more LMB\n\
+             1. CE-5\n\
+             2. CE-6 [default]\n\
+             Please select a stage (empty for default): "
+        );
     }
 }
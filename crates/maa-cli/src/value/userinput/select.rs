@@ -19,6 +19,8 @@ pub struct Select<S> {
     default_index: Option<usize>,
     /// Description of this parameter
     description: Option<String>,
+    /// Help text printed above the prompt
+    help: Option<String>,
     /// Allow custom input
     allow_custom: bool,
 }
@@ -38,18 +40,22 @@ impl<'de, S: Deserialize<'de>> Deserialize<'de> for Select<S> {
             #[serde(default)]
             description: Option<String>,
             #[serde(default)]
+            help: Option<String>,
+            #[serde(default)]
             allow_custom: bool,
         }
 
         let helper = SelectHelper::<S>::deserialize(deserializer)?;
 
-        Select::raw_new(
+        let mut select = Select::raw_new(
             helper.alternatives,
             helper.default_index,
             helper.description,
             helper.allow_custom,
         )
-        .map_err(serde::de::Error::custom)
+        .map_err(serde::de::Error::custom)?;
+        select.help = helper.help;
+        Ok(select)
     }
 }
 
@@ -132,9 +138,30 @@ impl<A> Select<A> {
             alternatives,
             default_index,
             description,
+            help: None,
             allow_custom,
         })
     }
+
+    /// Set the help text printed above the prompt.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "help is only set by deserialization")
+    )]
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Get the default alternative of this select, if any.
+    pub fn default_value(&self) -> Option<&A> {
+        self.default_index.map(|i| &self.alternatives[i])
+    }
+
+    /// Get the description of this select, if any.
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
 }
 
 impl<S> UserInput for Select<S>
@@ -157,6 +184,10 @@ where
             .value())
     }
 
+    fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
     fn prompt(&self, writer: &mut impl Write) -> io::Result<()> {
         for (i, alternative) in self.alternatives.iter().enumerate() {
             write!(writer, "{}. {}", i + 1, alternative)?;
@@ -277,6 +308,15 @@ impl<T: Display> ValueWithDesc<T> {
         }
     }
 
+    /// Get a reference to the value without its description.
+    pub fn as_value(&self) -> &T {
+        use ValueWithDesc::*;
+        match self {
+            Value(value) => value,
+            WithDesc { value, .. } => value,
+        }
+    }
+
     fn value(self) -> T {
         use ValueWithDesc::*;
         match self {
@@ -420,6 +460,26 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn serde_help() {
+        let select: SelectD<String> = serde_json::from_str(
+            r#"{ "alternatives": ["CE-5", "CE-6"], "help": "CE-6 drops more LMB" }"#,
+        )
+        .unwrap();
+        assert_eq!(select, test_none().with_help("CE-6 drops more LMB"));
+
+        let mut output = Vec::new();
+        let input = &mut std::io::BufReader::new("2\n".as_bytes());
+        assert_eq!(select.ask(&mut output, input).unwrap(), "CE-6");
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "CE-6 drops more LMB\n\
+             1. CE-5\n\
+             2. CE-6\n\
+             Please select one of the alternatives: "
+        );
+    }
+
     #[test]
     fn construct() {
         assert_matches!(
@@ -428,6 +488,7 @@ mod tests {
                 alternatives,
                 default_index: Some(1),
                 description: Some(description),
+                help: None,
                 allow_custom: true,
             } if alternatives == [
                 ValueWithDesc::new("CE-5", Some("LMB stage 5")),
@@ -441,6 +502,7 @@ mod tests {
                 alternatives,
                 default_index: None,
                 description: None,
+                help: None,
                 allow_custom: false,
             } if alternatives == vec!["CE-5", "CE-6"].into_iter().map(|s| s.into()).collect::<Vec<_>>()
         );
@@ -0,0 +1,147 @@
+use super::MAAValue;
+
+/// A segment of the path to a value in a walk
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[derive(Clone, Copy)]
+pub enum PathSegment<'a> {
+    /// Key of a value in an object
+    Key(&'a str),
+    /// Index of a value in an array
+    Index(usize),
+}
+
+impl MAAValue {
+    /// Walk through the value and call the visitor for every node with its path
+    ///
+    /// Nodes are visited in pre-order, the value itself is visited first with an empty path,
+    /// then values in objects and arrays recursively. Input and optional values are visited as
+    /// leaves, their inner values are not visited.
+    #[cfg_attr(
+        not(test),
+        expect(dead_code, reason = "only walk_mut has a caller so far")
+    )]
+    pub(crate) fn walk(&self, visitor: &mut impl FnMut(&[PathSegment], &MAAValue)) {
+        fn walk<'a>(
+            value: &'a MAAValue,
+            path: &mut Vec<PathSegment<'a>>,
+            visitor: &mut impl FnMut(&[PathSegment], &MAAValue),
+        ) {
+            visitor(path, value);
+            match value {
+                MAAValue::Object(map) => {
+                    for (key, value) in map {
+                        path.push(PathSegment::Key(key));
+                        walk(value, path, visitor);
+                        path.pop();
+                    }
+                }
+                MAAValue::Array(array) => {
+                    for (i, value) in array.iter().enumerate() {
+                        path.push(PathSegment::Index(i));
+                        walk(value, path, visitor);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        walk(self, &mut Vec::new(), visitor)
+    }
+
+    /// Walk through the value and call the visitor for every node with its path, allowing
+    /// in-place edits
+    ///
+    /// Same as `walk`, but the visitor can modify the node. A node is visited before its values,
+    /// so the values after modification are visited.
+    pub fn walk_mut(&mut self, visitor: &mut impl FnMut(&[PathSegment], &mut MAAValue)) {
+        fn walk<'a>(
+            value: &'a mut MAAValue,
+            path: &mut Vec<PathSegment<'a>>,
+            visitor: &mut impl FnMut(&[PathSegment], &mut MAAValue),
+        ) {
+            visitor(path, value);
+            match value {
+                MAAValue::Object(map) => {
+                    for (key, value) in map {
+                        path.push(PathSegment::Key(key));
+                        walk(value, path, visitor);
+                        path.pop();
+                    }
+                }
+                MAAValue::Array(array) => {
+                    for (i, value) in array.iter_mut().enumerate() {
+                        path.push(PathSegment::Index(i));
+                        walk(value, path, visitor);
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        walk(self, &mut Vec::new(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathSegment::*;
+    use crate::object;
+
+    #[test]
+    fn walk() {
+        let value = object!(
+            "stage" => "1-7",
+            "tasks" => [object!("type" => "Fight"), "Mall".into()],
+        );
+
+        let mut paths = Vec::new();
+        value.walk(&mut |path, value| {
+            let path = path
+                .iter()
+                .map(|segment| match segment {
+                    Key(key) => key.to_string(),
+                    Index(i) => format!("[{i}]"),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            paths.push((path, value.as_str().map(str::to_owned)));
+        });
+
+        let expected = [
+            ("", None),
+            ("stage", Some("1-7")),
+            ("tasks", None),
+            ("tasks/[0]", None),
+            ("tasks/[0]/type", Some("Fight")),
+            ("tasks/[1]", Some("Mall")),
+        ];
+        assert_eq!(
+            paths,
+            expected.map(|(path, value)| (path.to_owned(), value.map(str::to_owned)))
+        );
+    }
+
+    #[test]
+    fn walk_mut() {
+        let mut value = object!(
+            "stage" => "1-7",
+            "tasks" => [object!("type" => "Fight"), "Mall".into()],
+        );
+
+        value.walk_mut(&mut |path, value| match path {
+            [Key("tasks"), Index(1)] => *value = object!("type" => "Mall"),
+            [.., Key("type")] => *value = format!("{}Task", value.as_str().unwrap()).into(),
+            _ => {}
+        });
+
+        assert_eq!(
+            value,
+            object!(
+                "stage" => "1-7",
+                "tasks" => [object!("type" => "FightTask"), object!("type" => "MallTask")],
+            )
+        );
+    }
+}
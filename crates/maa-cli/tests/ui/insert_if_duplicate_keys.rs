@@ -0,0 +1,12 @@
+// The value module is included directly, as maa-cli has no library target
+#![allow(dead_code, unused_imports, unfulfilled_lint_expectations)]
+
+#[path = "../../src/value/mod.rs"]
+mod value;
+
+use value::MAAValue;
+
+fn main() {
+    let mut params = MAAValue::new();
+    insert_if!(params, true, "medicine" => 1, "medicine" => 2);
+}
@@ -0,0 +1,13 @@
+// The value module is included directly, as maa-cli has no library target
+#![allow(dead_code, unused_imports, unfulfilled_lint_expectations)]
+
+#[path = "../../src/value/mod.rs"]
+mod value;
+
+fn main() {
+    let _ = object!(
+        "stage" => "1-7",
+        "stage" if "medicine" == 1 => "CE-6",
+        "medicine" => 1,
+    );
+}
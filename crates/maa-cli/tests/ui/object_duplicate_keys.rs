@@ -0,0 +1,9 @@
+// The value module is included directly, as maa-cli has no library target
+#![allow(dead_code, unused_imports, unfulfilled_lint_expectations)]
+
+#[path = "../../src/value/mod.rs"]
+mod value;
+
+fn main() {
+    let _ = object!("stage" => "1-7", "medicine" => 1, "stage" => "CE-6");
+}
@@ -0,0 +1,9 @@
+// The value module is included directly, as maa-cli has no library target
+#![allow(dead_code, unused_imports, unfulfilled_lint_expectations)]
+
+#[path = "../../src/value/mod.rs"]
+mod value;
+
+fn main() {
+    let _ = schema!("stage": String, "times": Int, "stage": [String]);
+}
@@ -1,10 +1,12 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
     env::consts,
     ffi::{OsStr, OsString},
-    fs::{create_dir, create_dir_all, remove_dir_all},
+    fs::{create_dir, create_dir_all, read_dir, remove_dir, remove_dir_all, remove_file, rename},
     path::{Path, PathBuf},
     sync::LazyLock,
+    time::{Duration, SystemTime},
 };
 
 use directories::ProjectDirs;
@@ -103,6 +105,13 @@ fn get_state_dir(v: impl VarOs + Copy, proj: Option<&ProjectDirs>) -> PathBuf {
         .expect("Failed to get state directory!")
 }
 
+/// Get the log directory, which is `debug` in the state directory by default.
+fn get_log_dir(v: impl VarOs + Copy, state_dir: &Path) -> PathBuf {
+    v.var_os("MAA_LOG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| state_dir.join("debug"))
+}
+
 /// Get the cache directory.
 fn get_cache_dir(v: impl VarOs + Copy, proj: Option<&ProjectDirs>) -> PathBuf {
     dir_from_env(v, "MAA_CACHE_DIR", "XDG_CACHE_HOME")
@@ -131,10 +140,12 @@ pub struct Dirs {
     config: PathBuf,
     cache: PathBuf,
     copilot: PathBuf,
+    download_staging: PathBuf,
     resource: PathBuf,
     hot_update: PathBuf,
     state: PathBuf,
     log: PathBuf,
+    core_lib_path: Option<PathBuf>,
 }
 
 impl Dirs {
@@ -149,14 +160,16 @@ impl Dirs {
 
         Self {
             copilot: cache_dir.join("copilot"),
+            download_staging: join!(&cache_dir, "downloads", "tmp"),
             cache: cache_dir,
             config: get_config_dir(v, proj),
             library: data_dir.join("lib"),
             resource: data_dir.join("resource"),
             hot_update: data_dir.join("MaaResource"),
             data: data_dir,
-            log: state_dir.join("debug"),
+            log: get_log_dir(v, &state_dir),
             state: state_dir,
+            core_lib_path: v.var_os("MAA_CORE_LIB_PATH").map(PathBuf::from),
         }
     }
 
@@ -170,8 +183,33 @@ impl Dirs {
         &self.library
     }
 
+    /// Back up the library directory to its sibling `lib.bak` before an update.
+    ///
+    /// The library directory is copied, so it stays usable during the update. A stale backup is
+    /// replaced. Return `false` if there is no library directory to back up.
+    pub fn backup_library(&self) -> std::io::Result<bool> {
+        backup_dir(self.library())
+    }
+
+    /// Restore the library directory from the backup made by [`Dirs::backup_library`].
+    ///
+    /// The current library directory is replaced by the backup. Return `false` and do nothing if
+    /// there is no backup.
+    pub fn restore_library(&self) -> std::io::Result<bool> {
+        restore_dir(self.library())
+    }
+
+    /// Remove the backup made by [`Dirs::backup_library`] after a successful update.
+    pub fn discard_library_backup(&self) -> std::io::Result<()> {
+        discard_backup(self.library())
+    }
+
     /// Find the library directory.
     ///
+    /// If the environment variable `MAA_CORE_LIB_PATH` is set to the MaaCore library or the
+    /// directory containing it, that directory is used. If the referenced library does not exist
+    /// or is not named [`MAA_CORE_LIB`], it is ignored and the library is searched as below.
+    ///
     /// By default, the library directory is the `lib` directory in the data directory.
     /// If the library MaaCore is not found in the default library directory,
     /// Try to find it in the directory relative to the executable file.
@@ -181,18 +219,35 @@ impl Dirs {
     /// file. If the executable is a symbolic link, will try to find the MaaCore both in the
     /// symbolic link and the link target.
     pub fn find_library<'a>(&'a self, exe_path: &'a Path) -> Option<Cow<'a, Path>> {
-        if self.library().join(MAA_CORE_LIB).exists() {
+        if let Some(path) = &self.core_lib_path {
+            if is_file(path) {
+                // Only the directory is passed to MaaCore, other file names can't be loaded
+                if path.file_name() == Some(OsStr::new(MAA_CORE_LIB)) {
+                    if let Some(dir) = path.parent() {
+                        return Some(dir.into());
+                    }
+                }
+            } else if is_file(&path.join(MAA_CORE_LIB)) {
+                return Some(path.into());
+            }
+            log::warn!(
+                "MaaCore not found at MAA_CORE_LIB_PATH={}, ignored",
+                path.display()
+            );
+        }
+
+        if exists(&self.library().join(MAA_CORE_LIB)) {
             return Some(self.library().into());
         }
 
         _find_from(exe_path, |exe_dir| {
-            if exe_dir.join(MAA_CORE_LIB).exists() {
+            if exists(&exe_dir.join(MAA_CORE_LIB)) {
                 return Some(exe_dir);
             }
             if let Some(dir) = exe_dir.parent() {
                 let lib_dir = dir.join("lib");
                 let lib_path = lib_dir.join(MAA_CORE_LIB);
-                if lib_path.exists() {
+                if exists(&lib_path) {
                     return Some(lib_dir.into());
                 }
             }
@@ -209,6 +264,8 @@ impl Dirs {
     /// Get absolute path in config directory.
     ///
     /// If the given path is absolute, return `None`.
+    /// Otherwise, `${VAR}` in the path is replaced by the environment variable `VAR` and a
+    /// leading `~` is replaced by the home directory. If the expanded path is absolute, return it.
     /// Otherwise, return the path in the config directory.
     /// The `sub_dir` is the sub directory of the config directory.
     /// If `sub_dir` is `None`, the path is relative to the config directory.
@@ -218,9 +275,23 @@ impl Dirs {
         path: P,
         sub_dir: Option<D>,
     ) -> Option<PathBuf> {
-        let path = path.as_ref();
+        self.abs_config_with(path.as_ref(), sub_dir, EnvVarOs)
+    }
+
+    fn abs_config_with<D: AsRef<Path>>(
+        &self,
+        path: &Path,
+        sub_dir: Option<D>,
+        v: impl VarOs + Copy,
+    ) -> Option<PathBuf> {
         if path.is_absolute() {
-            None
+            return None;
+        }
+
+        let path = expand_env(path, v);
+        let path = expand_tilde(&path);
+        if path.is_absolute() {
+            Some(path.into_owned())
         } else {
             let mut result = self.config.to_path_buf();
             if let Some(sub_dir) = sub_dir {
@@ -231,6 +302,23 @@ impl Dirs {
         }
     }
 
+    /// Find a config file by its stem in the config directory.
+    ///
+    /// The file `<stem>.<ext>` is searched in the `sub_dir` of the config directory, or the
+    /// config directory itself if `sub_dir` is `None`. Extensions are tried in the order of
+    /// `json`, `yaml`, `yml` and `toml`, which is the same as the CLI, and the first existing
+    /// file is returned.
+    pub fn find_config(&self, stem: &str, sub_dir: Option<&Path>) -> Option<PathBuf> {
+        let dir = match sub_dir {
+            Some(sub_dir) => self.config.join(sub_dir),
+            None => self.config.to_path_buf(),
+        };
+        CONFIG_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{stem}.{ext}")))
+            .find(|path| path.is_file())
+    }
+
     /// Get cache directory.
     pub fn cache(&self) -> &Path {
         &self.cache
@@ -241,6 +329,50 @@ impl Dirs {
         &self.copilot
     }
 
+    /// Get the cached copilot file downloaded from `url`, if any.
+    ///
+    /// The file is marked as recently used, so it is evicted later by [`Dirs::evict_copilot`].
+    pub fn cached_copilot(&self, url: &str) -> Option<PathBuf> {
+        let path = self.copilot().join(copilot_file_name(url));
+        if !path.is_file() {
+            return None;
+        }
+        let touched = std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        if let Err(err) = touched {
+            log::debug!("Failed to update mtime of {}: {err}", path.display());
+        }
+        Some(path)
+    }
+
+    /// Store the copilot file downloaded from `url` in the copilot cache directory.
+    ///
+    /// Return the path of the cached file, see [`copilot_file_name`].
+    pub fn store_copilot(&self, url: &str, content: &[u8]) -> std::io::Result<PathBuf> {
+        let path = self.copilot().ensure()?.join(copilot_file_name(url));
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// Remove least recently used copilot files until their total size is at most `max_bytes`.
+    ///
+    /// Only files stored by [`Dirs::store_copilot`] and the `<id>.json` files cached by earlier
+    /// versions are removed, other files in the copilot directory are kept. Return the number of
+    /// removed files.
+    pub fn evict_copilot(&self, max_bytes: u64) -> std::io::Result<usize> {
+        evict_files(self.copilot(), max_bytes)
+    }
+
+    /// Get download staging directory.
+    ///
+    /// In-progress downloads are written to this directory and only moved to the cache directory
+    /// once they are complete and verified, so a partial file is never mistaken for a complete one.
+    pub fn download_staging(&self) -> &Path {
+        &self.download_staging
+    }
+
     /// Get resource directory.
     pub fn resource(&self) -> &Path {
         &self.resource
@@ -261,25 +393,25 @@ impl Dirs {
     /// try to find the resource directory in the `share/$MAA_EXTRA_SHARE_NAME` directory.
     /// This is used to support the situation that MaaCore is installed by other package manager.
     pub fn find_resource<'a>(&'a self, exe_path: &'a Path) -> Option<Cow<'a, Path>> {
-        if self.resource().exists() {
+        if exists(self.resource()) {
             return Some(Cow::Borrowed(self.resource()));
         }
 
         _find_from(exe_path, |exe_dir| {
             let resource_dir = exe_dir.join("resource");
-            if resource_dir.exists() {
+            if exists(&resource_dir) {
                 return Some(resource_dir.into());
             }
             if let Some(dir) = exe_dir.parent() {
                 let share_dir = dir.join("share");
                 if let Some(extra_share) = option_env!("MAA_EXTRA_SHARE_NAME") {
                     let resource_dir = join!(&share_dir, extra_share, "resource");
-                    if resource_dir.exists() {
+                    if exists(&resource_dir) {
                         return Some(resource_dir.into());
                     }
                 }
                 let resource_dir = join!(share_dir, "maa", "resource");
-                if resource_dir.exists() {
+                if exists(&resource_dir) {
                     return Some(resource_dir.into());
                 }
             }
@@ -301,6 +433,309 @@ impl Dirs {
     pub fn log(&self) -> &Path {
         &self.log
     }
+
+    /// Acquire the lock file `maa.lock` in the state directory.
+    ///
+    /// Return an error of kind `WouldBlock` if the lock is held by another process (or another
+    /// guard in the current process). The lock is released when the guard is dropped.
+    pub fn acquire_lock(&self) -> std::io::Result<LockGuard> {
+        LockGuard::acquire(self.state().ensure_private()?.join(LOCK_FILE))
+    }
+
+    /// Move the contents of the legacy flat directory `~/.maa` into the current directories.
+    ///
+    /// See [`Dirs::migrate_legacy_layout_from`] for details.
+    pub fn migrate_legacy_layout(&self) -> std::io::Result<bool> {
+        self.migrate_legacy_layout_from(&home().join(LEGACY_ROOT))
+    }
+
+    /// Move the contents of a legacy flat directory into the current directories.
+    ///
+    /// The sub dirs `config`, `lib`, `resource`, `MaaResource`, `cache` and `debug` of `root` are
+    /// moved into the config, library, resource, hot update, cache and log directories
+    /// respectively. Existing files in the current directories are never overwritten, the
+    /// conflicting legacy files are kept in place. A sub dir is skipped if its current directory
+    /// is inside `root`, e.g. the directory is set to the legacy one by an environment variable.
+    ///
+    /// Return whether anything is moved, so a second call is a no-op and returns `false`.
+    fn migrate_legacy_layout_from(&self, root: &Path) -> std::io::Result<bool> {
+        if !root.is_dir() {
+            return Ok(false);
+        }
+
+        let mut migrated = false;
+        for (name, dest) in [
+            ("config", self.config()),
+            ("lib", self.library()),
+            ("resource", self.resource()),
+            ("MaaResource", self.hot_update()),
+            ("cache", self.cache()),
+            ("debug", self.log()),
+        ] {
+            let src = root.join(name);
+            if !src.is_dir() || dest.starts_with(root) {
+                continue;
+            }
+            log::info!("Migrating {} to {}", src.display(), dest.display());
+            migrated |= merge_dir(&src, dest)?;
+        }
+
+        Ok(migrated)
+    }
+
+    /// Get the total size in bytes of the data, cache, log, resource and copilot directories.
+    ///
+    /// The resource directory is in the data directory and the copilot directory is in the cache
+    /// directory by default, so their sizes are also counted in their parents. A missing
+    /// directory has size 0.
+    pub fn sizes(&self) -> std::io::Result<DirSizes> {
+        Ok(DirSizes {
+            data: dir_size(self.data())?,
+            cache: dir_size(self.cache())?,
+            log: dir_size(self.log())?,
+            resource: dir_size(self.resource())?,
+            copilot: dir_size(self.copilot())?,
+        })
+    }
+
+    /// Remove log files in the log directory which have not been modified for `max_age`.
+    ///
+    /// The active log of MaaCore `asst.log` is never removed, and directories emptied by the
+    /// pruning are removed as well.
+    ///
+    /// # Returns
+    /// The number of removed files.
+    pub fn prune_logs(&self, max_age: Duration) -> std::io::Result<usize> {
+        prune_files(self.log(), max_age, SystemTime::now())
+    }
+}
+
+/// Total sizes in bytes of directories, see [`Dirs::sizes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirSizes {
+    pub data: u64,
+    pub cache: u64,
+    pub log: u64,
+    pub resource: u64,
+    pub copilot: u64,
+}
+
+/// Get the total size in bytes of files in `path` recursively.
+///
+/// Symbolic links are followed, but a directory is only counted once, so symbolic link loops
+/// are skipped. Return 0 if `path` does not exist, and dangling symbolic links are ignored.
+pub fn dir_size(path: &Path) -> std::io::Result<u64> {
+    fn inner(path: &Path, visited: &mut HashSet<PathBuf>) -> std::io::Result<u64> {
+        let metadata = std::fs::metadata(path)?;
+        if !metadata.is_dir() {
+            return Ok(metadata.len());
+        }
+        if !visited.insert(canonicalize(path)?) {
+            return Ok(0);
+        }
+
+        let mut size = 0;
+        for entry in read_dir(path)? {
+            size += match inner(&entry?.path(), visited) {
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+                result => result?,
+            };
+        }
+        Ok(size)
+    }
+
+    if !path.exists() {
+        return Ok(0);
+    }
+    inner(path, &mut HashSet::new())
+}
+
+/// Name of the cached copilot file downloaded from `url`, e.g. `7d17019fe71f4ced.json`.
+///
+/// The name is the FNV-1a hash of the url, which is stable across versions and platforms.
+pub fn copilot_file_name(url: &str) -> String {
+    let hash = url.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}.json")
+}
+
+/// Whether `name` is a cached copilot file, including the `<id>.json` files cached by earlier
+/// versions, which are evicted like others instead of being kept forever.
+fn is_copilot_file_name(name: &OsStr) -> bool {
+    name.to_str()
+        .and_then(|name| name.strip_suffix(".json"))
+        .is_some_and(|stem| {
+            let is_hash = stem.len() == 16 && stem.bytes().all(|b| b.is_ascii_hexdigit());
+            let is_legacy_id = !stem.is_empty() && stem.bytes().all(|b| b.is_ascii_digit());
+            is_hash || is_legacy_id
+        })
+}
+
+fn evict_files(dir: &Path, max_bytes: u64) -> std::io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut files = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && is_copilot_file_name(&entry.file_name()) {
+            files.push((metadata.modified()?, metadata.len(), entry.path()));
+        }
+    }
+    files.sort_by_key(|(modified, ..)| *modified);
+
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    let mut removed = 0;
+    for (_, len, path) in files {
+        if total <= max_bytes {
+            break;
+        }
+        log::debug!("Evicting copilot file {}", path.display());
+        remove_file(&path)?;
+        total -= len;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// Name of the legacy flat directory in the home directory.
+const LEGACY_ROOT: &str = ".maa";
+
+/// Move the contents of `src` into `dest` without overwriting existing files.
+///
+/// Entries moved are removed from `src`, and so are the dirs which become empty. Return whether
+/// anything is moved.
+fn merge_dir(src: &Path, dest: &Path) -> std::io::Result<bool> {
+    let mut moved = false;
+    if !dest.exists() {
+        if let Some(parent) = dest.parent() {
+            parent.ensure()?;
+        }
+        move_path(src, dest)?;
+        return Ok(true);
+    }
+    if !dest.is_dir() {
+        return Ok(false);
+    }
+
+    for entry in read_dir(src)? {
+        let entry = entry?;
+        let src = entry.path();
+        let dest = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            moved |= merge_dir(&src, &dest)?;
+        } else if !dest.exists() {
+            move_path(&src, &dest)?;
+            moved = true;
+        } else {
+            log::warn!(
+                "Skip migrating {} as {} exists",
+                src.display(),
+                dest.display()
+            );
+        }
+    }
+
+    if read_dir(src)?.next().is_none() {
+        remove_dir(src)?;
+    }
+
+    Ok(moved)
+}
+
+/// Rename `src` to `dest`, or copy and remove it if they are on different file systems.
+fn move_path(src: &Path, dest: &Path) -> std::io::Result<()> {
+    if rename(src, dest).is_ok() {
+        return Ok(());
+    }
+    if src.is_dir() {
+        copy_dir_all(src, dest)?;
+        remove_dir_all(src)
+    } else {
+        std::fs::copy(src, dest)?;
+        remove_file(src)
+    }
+}
+
+/// Name of the lock file in the state directory.
+const LOCK_FILE: &str = "maa.lock";
+
+/// Guard of an exclusively locked lock file, the lock is released on drop.
+pub struct LockGuard {
+    file: std::fs::File,
+    path: PathBuf,
+}
+
+impl LockGuard {
+    fn acquire(path: PathBuf) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { file, path }),
+            Err(std::fs::TryLockError::WouldBlock) => Err(std::io::Error::new(
+                std::io::ErrorKind::WouldBlock,
+                format!(
+                    "Lock file {} is held by another maa process",
+                    path.display()
+                ),
+            )),
+            Err(std::fs::TryLockError::Error(err)) => Err(err),
+        }
+    }
+
+    /// Path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.file.unlock() {
+            log::warn!("Failed to unlock {} due to {err}", self.path.display());
+        }
+    }
+}
+
+/// Extensions of config files, in the order of precedence.
+const CONFIG_EXTENSIONS: [&str; 4] = ["json", "yaml", "yml", "toml"];
+
+/// Name of the log file which MaaCore is writing to.
+const ACTIVE_LOG: &str = "asst.log";
+
+fn prune_files(dir: &Path, max_age: Duration, now: SystemTime) -> std::io::Result<usize> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            let removed_in_dir = prune_files(&path, max_age, now)?;
+            if removed_in_dir > 0 && read_dir(&path)?.next().is_none() {
+                remove_dir(&path)?;
+            }
+            removed += removed_in_dir;
+        } else if entry.file_name() != ACTIVE_LOG {
+            let modified = entry.metadata()?.modified()?;
+            if now.duration_since(modified).is_ok_and(|age| age >= max_age) {
+                log::debug!("Removing old log {}", path.display());
+                remove_file(path)?;
+                removed += 1;
+            }
+        }
+    }
+
+    Ok(removed)
 }
 
 const QUALIFIER: &str = "com";
@@ -310,57 +745,169 @@ const APPLICATION: &str = "maa";
 static DIRS: LazyLock<Dirs> =
     LazyLock::new(|| Dirs::new(ProjectDirs::from(QUALIFIER, ORGANIZATION, APPLICATION).as_ref()));
 
+#[cfg(any(test, feature = "testing"))]
+thread_local! {
+    static DIRS_OVERRIDE: std::cell::Cell<Option<&'static Dirs>> =
+        const { std::cell::Cell::new(None) };
+}
+
+/// Get the directories used by global functions.
+fn dirs() -> &'static Dirs {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dirs) = DIRS_OVERRIDE.get() {
+        return dirs;
+    }
+    &DIRS
+}
+
+/// A guard to override the directories used by global functions on the current thread.
+///
+/// All directories are placed under the given root (`data`, `state`, `cache` and `config`),
+/// so tests depending on directories can run in parallel without touching the real directories
+/// or racing on environment variables. The previous directories are restored when the guard is
+/// dropped, and guards can be nested.
+///
+/// The overridden `Dirs` is leaked to be used as `'static`, so this is only intended for tests.
+#[cfg(any(test, feature = "testing"))]
+pub struct DirsOverrideGuard {
+    prev: Option<&'static Dirs>,
+    // The guard must be dropped on the thread where it is created
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl DirsOverrideGuard {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        #[derive(Clone, Copy)]
+        struct RootVarOs<'a>(&'a Path);
+
+        impl VarOs for RootVarOs<'_> {
+            fn var_os(self, key: impl AsRef<OsStr>) -> Option<OsString> {
+                let sub_dir = match key.as_ref().to_str()? {
+                    "MAA_DATA_DIR" => "data",
+                    "MAA_STATE_DIR" => "state",
+                    "MAA_CACHE_DIR" => "cache",
+                    "MAA_CONFIG_DIR" => "config",
+                    _ => return None,
+                };
+                Some(self.0.join(sub_dir).into())
+            }
+        }
+
+        let dirs = Box::leak(Box::new(Dirs::new_inner(None, RootVarOs(root.as_ref()))));
+        Self {
+            prev: DIRS_OVERRIDE.replace(Some(dirs)),
+            _not_send: std::marker::PhantomData,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl Drop for DirsOverrideGuard {
+    fn drop(&mut self) {
+        DIRS_OVERRIDE.set(self.prev);
+    }
+}
+
 fn current_exe() -> Option<&'static Path> {
     static CURRENT_EXE: LazyLock<Option<PathBuf>> = LazyLock::new(|| std::env::current_exe().ok());
     CURRENT_EXE.as_deref()
 }
 
 pub fn data() -> &'static Path {
-    DIRS.data()
+    dirs().data()
 }
 
 pub fn library() -> &'static Path {
-    DIRS.library()
+    dirs().library()
 }
 
 pub fn find_library() -> Option<Cow<'static, Path>> {
-    DIRS.find_library(current_exe()?)
+    dirs().find_library(current_exe()?)
+}
+
+pub fn backup_library() -> std::io::Result<bool> {
+    dirs().backup_library()
+}
+
+pub fn restore_library() -> std::io::Result<bool> {
+    dirs().restore_library()
+}
+
+pub fn discard_library_backup() -> std::io::Result<()> {
+    dirs().discard_library_backup()
 }
 
 pub fn config() -> &'static Path {
-    DIRS.config()
+    dirs().config()
 }
 
 pub fn abs_config<P: AsRef<Path>, D: AsRef<Path>>(path: P, sub_dir: Option<D>) -> Option<PathBuf> {
-    DIRS.abs_config(path, sub_dir)
+    dirs().abs_config(path, sub_dir)
+}
+
+pub fn find_config(stem: &str, sub_dir: Option<&Path>) -> Option<PathBuf> {
+    dirs().find_config(stem, sub_dir)
 }
 
 pub fn cache() -> &'static Path {
-    DIRS.cache()
+    dirs().cache()
 }
 
 pub fn copilot() -> &'static Path {
-    DIRS.copilot()
+    dirs().copilot()
+}
+
+pub fn cached_copilot(url: &str) -> Option<PathBuf> {
+    dirs().cached_copilot(url)
+}
+
+pub fn store_copilot(url: &str, content: &[u8]) -> std::io::Result<PathBuf> {
+    dirs().store_copilot(url, content)
+}
+
+pub fn evict_copilot(max_bytes: u64) -> std::io::Result<usize> {
+    dirs().evict_copilot(max_bytes)
+}
+
+pub fn download_staging() -> &'static Path {
+    dirs().download_staging()
 }
 
 pub fn resource() -> &'static Path {
-    DIRS.resource()
+    dirs().resource()
 }
 
 pub fn find_resource() -> Option<Cow<'static, Path>> {
-    DIRS.find_resource(current_exe()?)
+    dirs().find_resource(current_exe()?)
 }
 
 pub fn hot_update() -> &'static Path {
-    DIRS.hot_update()
+    dirs().hot_update()
 }
 
 pub fn state() -> &'static Path {
-    DIRS.state()
+    dirs().state()
 }
 
 pub fn log() -> &'static Path {
-    DIRS.log()
+    dirs().log()
+}
+
+pub fn acquire_lock() -> std::io::Result<LockGuard> {
+    dirs().acquire_lock()
+}
+
+pub fn migrate_legacy_layout() -> std::io::Result<bool> {
+    dirs().migrate_legacy_layout()
+}
+
+pub fn sizes() -> std::io::Result<DirSizes> {
+    dirs().sizes()
+}
+
+pub fn prune_logs(max_age: Duration) -> std::io::Result<usize> {
+    dirs().prune_logs(max_age)
 }
 
 fn home() -> &'static Path {
@@ -382,6 +929,103 @@ pub fn expand_tilde(path: &Path) -> Cow<Path> {
     }
 }
 
+/// Replace `${VAR}` in the path with the value of environment variable `VAR`.
+///
+/// Unset variables and paths which are not valid unicode are kept as is.
+fn expand_env(path: &Path, v: impl VarOs + Copy) -> Cow<'_, Path> {
+    let Some(mut rest) = path.to_str().filter(|s| s.contains("${")) else {
+        return path.into();
+    };
+
+    let mut expanded = OsString::new();
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let reference = &rest[start..start + len + 1];
+        expanded.push(&rest[..start]);
+        match v.var_os(&reference[2..reference.len() - 1]) {
+            Some(value) => expanded.push(value),
+            None => expanded.push(reference),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push(rest);
+
+    PathBuf::from(expanded).into()
+}
+
+/// Convert an over-length absolute path on Windows to an extended-length path.
+///
+/// Paths longer than `MAX_PATH` (260) are prefixed with `\\?\` (or `\\?\UNC\` for UNC
+/// paths), so they can be checked or opened by Windows APIs. As extended-length paths are not
+/// normalized by Windows, `.` and `..` are resolved lexically, same as what Windows does for
+/// normal paths. Other paths, and all paths on other platforms, are returned unchanged.
+///
+/// The converted path is only meant to be passed to the file system, paths shown to users
+/// should be kept in the normal form, e.g. the output of [`dunce::canonicalize`].
+pub fn extended_length(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        use std::path::{Component, Prefix};
+
+        const MAX_PATH: usize = 260;
+
+        if path.as_os_str().len() < MAX_PATH || !path.is_absolute() {
+            return Cow::Borrowed(path);
+        }
+
+        let mut components = path.components();
+        let mut extended = match components.next() {
+            Some(Component::Prefix(prefix)) => match prefix.kind() {
+                Prefix::Disk(_) => {
+                    let mut extended = OsString::from(r"\\?\");
+                    extended.push(prefix.as_os_str());
+                    extended
+                }
+                Prefix::UNC(server, share) => {
+                    let mut extended = OsString::from(r"\\?\UNC\");
+                    extended.push(server);
+                    extended.push(r"\");
+                    extended.push(share);
+                    extended
+                }
+                // Verbatim and device paths are passed to the file system as is
+                _ => return Cow::Borrowed(path),
+            },
+            _ => return Cow::Borrowed(path),
+        };
+
+        let mut names = Vec::new();
+        for component in components {
+            match component {
+                Component::Normal(name) => names.push(name),
+                Component::ParentDir => {
+                    names.pop();
+                }
+                Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            }
+        }
+        for name in names {
+            extended.push(r"\");
+            extended.push(name);
+        }
+
+        Cow::Owned(extended.into())
+    }
+
+    #[cfg(not(windows))]
+    Cow::Borrowed(path)
+}
+
+fn exists(path: &Path) -> bool {
+    extended_length(path).exists()
+}
+
+fn is_file(path: &Path) -> bool {
+    extended_length(path).is_file()
+}
+
 /// Similar to `finder(exe_path.parent()?)`, but try to canonicalize the path first.
 fn _find_from<F>(exe_path: &Path, finder: F) -> Option<Cow<Path>>
 where
@@ -409,8 +1053,17 @@ pub trait Ensure: Sized {
     /// Ensure the dir is empty, create it if not.
     ///
     /// Return the path itself if it exists or created successfully.
-    /// If the dir is not empty, remove all files in it.
+    /// If the dir exists, it is renamed to a sibling `<name>.old.<pid>` and a fresh dir is
+    /// created, then the renamed dir is removed. Failing to remove the renamed dir is only
+    /// logged, so the fresh dir is usable even if the old one can not be deleted, e.g. on NFS.
     fn ensure_clean(self) -> Result<Self, Self::Error>;
+
+    /// Ensure the dir exists and is only accessible by the current user, create it if not.
+    ///
+    /// This is used for dirs which may hold sensitive data, e.g. tokens and device info.
+    /// On Unix, the mode of the dir is set to `0700`, even if it exists already.
+    /// On other platforms, this is the same as [`Ensure::ensure`].
+    fn ensure_private(self) -> Result<Self, Self::Error>;
 }
 
 impl Ensure for &Path {
@@ -424,27 +1077,262 @@ impl Ensure for &Path {
     }
 
     fn ensure_clean(self) -> Result<Self, Self::Error> {
-        if self.exists() {
-            let mut ret = remove_dir_all(self);
-            for i in 1..=3 {
-                if let Err(err) = &ret {
-                    log::warn!(
-                        "Failed to remove dir {} due to {err}, retry {i} times",
-                        self.display()
-                    );
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                    ret = remove_dir_all(self);
-                } else {
-                    break;
-                }
+        ensure_clean_with(self, |path| remove_dir_all(path))?;
+        Ok(self)
+    }
+
+    fn ensure_private(self) -> Result<Self, Self::Error> {
+        self.ensure()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(self, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(self)
+    }
+}
+
+/// Path of the sibling `<name>.<kind>.<pid>` of `path`, e.g. `lib.old.1234`.
+fn sibling_path(path: &Path, kind: &str) -> Option<PathBuf> {
+    let mut name = path.file_name()?.to_owned();
+    name.push(format!(".{kind}.{}", std::process::id()));
+    Some(path.with_file_name(name))
+}
+
+/// Path of the sibling of `path` to move the old dir to before removing it.
+fn old_dir_path(path: &Path) -> Option<PathBuf> {
+    sibling_path(path, "old")
+}
+
+/// Staging dir to prepare the new content of `target` in, before installing it by
+/// [`install_atomic`].
+///
+/// The staging dir is a sibling of `target`, so they are on the same file system and the
+/// staging dir can be renamed to `target`. Return `None` if `target` has no file name.
+pub fn staging_for(target: &Path) -> Option<PathBuf> {
+    sibling_path(target, "new")
+}
+
+/// Replace the dir `target` with the prepared dir `staging` by renaming.
+///
+/// The old `target` is moved aside before `staging` is moved into place, and removed
+/// afterward. If `staging` can not be moved into place, the old `target` is restored, so
+/// `target` is never left half-installed. The `staging` should be on the same file system as
+/// `target`, see [`staging_for`].
+pub fn install_atomic(staging: &Path, target: &Path) -> std::io::Result<()> {
+    install_atomic_with(staging, target, |from, to| rename(from, to))
+}
+
+fn install_atomic_with(
+    staging: &Path,
+    target: &Path,
+    rename: impl Fn(&Path, &Path) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    let old = old_dir_path(target).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Can not install to {}", target.display()),
+        )
+    })?;
+    if old.exists() {
+        remove_dir_all(&old)?;
+    }
+
+    let moved = target.exists();
+    if moved {
+        rename(target, &old)?;
+    } else if let Some(parent) = target.parent() {
+        parent.ensure()?;
+    }
+
+    if let Err(err) = rename(staging, target) {
+        if moved {
+            if let Err(restore_err) = rename(&old, target) {
+                log::error!(
+                    "Failed to restore {} from {} due to {restore_err}",
+                    target.display(),
+                    old.display()
+                );
             }
-            ret?;
-        } else if let Some(parent) = self.parent() {
+        }
+        return Err(err);
+    }
+
+    if moved {
+        if let Err(err) = remove_dir_all(&old) {
+            log::warn!(
+                "Failed to remove old dir {} due to {err}, please remove it manually",
+                old.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn ensure_clean_with(
+    path: &Path,
+    remove: impl Fn(&Path) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
             parent.ensure()?;
         }
-        create_dir(self)?;
-        Ok(self)
+        return create_dir(path);
+    }
+
+    let Some(old) = old_dir_path(path) else {
+        // The path has no file name (e.g. `/`), it can only be removed in place
+        remove(path)?;
+        return create_dir(path);
+    };
+    if old.exists() {
+        remove(&old)?;
+    }
+    rename(path, &old)?;
+    create_dir(path)?;
+
+    if let Err(err) = remove(&old) {
+        log::warn!(
+            "Failed to remove old dir {} due to {err}, please remove it manually",
+            old.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Path of the backup `<name>.bak` of `path`, e.g. `lib.bak`.
+fn backup_path(path: &Path) -> std::io::Result<PathBuf> {
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Can not back up {}", path.display()),
+            )
+        })?
+        .to_owned();
+    name.push(".bak");
+    Ok(path.with_file_name(name))
+}
+
+fn backup_dir(dir: &Path) -> std::io::Result<bool> {
+    if !dir.exists() {
+        return Ok(false);
+    }
+    let backup = backup_path(dir)?;
+    if backup.exists() {
+        remove_dir_all(&backup)?;
+    }
+    if let Err(err) = copy_dir_all(dir, &backup) {
+        let _ = remove_dir_all(&backup);
+        return Err(err);
+    }
+    Ok(true)
+}
+
+fn restore_dir(dir: &Path) -> std::io::Result<bool> {
+    let backup = backup_path(dir)?;
+    if !backup.exists() {
+        return Ok(false);
+    }
+    if dir.exists() {
+        remove_dir_all(dir)?;
+    }
+    rename(&backup, dir)?;
+    Ok(true)
+}
+
+fn discard_backup(dir: &Path) -> std::io::Result<()> {
+    let backup = backup_path(dir)?;
+    if backup.exists() {
+        remove_dir_all(&backup)?;
+    }
+    Ok(())
+}
+
+fn copy_dir_all(from: &Path, to: &Path) -> std::io::Result<()> {
+    create_dir_all(to)?;
+    for entry in read_dir(from)? {
+        let entry = entry?;
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dest)?;
+        } else {
+            std::fs::copy(entry.path(), &dest)?;
+        }
     }
+    Ok(())
+}
+
+/// Get the free space in bytes available to the current user on the file system of `path`.
+///
+/// If `path` does not exist, its nearest existing ancestor is used.
+pub fn free_space(path: &Path) -> std::io::Result<u64> {
+    let existing = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+    sys_free_space(existing).map_err(|err| {
+        std::io::Error::new(
+            err.kind(),
+            format!("Failed to get free space of {}: {err}", path.display()),
+        )
+    })
+}
+
+/// Check if there is at least `required` bytes of free space on the file system of `path`.
+///
+/// Return an error instead of `false` if the free space is not available.
+pub fn check_free_space(path: &Path, required: u64) -> std::io::Result<bool> {
+    Ok(free_space(path)? >= required)
+}
+
+#[cfg(unix)]
+fn sys_free_space(path: &Path) -> std::io::Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is a valid C string and `stat` is only read after a successful call
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    #[allow(
+        clippy::unnecessary_cast,
+        reason = "The types of fields differ between platforms"
+    )]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn sys_free_space(path: &Path) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0;
+    // SAFETY: `path` is a null-terminated wide string and other pointers are valid or null
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            path.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn sys_free_space(_path: &Path) -> std::io::Result<u64> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "free space is not available on this platform",
+    ))
 }
 
 /// Similar to `globpath` of vim
@@ -576,6 +1464,8 @@ mod tests {
                 env::remove_var("MAA_STATE_DIR");
                 env::remove_var("MAA_CACHE_DIR");
                 env::remove_var("MAA_CONFIG_DIR");
+                env::remove_var("MAA_LOG_DIR");
+                env::remove_var("MAA_CORE_LIB_PATH");
             });
         }
 
@@ -824,6 +1714,82 @@ mod tests {
             assert_eq!(dirs.log(), PathBuf::from("/maa/debug"));
         }
 
+        #[test]
+        fn core_lib_path() {
+            use std::fs::File;
+
+            let root = tempfile::tempdir().expect("Failed to create temp dir");
+            let root = canonicalize(root.path()).unwrap();
+            let exe = join!(&root, MAA_CLI_EXE);
+            let custom_dir = join!(&root, "custom");
+            let custom_lib = join!(&custom_dir, MAA_CORE_LIB);
+            custom_dir.ensure().expect("Failed to create custom dir");
+            File::create(&exe).expect("Failed to create exe file");
+            File::create(join!(&root, MAA_CORE_LIB)).expect("Failed to create lib file");
+            File::create(&custom_lib).expect("Failed to create lib file");
+
+            let find_with = |lib_path: &Path| {
+                let mock = MockVarOs::new()
+                    .with_var("XDG_DATA_HOME", "/xdg")
+                    .with_var("MAA_CORE_LIB_PATH", lib_path.to_str().unwrap());
+                Dirs::new_inner(PROJECT.as_ref(), &mock)
+                    .find_library(&exe)
+                    .map(Cow::into_owned)
+            };
+
+            // Point to the library file
+            assert_eq!(find_with(&custom_lib), Some(custom_dir.clone()));
+            // Point to the directory containing the library
+            assert_eq!(find_with(&custom_dir), Some(custom_dir.clone()));
+            // Missing library falls back to the search relative to the executable
+            assert_eq!(find_with(&root.join("missing")), Some(root.clone()));
+            assert_eq!(
+                find_with(&custom_dir.join("missing.so")),
+                Some(root.clone())
+            );
+            // A file with another name falls back to the search relative to the executable
+            let other_lib = join!(&custom_dir, "other_lib");
+            File::create(&other_lib).expect("Failed to create other file");
+            assert_eq!(find_with(&other_lib), Some(root));
+        }
+
+        #[test]
+        fn log_dir() {
+            // Test with MAA_LOG_DIR set
+            let mock = MockVarOs::new()
+                .with_var("MAA_STATE_DIR", "/maa")
+                .with_var("MAA_LOG_DIR", "/var/log/maa");
+            let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
+            assert_eq!(dirs.state(), PathBuf::from("/maa"));
+            assert_eq!(dirs.log(), PathBuf::from("/var/log/maa"));
+
+            // Test with MAA_LOG_DIR unset
+            let mock = MockVarOs::new().with_var("XDG_STATE_HOME", "/xdg");
+            let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
+            assert_eq!(dirs.log(), PathBuf::from("/xdg/maa/debug"));
+        }
+
+        #[test]
+        #[cfg(windows)]
+        fn find_long_resource() {
+            let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+            let long = "a".repeat(100);
+            let data_dir = join!(canonicalize(test_root.path()).unwrap(), &long, &long);
+            let resource_dir = data_dir.join("resource");
+            assert!(resource_dir.as_os_str().len() > 260);
+            create_dir_all(crate::extended_length(&resource_dir)).unwrap();
+
+            let mock = MockVarOs::new().with_var("MAA_DATA_DIR", data_dir.to_str().unwrap());
+            let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
+            // The found path is kept in the normal form
+            assert_eq!(
+                dirs.find_resource(Path::new("maa.exe")),
+                Some(resource_dir.as_path().into())
+            );
+
+            std::fs::remove_dir_all(crate::extended_length(&data_dir)).unwrap();
+        }
+
         #[test]
         fn cache_dir() {
             // Test with XDG_CACHE_HOME set
@@ -831,12 +1797,17 @@ mod tests {
             let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
             assert_eq!(dirs.cache(), PathBuf::from("/xdg/maa"));
             assert_eq!(dirs.copilot(), PathBuf::from("/xdg/maa/copilot"));
+            assert_eq!(
+                dirs.download_staging(),
+                PathBuf::from("/xdg/maa/downloads/tmp")
+            );
 
             // Test with MAA_CACHE_DIR set
             let mock = MockVarOs::new().with_var("MAA_CACHE_DIR", "/maa");
             let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
             assert_eq!(dirs.cache(), PathBuf::from("/maa"));
             assert_eq!(dirs.copilot(), PathBuf::from("/maa/copilot"));
+            assert_eq!(dirs.download_staging(), PathBuf::from("/maa/downloads/tmp"));
         }
 
         #[test]
@@ -851,6 +1822,186 @@ mod tests {
             let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
             assert_eq!(dirs.config(), PathBuf::from("/maa"));
         }
+
+        #[test]
+        fn find_config() {
+            use std::fs::File;
+
+            let root = tempfile::tempdir().expect("Failed to create temp dir");
+            let root = root.path();
+            let tasks = join!(root, "tasks");
+            tasks.ensure().unwrap();
+
+            let mock = MockVarOs::new().with_var("MAA_CONFIG_DIR", root.to_str().unwrap());
+            let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
+            let find = |stem| dirs.find_config(stem, Some(Path::new("tasks")));
+
+            assert_eq!(find("daily"), None);
+
+            for ext in ["toml", "yml", "yaml", "json"] {
+                File::create(tasks.join(format!("daily.{ext}"))).unwrap();
+                assert_eq!(find("daily"), Some(tasks.join(format!("daily.{ext}"))));
+            }
+            // Dots in the stem are kept
+            File::create(tasks.join("daily.v2.toml")).unwrap();
+            assert_eq!(find("daily.v2"), Some(tasks.join("daily.v2.toml")));
+
+            // Search in the config directory if no sub directory
+            File::create(root.join("cli.toml")).unwrap();
+            assert_eq!(dirs.find_config("cli", None), Some(root.join("cli.toml")));
+            assert_eq!(dirs.find_config("daily", None), None);
+            // Directories are not config files
+            tasks.join("weekly.json").ensure().unwrap();
+            assert_eq!(find("weekly"), None);
+            File::create(tasks.join("weekly.toml")).unwrap();
+            assert_eq!(find("weekly"), Some(tasks.join("weekly.toml")));
+        }
+
+        #[test]
+        fn abs_config() {
+            let mock = MockVarOs::new()
+                .with_var("MAA_CONFIG_DIR", "/maa")
+                .with_var("TASKS", "my-tasks")
+                .with_var("TASKS_DIR", "/opt/tasks");
+            let dirs = Dirs::new_inner(PROJECT.as_ref(), &mock);
+            let abs_config =
+                |path: &str| dirs.abs_config_with(Path::new(path), Some("tasks"), &mock);
+
+            // Plain relative path
+            assert_eq!(
+                abs_config("daily.toml"),
+                Some(PathBuf::from("/maa/tasks/daily.toml"))
+            );
+            // Absolute path
+            assert_eq!(abs_config("/daily.toml"), None);
+            // Tilde
+            assert_eq!(
+                abs_config("~/my-tasks/daily.toml"),
+                Some(home().join("my-tasks").join("daily.toml"))
+            );
+            // Environment variables
+            assert_eq!(
+                abs_config("${TASKS}/daily.toml"),
+                Some(PathBuf::from("/maa/tasks/my-tasks/daily.toml"))
+            );
+            assert_eq!(
+                abs_config("${TASKS_DIR}/daily.toml"),
+                Some(PathBuf::from("/opt/tasks/daily.toml"))
+            );
+            assert_eq!(
+                abs_config("${UNSET}/daily.toml"),
+                Some(PathBuf::from("/maa/tasks/${UNSET}/daily.toml"))
+            );
+        }
+    }
+
+    #[test]
+    fn dirs_override() {
+        use std::sync::{Arc, Barrier};
+
+        let barrier = Arc::new(Barrier::new(2));
+        let handles: Vec<_> = ["/root1", "/root2"]
+            .into_iter()
+            .map(|root| {
+                let barrier = barrier.clone();
+                std::thread::spawn(move || {
+                    let root = Path::new(root);
+                    let guard = DirsOverrideGuard::new(root);
+                    // Make sure both overrides are installed at the same time
+                    barrier.wait();
+                    assert_eq!(data(), root.join("data"));
+                    assert_eq!(library(), join!(root, "data", "lib"));
+                    assert_eq!(state(), root.join("state"));
+                    assert_eq!(log(), join!(root, "state", "debug"));
+                    assert_eq!(cache(), root.join("cache"));
+                    assert_eq!(copilot(), join!(root, "cache", "copilot"));
+                    assert_eq!(config(), root.join("config"));
+
+                    {
+                        let _nested = DirsOverrideGuard::new(root.join("nested"));
+                        assert_eq!(cache(), join!(root, "nested", "cache"));
+                    }
+                    assert_eq!(cache(), root.join("cache"));
+
+                    drop(guard);
+                    assert_eq!(cache(), DIRS.cache());
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_layout() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let _guard = DirsOverrideGuard::new(test_root.path().join("new"));
+        let legacy = test_root.path().join(LEGACY_ROOT);
+        let write = |path: PathBuf, content: &str| {
+            path.parent().unwrap().ensure().unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+
+        // No legacy layout
+        assert!(!dirs().migrate_legacy_layout_from(&legacy).unwrap());
+
+        write(join!(&legacy, "config", "cli.toml"), "legacy");
+        write(join!(&legacy, "config", "tasks", "daily.toml"), "legacy");
+        write(join!(&legacy, "config", "tasks", "weekly.toml"), "legacy");
+        write(join!(&legacy, "lib", "libMaaCore.so"), "legacy");
+        write(join!(&legacy, "unknown", "file"), "legacy");
+        // Existing files in the new layout are not overwritten
+        write(join!(config(), "tasks", "daily.toml"), "new");
+
+        assert!(dirs().migrate_legacy_layout_from(&legacy).unwrap());
+        assert_eq!(read(config().join("cli.toml")), "legacy");
+        assert_eq!(read(join!(config(), "tasks", "daily.toml")), "new");
+        assert_eq!(read(join!(config(), "tasks", "weekly.toml")), "legacy");
+        assert_eq!(read(library().join("libMaaCore.so")), "legacy");
+        assert_eq!(
+            read(join!(&legacy, "config", "tasks", "daily.toml")),
+            "legacy"
+        );
+        assert!(!join!(&legacy, "config", "cli.toml").exists());
+        assert!(!join!(&legacy, "lib").exists());
+        assert!(join!(&legacy, "unknown", "file").exists());
+
+        // Idempotent
+        assert!(!dirs().migrate_legacy_layout_from(&legacy).unwrap());
+        assert_eq!(read(join!(config(), "tasks", "daily.toml")), "new");
+    }
+
+    #[test]
+    fn extended_length() {
+        let path = Path::new("relative").join("a".repeat(300));
+        assert_eq!(super::extended_length(&path), path);
+        let path = Path::new("/short/path");
+        assert_eq!(super::extended_length(path), path);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn extended_length_windows() {
+        let long = "a".repeat(300);
+
+        let path = PathBuf::from(format!(r"C:\Users\maa\.\{long}\..\{long}/lib"));
+        assert_eq!(
+            super::extended_length(&path),
+            PathBuf::from(format!(r"\\?\C:\Users\maa\{long}\lib"))
+        );
+
+        let path = PathBuf::from(format!(r"\\server\share\{long}"));
+        assert_eq!(
+            super::extended_length(&path),
+            PathBuf::from(format!(r"\\?\UNC\server\share\{long}"))
+        );
+
+        // Already extended-length
+        let path = PathBuf::from(format!(r"\\?\C:\{long}"));
+        assert_eq!(super::extended_length(&path), path);
     }
 
     #[test]
@@ -875,6 +2026,334 @@ mod tests {
         assert!(test_dir.exists());
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn ensure_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        // Create if not exists
+        let test_dir = join!(test_root.path(), "state", "maa");
+        assert_eq!(test_dir.as_path().ensure_private().unwrap(), test_dir);
+        assert_eq!(mode(&test_dir), 0o700);
+
+        // Restrict existing dir
+        let test_dir = test_root.path().join("config");
+        test_dir.as_path().ensure().unwrap();
+        std::fs::set_permissions(&test_dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        test_dir.as_path().ensure_private().unwrap();
+        assert_eq!(mode(&test_dir), 0o700);
+    }
+
+    #[test]
+    fn ensure_clean() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let test_dir = test_root.path().join("lib");
+        let old_dir = old_dir_path(&test_dir).unwrap();
+
+        // Create if not exists
+        assert_eq!(test_dir.as_path().ensure_clean().unwrap(), test_dir);
+        assert!(test_dir.is_dir());
+
+        // Clean existing dir
+        std::fs::write(test_dir.join("libMaaCore.so"), "old").unwrap();
+        test_dir.as_path().ensure_clean().unwrap();
+        assert!(test_dir.is_dir());
+        assert_eq!(test_dir.read_dir().unwrap().count(), 0);
+        assert!(!old_dir.exists());
+
+        // Failed to remove the old dir
+        std::fs::write(test_dir.join("libMaaCore.so"), "old").unwrap();
+        ensure_clean_with(&test_dir, |_| {
+            Err(std::io::Error::other("Directory not empty (os error 39)"))
+        })
+        .unwrap();
+        assert_eq!(test_dir.read_dir().unwrap().count(), 0);
+        std::fs::write(test_dir.join("libMaaCore.so"), "new").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(old_dir.join("libMaaCore.so")).unwrap(),
+            "old"
+        );
+
+        // The leftover old dir is removed next time
+        test_dir.as_path().ensure_clean().unwrap();
+        assert_eq!(test_dir.read_dir().unwrap().count(), 0);
+        assert!(!old_dir.exists());
+    }
+
+    #[test]
+    fn install_atomic() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let target = test_root.path().join("lib");
+        let staging = staging_for(&target).unwrap();
+        let prepare = |content: &str| {
+            staging.as_path().ensure_clean().unwrap();
+            std::fs::write(staging.join("libMaaCore.so"), content).unwrap();
+        };
+        let installed = || std::fs::read_to_string(target.join("libMaaCore.so")).unwrap();
+
+        // Fresh install
+        prepare("v1");
+        super::install_atomic(&staging, &target).unwrap();
+        assert_eq!(installed(), "v1");
+        assert!(!staging.exists());
+
+        // Replace the installed one
+        prepare("v2");
+        super::install_atomic(&staging, &target).unwrap();
+        assert_eq!(installed(), "v2");
+        assert!(!staging.exists());
+        assert!(!old_dir_path(&target).unwrap().exists());
+
+        // Roll back if failed to move the staging dir into place
+        prepare("v3");
+        let err = install_atomic_with(&staging, &target, |from, to| {
+            if from == staging {
+                Err(std::io::Error::other("Simulated failure"))
+            } else {
+                rename(from, to)
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Simulated failure");
+        assert_eq!(installed(), "v2");
+        assert!(!old_dir_path(&target).unwrap().exists());
+        assert!(staging.exists());
+    }
+
+    #[test]
+    fn backup_library() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let lib_dir = test_root.path().join("lib");
+        let backup = test_root.path().join("lib.bak");
+        let lib_file = lib_dir.join("libMaaCore.so");
+        let write = |content: &str| {
+            lib_dir.as_path().ensure_clean().unwrap();
+            std::fs::write(&lib_file, content).unwrap();
+            join!(&lib_dir, "deps").as_path().ensure().unwrap();
+            std::fs::write(join!(&lib_dir, "deps", "libonnxruntime.so"), content).unwrap();
+        };
+        let installed = || std::fs::read_to_string(&lib_file).unwrap();
+
+        // Nothing to back up or restore
+        assert!(!backup_dir(&lib_dir).unwrap());
+        assert!(!restore_dir(&lib_dir).unwrap());
+        assert!(!lib_dir.exists());
+
+        // Back up and discard after a successful update
+        write("v1");
+        assert!(backup_dir(&lib_dir).unwrap());
+        assert_eq!(installed(), "v1");
+        assert_eq!(
+            std::fs::read_to_string(join!(&backup, "deps", "libonnxruntime.so")).unwrap(),
+            "v1"
+        );
+        write("v2");
+        discard_backup(&lib_dir).unwrap();
+        assert!(!backup.exists());
+        assert!(!restore_dir(&lib_dir).unwrap());
+        assert_eq!(installed(), "v2");
+
+        // Back up and roll back an aborted update
+        assert!(backup_dir(&lib_dir).unwrap());
+        std::fs::remove_dir_all(&lib_dir).unwrap();
+        lib_dir.as_path().ensure().unwrap();
+        assert!(restore_dir(&lib_dir).unwrap());
+        assert!(!backup.exists());
+        assert_eq!(installed(), "v2");
+        assert_eq!(
+            std::fs::read_to_string(join!(&lib_dir, "deps", "libonnxruntime.so")).unwrap(),
+            "v2"
+        );
+    }
+
+    #[test]
+    fn dir_size() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let _guard = DirsOverrideGuard::new(test_root.path());
+        let write = |path: PathBuf, size: usize| {
+            path.parent().unwrap().ensure().unwrap();
+            std::fs::write(path, vec![0u8; size]).unwrap();
+        };
+
+        assert_eq!(
+            super::dir_size(&test_root.path().join("missing")).unwrap(),
+            0
+        );
+
+        write(join!(data(), "lib", "libMaaCore.so"), 1000);
+        write(join!(resource(), "tasks.json"), 200);
+        write(join!(resource(), "template", "a.png"), 30);
+        write(join!(copilot(), "1234.json"), 4);
+        write(join!(cache(), "MaaCore.tar.gz"), 500);
+        write(join!(log(), "asst.log"), 60);
+
+        assert_eq!(
+            super::dir_size(&resource().join("tasks.json")).unwrap(),
+            200
+        );
+        assert_eq!(sizes().unwrap(), DirSizes {
+            data: 1230,
+            cache: 504,
+            log: 60,
+            resource: 230,
+            copilot: 4,
+        });
+
+        #[cfg(unix)]
+        {
+            // Symbolic link loops and dangling links are skipped
+            std::os::unix::fs::symlink(resource(), join!(resource(), "template", "loop")).unwrap();
+            std::os::unix::fs::symlink(join!(resource(), "missing"), resource().join("dangling"))
+                .unwrap();
+            assert_eq!(super::dir_size(resource()).unwrap(), 230);
+        }
+    }
+
+    #[test]
+    fn copilot_file_name() {
+        let url = "https://prts.maa.plus/copilot/get/40051";
+        assert_eq!(super::copilot_file_name(url), "7d17019fe71f4ced.json");
+        assert_eq!(super::copilot_file_name(""), "cbf29ce484222325.json");
+        assert_ne!(
+            super::copilot_file_name(url),
+            super::copilot_file_name("https://prts.maa.plus/copilot/get/40052")
+        );
+
+        assert!(is_copilot_file_name(OsStr::new("7d17019fe71f4ced.json")));
+        assert!(is_copilot_file_name(OsStr::new("40051.json")));
+        assert!(!is_copilot_file_name(OsStr::new(".json")));
+        assert!(!is_copilot_file_name(OsStr::new("40051s.json")));
+        assert!(!is_copilot_file_name(OsStr::new("7d17019fe71f4ced.toml")));
+    }
+
+    #[test]
+    fn copilot_cache() {
+        const HOUR: Duration = Duration::from_secs(60 * 60);
+
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let _guard = DirsOverrideGuard::new(test_root.path());
+        let now = SystemTime::now();
+        let url = |id: u32| format!("https://prts.maa.plus/copilot/get/{id}");
+        let store = |id: u32, age: Duration| {
+            let path = store_copilot(&url(id), &[0; 100]).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - age)
+                .unwrap();
+            path
+        };
+
+        assert_eq!(cached_copilot(&url(1)), None);
+        assert_eq!(evict_copilot(0).unwrap(), 0);
+
+        let first = store(1, 4 * HOUR);
+        let second = store(2, 3 * HOUR);
+        let third = store(3, 2 * HOUR);
+        let fourth = store(4, HOUR);
+        let user_file = copilot().join("my_copilot.json");
+        std::fs::write(&user_file, [0; 1000]).unwrap();
+        // Files cached by earlier versions are evicted as the least recently used ones
+        let legacy = copilot().join("40051.json");
+        std::fs::write(&legacy, [0; 100]).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&legacy)
+            .unwrap()
+            .set_modified(now - 5 * HOUR)
+            .unwrap();
+        assert_eq!(first, copilot().join(super::copilot_file_name(&url(1))));
+
+        // Retrieving marks the file as recently used
+        assert_eq!(cached_copilot(&url(1)), Some(first.clone()));
+
+        assert_eq!(evict_copilot(500).unwrap(), 0);
+        assert_eq!(evict_copilot(400).unwrap(), 1);
+        assert!(!legacy.exists());
+        assert_eq!(evict_copilot(250).unwrap(), 2);
+        assert!(!second.exists());
+        assert!(!third.exists());
+        assert!(first.exists());
+        assert!(fourth.exists());
+        assert!(user_file.exists());
+
+        assert_eq!(evict_copilot(0).unwrap(), 2);
+        assert_eq!(cached_copilot(&url(1)), None);
+        assert!(user_file.exists());
+    }
+
+    #[test]
+    fn lock() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = test_root.path().join(LOCK_FILE);
+
+        let guard = LockGuard::acquire(path.clone()).unwrap();
+        assert_eq!(guard.path(), path);
+        assert_eq!(
+            LockGuard::acquire(path.clone()).err().unwrap().kind(),
+            std::io::ErrorKind::WouldBlock
+        );
+
+        drop(guard);
+        let guard = LockGuard::acquire(path.clone()).unwrap();
+        drop(guard);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn prune_logs() {
+        use std::fs::File;
+
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+        let log_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let log_dir = log_dir.path();
+        let now = SystemTime::now();
+        let create = |path: &Path, age: Duration| {
+            path.parent().unwrap().ensure().unwrap();
+            File::create(path).unwrap().set_modified(now - age).unwrap();
+        };
+
+        let old_dir = join!(log_dir, "2024", "01", "01");
+        let old = join!(&old_dir, "08:00:00.log");
+        let recent = join!(log_dir, "2024", "01", "08", "08:00:00.log");
+        let active = join!(log_dir, ACTIVE_LOG);
+        let backup = join!(log_dir, "asst.bak.log");
+        create(&old, 10 * DAY);
+        create(&recent, DAY);
+        create(&active, 10 * DAY);
+        create(&backup, 10 * DAY);
+
+        assert_eq!(prune_files(log_dir, 7 * DAY, now).unwrap(), 2);
+        assert!(!old.exists());
+        assert!(!old_dir.exists());
+        assert!(!backup.exists());
+        assert!(recent.exists());
+        assert!(active.exists());
+
+        assert_eq!(prune_files(log_dir, 7 * DAY, now).unwrap(), 0);
+        assert_eq!(prune_files(&log_dir.join("missing"), DAY, now).unwrap(), 0);
+    }
+
+    #[test]
+    #[cfg(any(unix, windows))]
+    fn free_space() {
+        let test_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let test_root = test_root.path();
+
+        let space = super::free_space(test_root).unwrap();
+        assert!(space > 0);
+        // Nonexistent paths use their existing ancestors
+        assert!(super::free_space(&join!(test_root, "not", "exist")).is_ok());
+
+        assert!(check_free_space(test_root, 0).unwrap());
+        assert!(!check_free_space(test_root, u64::MAX).unwrap());
+    }
+
     #[test]
     fn global_path_and_find() {
         let test_root = tempfile::tempdir().expect("Failed to create temp dir");
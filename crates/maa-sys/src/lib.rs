@@ -1,4 +1,14 @@
-use std::ffi::CStr;
+use std::{
+    cmp::min,
+    collections::HashMap,
+    ffi::CStr,
+    mem::MaybeUninit,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
 
 use maa_types::primitive::*;
 pub use maa_types::{InstanceOptionKey, StaticOptionKey, TaskType, TouchMode};
@@ -12,6 +22,8 @@ mod link;
 /// Raw binding of MaaCore API
 pub mod binding;
 
+mod png;
+
 #[cfg_attr(test, derive(PartialEq, Eq))]
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -25,6 +37,8 @@ pub enum Error {
     InvalidUtf8(#[from] std::str::Utf8Error),
     #[error("Invalid UTF-8")]
     InvalidUtf8NoInfo,
+    #[error("Timed out after {0:?}")]
+    Timeout(Duration),
     #[error("{0}")]
     Custom(String),
 }
@@ -37,9 +51,56 @@ impl Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Number of live `Assistant` instances.
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+/// Load MaaCore from the given path and return a guard to unload it.
+#[cfg(feature = "runtime")]
+pub fn load_guard(
+    path: impl AsRef<std::ffi::OsStr>,
+) -> std::result::Result<LoadGuard, libloading::Error> {
+    binding::load(path)?;
+    Ok(LoadGuard {
+        unload: binding::unload,
+    })
+}
+
+/// A guard of the dynamically loaded MaaCore, which unloads MaaCore when dropped.
+///
+/// All `Assistant` instances must be dropped before the guard, because they can not be used
+/// after MaaCore is unloaded. Declaring the guard before any instance in the same scope is
+/// enough, as local variables are dropped in reverse order.
+///
+/// Dropping the guard with live `Assistant` instances logs a warning, as using them after
+/// MaaCore is unloaded will panic.
+#[cfg(feature = "runtime")]
+pub struct LoadGuard {
+    unload: fn(),
+}
+
+#[cfg(feature = "runtime")]
+impl Drop for LoadGuard {
+    fn drop(&mut self) {
+        let instances = Assistant::instances();
+        if instances != 0 {
+            log::warn!("MaaCore unloaded with {instances} live assistant instance(s)");
+        }
+        (self.unload)();
+    }
+}
+
+/// A Rust closure used as the callback of an assistant, see `Assistant::with_callback`.
+type Callback = Box<dyn FnMut(AsstMsgId, &str) + Send>;
+
 /// A safe and convenient wrapper of MaaCore Assistant API.
 pub struct Assistant {
     handle: binding::AsstHandle,
+    /// The closure called by `trampoline`, whose address is passed to MaaCore as custom arg.
+    ///
+    /// Fields are dropped after `Drop::drop`, so the closure outlives the handle.
+    callback: Option<Box<Callback>>,
+    /// Connections reported to `callback`, only available for assistants with a Rust callback.
+    connections: Option<Arc<Connections>>,
 }
 
 impl Drop for Assistant {
@@ -47,26 +108,58 @@ impl Drop for Assistant {
         unsafe {
             binding::AsstDestroy(self.handle);
         }
+        INSTANCES.fetch_sub(1, Ordering::Relaxed);
     }
 }
 
 impl Assistant {
     /// Create a new assistant instance with the given callback and argument.
     pub fn new(callback: binding::AsstApiCallback, arg: Option<*mut std::os::raw::c_void>) -> Self {
+        INSTANCES.fetch_add(1, Ordering::Relaxed);
         match callback {
             Some(cb) => unsafe {
                 let handle = binding::AsstCreateEx(Some(cb), arg.unwrap_or(std::ptr::null_mut()));
-                Self { handle }
+                Self {
+                    handle,
+                    callback: None,
+                    connections: None,
+                }
             },
             None => unsafe {
                 let handle = binding::AsstCreate();
-                Self { handle }
+                Self {
+                    handle,
+                    callback: None,
+                    connections: None,
+                }
             },
         }
     }
 
+    /// Create a new assistant instance with a Rust closure as callback.
+    ///
+    /// The closure is called with the message id and the details of the message as a JSON
+    /// string. It may be called from a thread of MaaCore, so it must be `Send`.
+    pub fn with_callback<F>(cb: F) -> Self
+    where
+        F: FnMut(AsstMsgId, &str) + Send + 'static,
+    {
+        let connections = Arc::new(Connections::default());
+        let mut callback: Box<Callback> = Box::new(Connections::recording(&connections, cb));
+        let arg = callback.as_mut() as *mut Callback as *mut std::os::raw::c_void;
+        let mut asst = Self::new(Some(trampoline), Some(arg));
+        asst.callback = Some(callback);
+        asst.connections = Some(connections);
+        asst
+    }
+
     /* ------------------------- Static Methods ------------------------- */
 
+    /// Get the number of live assistant instances.
+    pub fn instances() -> usize {
+        INSTANCES.load(Ordering::Relaxed)
+    }
+
     /// Set the user directory of the assistant.
     ///
     /// The user directory is used to store the log file and some cache files.
@@ -161,6 +254,21 @@ impl Assistant {
         .to_result()
     }
 
+    /// Append a task with parameters serialized to JSON, return the task id.
+    ///
+    /// # Errors
+    ///
+    /// This function will raise an error if the parameters can not be serialized, e.g. there
+    /// are uninitialized values in it, or raise an error if append task failed.
+    #[cfg(feature = "serde")]
+    pub fn append_task_value(
+        &self,
+        task: TaskType,
+        params: &impl serde::Serialize,
+    ) -> Result<AsstTaskId> {
+        self.append_task(task, params_to_json(params)?.as_str())
+    }
+
     /// Set the parameters of the given task.
     pub fn set_task_params(&self, task_id: AsstTaskId, params: impl ToCString) -> Result<()> {
         unsafe { binding::AsstSetTaskParams(self.handle, task_id, params.to_cstring()?.as_ptr()) }
@@ -207,6 +315,92 @@ impl Assistant {
         .to_result()
     }
 
+    /// Connect to device with the given adb path, address and config, waiting at most `timeout`
+    ///
+    /// Unlike `async_connect` with `block = true`, which may hang forever if the device never
+    /// responds, this issues a non-blocking connection and waits for its result reported by
+    /// callback. For an assistant created by `new`, whose callback is not observable, it polls
+    /// `connected` instead.
+    ///
+    /// MaaCore can not cancel a pending connection, so the connection may still complete after
+    /// this function returns with a `Timeout` error.
+    ///
+    /// # Errors
+    ///
+    /// If the connection failed or is not established within `timeout`, the assistant will be
+    /// stopped, so no task will run on a half-connected device. A failed connection results in
+    /// a `Custom` error with the reason reported by MaaCore, and a pending one in a `Timeout`
+    /// error. Without an observable callback, a failed connection also results in `Timeout`.
+    pub fn connect_timeout(
+        &self,
+        adb_path: impl ToCString,
+        address: impl ToCString,
+        config: impl ToCString,
+        timeout: Duration,
+    ) -> Result<AsstAsyncCallId> {
+        let result = match &self.connections {
+            Some(connections) => {
+                connections.reset();
+                let id = self.async_connect(adb_path, address, config, false)?;
+                connections.wait(id, timeout).map(|()| id)
+            }
+            None => {
+                let id = self.async_connect(adb_path, address, config, false)?;
+                wait_until(timeout, CONNECT_POLL_INTERVAL, || self.connected()).map(|()| id)
+            }
+        };
+        match result {
+            Ok(id) => Ok(id),
+            Err(e) => {
+                let _ = self.stop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Connect to device with the given adb path, address and config, retrying on failure
+    ///
+    /// Each attempt is made by `connect_timeout`, waiting at most `timeout`, and is considered
+    /// successful only if the assistant is `connected` afterwards. After a failed attempt, wait
+    /// `backoff` before the next one, doubling the wait each time, until `retries` retries are
+    /// used up. So an unresponsive device never blocks for longer than about
+    /// `(retries + 1) * timeout` plus the backoff.
+    ///
+    /// For an assistant created by `new`, a failed connection is not observable and an attempt
+    /// only fails after polling `connected` for the whole `timeout`, see `connect_timeout`.
+    ///
+    /// # Errors
+    ///
+    /// If all attempts failed, an error with the address and the number of attempts will be
+    /// returned. Errors of converting arguments are returned immediately without retrying.
+    pub fn reconnect(
+        &self,
+        adb_path: impl ToCString,
+        address: impl ToCString,
+        config: impl ToCString,
+        timeout: Duration,
+        retries: u32,
+        backoff: Duration,
+    ) -> Result<AsstAsyncCallId> {
+        let adb_path = adb_path.to_cstring()?;
+        let address = address.to_cstring()?;
+        let config = config.to_cstring()?;
+
+        retry_connect(
+            retries,
+            backoff,
+            || self.connect_timeout(adb_path.clone(), address.clone(), config.clone(), timeout),
+            || self.connected(),
+        )
+        .ok_or_else(|| {
+            Error::custom(format!(
+                "Failed to connect to {} after {} attempt(s)",
+                address.to_string_lossy(),
+                retries as u64 + 1,
+            ))
+        })
+    }
+
     /// Click the screen at the given position
     pub fn async_click(&self, x: i32, y: i32, block: bool) -> Result<AsstAsyncCallId> {
         unsafe { binding::AsstAsyncClick(self.handle, x, y, block.into()) }.to_result()
@@ -229,6 +423,51 @@ impl Assistant {
         .to_result()
     }
 
+    /// Save the last screenshot to the given buffer, growing it if needed.
+    ///
+    /// The capacity of the buffer is reused and retained, so a buffer reused across calls is
+    /// allocated only once. On success, the length of the buffer is the size of the image.
+    /// On error, the buffer is cleared, and its capacity is restored if it was grown.
+    pub fn get_image_into(&self, buff: &mut Vec<u8>) -> Result<()> {
+        fill_image_buffer(buff, |spare| unsafe {
+            binding::AsstGetImage(
+                self.handle,
+                spare.as_mut_ptr() as *mut std::os::raw::c_void,
+                spare.len() as AsstSize,
+            )
+            .to_result()
+        })
+    }
+
+    /// Get the resolution of the connected device as `(width, height)`.
+    ///
+    /// MaaCore does not expose the device resolution, so this takes a screenshot and reads the
+    /// size from the header of the PNG image returned by `get_image`.
+    ///
+    /// Note: the screenshot may be scaled by MaaCore, so the size is the one used by MaaCore
+    /// for recognition and touch, which may differ from the physical resolution of the device.
+    pub fn screen_size(&self) -> Result<(u32, u32)> {
+        let image = self.screenshot()?;
+        png::png_size(&image).ok_or_else(|| Error::custom("Screenshot is not a PNG image"))
+    }
+
+    /// Take a screenshot and decode it as an image.
+    ///
+    /// Like `screen_size`, the image is the one used by MaaCore, which may be scaled.
+    #[cfg(feature = "image")]
+    pub fn get_image_decoded(&self) -> Result<image::DynamicImage> {
+        png::decode_png(&self.screenshot()?)
+    }
+
+    /// Take a screenshot and return the PNG image returned by `get_image`.
+    fn screenshot(&self) -> Result<Vec<u8>> {
+        self.async_screncap(true)?;
+
+        let mut buff = Vec::new();
+        self.get_image_into(&mut buff)?;
+        Ok(buff)
+    }
+
     /// Get the UUID of the device
     pub fn get_uuid(&self, buff: &mut [u8], buff_size: AsstSize) -> Result<AsstSize> {
         unsafe {
@@ -242,6 +481,223 @@ impl Assistant {
     }
 }
 
+/// Serialize the task parameters to a JSON string passed to MaaCore.
+#[cfg(feature = "serde")]
+fn params_to_json(params: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string(params)
+        .map_err(|e| Error::custom(format!("Failed to serialize task params: {e}")))
+}
+
+/// Decode the message from MaaCore and pass it to the closure stored at `custom_arg`.
+///
+/// # Safety
+///
+/// `custom_arg` must point to a live `Callback`, and `details_json` must be null or a valid
+/// C string. Details which are not valid UTF-8 are passed as an empty string.
+unsafe extern "C" fn trampoline(
+    msg: AsstMsgId,
+    details_json: *const std::os::raw::c_char,
+    custom_arg: *mut std::os::raw::c_void,
+) {
+    let callback = unsafe { &mut *(custom_arg as *mut Callback) };
+    let details = if details_json.is_null() {
+        ""
+    } else {
+        unsafe { CStr::from_ptr(details_json) }
+            .to_str()
+            .unwrap_or_default()
+    };
+    callback(msg, details);
+}
+
+/// Initial size of image buffers, enough for most screenshots
+const IMAGE_INIT_SIZE: usize = 1 << 22;
+/// Maximum size of image buffers, a raw 4K RGB image is about 24 MiB
+const IMAGE_MAX_SIZE: usize = 1 << 26;
+
+/// Growth factor of image buffers, so at most three attempts are made from the initial size
+const IMAGE_GROWTH: usize = 4;
+
+/// Fill the buffer by `get_image`, growing its capacity until the image fits.
+///
+/// The spare capacity of the cleared buffer is passed to `get_image` without being initialized,
+/// and the length of the buffer is set to the size of the image returned by `get_image`.
+///
+/// MaaCore returns the same error when the buffer is too small and when there is no image,
+/// so the buffer is grown only up to `IMAGE_MAX_SIZE`. On error, the buffer is cleared and
+/// shrunk back to its original capacity, so a failure does not leave a huge buffer behind.
+fn fill_image_buffer(
+    buff: &mut Vec<u8>,
+    mut get_image: impl FnMut(&mut [MaybeUninit<u8>]) -> Result<AsstSize>,
+) -> Result<()> {
+    buff.clear();
+    let original = buff.capacity();
+    buff.reserve(IMAGE_INIT_SIZE);
+    loop {
+        let spare = buff.spare_capacity_mut();
+        let capacity = spare.len();
+        match get_image(spare) {
+            Ok(size) => {
+                // SAFETY: the first `size` bytes are written by `get_image`
+                unsafe { buff.set_len(min(size as usize, capacity)) };
+                return Ok(());
+            }
+            Err(Error::MAAError) if capacity < IMAGE_MAX_SIZE => {
+                buff.reserve((capacity * IMAGE_GROWTH).min(IMAGE_MAX_SIZE))
+            }
+            Err(e) => {
+                buff.shrink_to(original);
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Message id of `ConnectionInfo`, reporting the progress of connections.
+const CONNECTION_INFO: AsstMsgId = 2;
+/// Message id of `AsyncCallInfo`, reporting the result of async calls.
+const ASYNC_CALL_INFO: AsstMsgId = 4;
+
+/// Connections reported to the callback of an assistant, see `Assistant::connect_timeout`.
+#[derive(Default)]
+struct Connections {
+    state: Mutex<ConnectionState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct ConnectionState {
+    /// Results of finished async connect calls by call id
+    results: HashMap<AsstAsyncCallId, bool>,
+    /// Reason of the last `ConnectFailed` since the last `reset`
+    failure: Option<String>,
+}
+
+impl Connections {
+    /// Wrap `cb` into a callback which records connections before calling `cb`.
+    fn recording<F>(connections: &Arc<Self>, mut cb: F) -> Callback
+    where
+        F: FnMut(AsstMsgId, &str) + Send + 'static,
+    {
+        let connections = Arc::clone(connections);
+        Box::new(move |msg, details| {
+            connections.record(msg, details);
+            cb(msg, details);
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ConnectionState> {
+        // The state is always consistent, so it is fine to ignore poisoning
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record a message of MaaCore if it reports the failure or the result of a connection.
+    ///
+    /// A `ConnectFailed` in `ConnectionInfo` has no call id, so it is recorded as the failure of
+    /// the pending connection. The result of a connect call is recorded by its call id from
+    /// `AsyncCallInfo`.
+    fn record(&self, msg: AsstMsgId, details: &str) {
+        if msg != CONNECTION_INFO && msg != ASYNC_CALL_INFO {
+            return;
+        }
+        let Ok(details) = serde_json::from_str::<serde_json::Value>(details) else {
+            return;
+        };
+
+        let mut state = self.lock();
+        match (msg, details["what"].as_str()) {
+            (CONNECTION_INFO, Some("ConnectFailed")) => {
+                let why = details["why"].as_str().unwrap_or("unknown reason");
+                state.failure = Some(why.to_owned());
+            }
+            (ASYNC_CALL_INFO, Some("Connect")) => {
+                let id = details["async_call_id"].as_i64();
+                let ret = details["details"]["ret"].as_bool();
+                let (Some(id), Some(ret)) = (id.and_then(|id| id.try_into().ok()), ret) else {
+                    return;
+                };
+                state.results.insert(id, ret);
+            }
+            _ => return,
+        }
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// Forget the failure of previous connections, called before a new connection.
+    fn reset(&self) {
+        self.lock().failure = None;
+    }
+
+    /// Wait until the connect call `id` finishes or fails, at most `timeout`.
+    fn wait(&self, id: AsstAsyncCallId, timeout: Duration) -> Result<()> {
+        let (mut state, _) = self
+            .changed
+            .wait_timeout_while(self.lock(), timeout, |state| {
+                state.failure.is_none() && !state.results.contains_key(&id)
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+
+        match (state.results.remove(&id), state.failure.take()) {
+            (Some(true), _) => Ok(()),
+            (_, Some(why)) => Err(Error::custom(format!("Failed to connect: {why}"))),
+            (Some(false), None) => Err(Error::custom("Failed to connect")),
+            (None, None) => Err(Error::Timeout(timeout)),
+        }
+    }
+}
+
+/// Interval to poll the connection status in `connect_timeout`.
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait until `cond` returns true, checking it every `interval`.
+///
+/// Return a `Timeout` error if `cond` is still false after `timeout`.
+fn wait_until(timeout: Duration, interval: Duration, mut cond: impl FnMut() -> bool) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        if cond() {
+            return Ok(());
+        }
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(Error::Timeout(timeout));
+        }
+        std::thread::sleep(interval.min(timeout - elapsed));
+    }
+}
+
+/// Call `attempt` until it returns `Some`, at most `retries + 1` times.
+///
+/// Wait `backoff` after the first failed attempt, and double the wait after each failure.
+fn retry_with_backoff<T>(
+    retries: u32,
+    backoff: Duration,
+    mut attempt: impl FnMut() -> Option<T>,
+) -> Option<T> {
+    let mut wait = backoff;
+    for i in 0..=retries {
+        if let Some(value) = attempt() {
+            return Some(value);
+        }
+        if i < retries {
+            std::thread::sleep(wait);
+            wait = wait.saturating_mul(2);
+        }
+    }
+    None
+}
+
+/// Call `connect` until it succeeds and `connected` is true afterwards, see `retry_with_backoff`.
+fn retry_connect<T>(
+    retries: u32,
+    backoff: Duration,
+    mut connect: impl FnMut() -> Result<T>,
+    connected: impl Fn() -> bool,
+) -> Option<T> {
+    retry_with_backoff(retries, backoff, || connect().ok().filter(|_| connected()))
+}
+
 trait AsstResult {
     /// The return type of the function
     type Return;
@@ -310,6 +766,360 @@ mod tests {
         }
     }
 
+    #[test]
+    fn wait_until_timeout() {
+        let interval = Duration::from_millis(1);
+        let timeout = Duration::from_millis(20);
+
+        // connection established after a few polls
+        let mut polls = 0;
+        assert_eq!(
+            wait_until(timeout, interval, || {
+                polls += 1;
+                polls == 3
+            }),
+            Ok(())
+        );
+        assert_eq!(polls, 3);
+
+        // connection callback never arrives
+        let start = Instant::now();
+        assert_eq!(
+            wait_until(timeout, interval, || false),
+            Err(Error::Timeout(timeout))
+        );
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn connections() {
+        use std::{sync::mpsc, thread};
+
+        let timeout = Duration::from_millis(20);
+        let (tx, rx) = mpsc::channel();
+        let connections = Arc::new(Connections::default());
+        let mut callback = Connections::recording(&connections, move |msg, _| {
+            tx.send(msg).unwrap();
+        });
+
+        // messages are still passed to the wrapped closure
+        callback(
+            ASYNC_CALL_INFO,
+            r#"{"what":"Connect","async_call_id":1,"details":{"ret":true}}"#,
+        );
+        callback(10001, "{}");
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), vec![4, 10001]);
+        assert_eq!(connections.wait(1, timeout), Ok(()));
+
+        // result reported later from another thread
+        let handle = thread::spawn({
+            let connections = Arc::clone(&connections);
+            move || {
+                thread::sleep(Duration::from_millis(5));
+                let details = r#"{"what":"Connect","async_call_id":2,"details":{"ret":true}}"#;
+                connections.record(ASYNC_CALL_INFO, details);
+            }
+        });
+        assert_eq!(connections.wait(2, Duration::from_secs(10)), Ok(()));
+        handle.join().unwrap();
+
+        // connection failed with reason
+        connections.reset();
+        callback(
+            CONNECTION_INFO,
+            r#"{"what":"ConnectFailed","why":"Connection command failed to exec"}"#,
+        );
+        assert_eq!(
+            connections.wait(3, timeout),
+            Err(Error::custom(
+                "Failed to connect: Connection command failed to exec"
+            ))
+        );
+
+        // connection failed without reason
+        callback(
+            ASYNC_CALL_INFO,
+            r#"{"what":"Connect","async_call_id":4,"details":{"ret":false}}"#,
+        );
+        assert_eq!(
+            connections.wait(4, timeout),
+            Err(Error::custom("Failed to connect"))
+        );
+
+        // results of other calls and unrelated messages are ignored
+        callback(
+            ASYNC_CALL_INFO,
+            r#"{"what":"Connect","async_call_id":6,"details":{"ret":true}}"#,
+        );
+        callback(CONNECTION_INFO, r#"{"what":"Connected"}"#);
+        callback(CONNECTION_INFO, "not json");
+        let start = Instant::now();
+        assert_eq!(connections.wait(5, timeout), Err(Error::Timeout(timeout)));
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn params_to_json() {
+        use std::collections::BTreeMap;
+
+        let params = BTreeMap::from([("stage", "1-7"), ("client_type", "Official")]);
+        assert_eq!(
+            super::params_to_json(&params).unwrap(),
+            r#"{"client_type":"Official","stage":"1-7"}"#
+        );
+
+        struct Uninit;
+
+        impl serde::Serialize for Uninit {
+            fn serialize<S: serde::Serializer>(
+                &self,
+                _: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("uninitialized input"))
+            }
+        }
+
+        assert_eq!(
+            super::params_to_json(&Uninit),
+            Err(Error::custom(
+                "Failed to serialize task params: uninitialized input"
+            ))
+        );
+    }
+
+    #[test]
+    fn trampoline() {
+        use std::sync::mpsc;
+
+        let (tx, rx) = mpsc::channel();
+        let mut callback: Callback = Box::new(move |msg, details| {
+            tx.send((msg, details.to_owned())).unwrap();
+        });
+        let arg = &mut callback as *mut Callback as *mut std::os::raw::c_void;
+
+        unsafe {
+            super::trampoline(2, c"{\"what\":\"Connected\"}".as_ptr(), arg);
+            super::trampoline(3, std::ptr::null(), arg);
+            super::trampoline(4, c"\xff".as_ptr(), arg);
+        }
+        drop(callback);
+
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![
+            (2, r#"{"what":"Connected"}"#.to_owned()),
+            (3, String::new()),
+            (4, String::new()),
+        ]);
+    }
+
+    #[test]
+    fn fill_image_buffer() {
+        /// Mock `get_image` returning an image of given size
+        fn image(size: usize) -> impl FnMut(&mut [MaybeUninit<u8>]) -> Result<AsstSize> {
+            move |buff| {
+                if buff.len() < size {
+                    return Err(Error::MAAError);
+                }
+                buff[..size].fill(MaybeUninit::new(1));
+                Ok(size as AsstSize)
+            }
+        }
+
+        // an empty buffer is grown to the initial size
+        let mut buff = Vec::new();
+        super::fill_image_buffer(&mut buff, image(100)).unwrap();
+        assert_eq!(buff, vec![1; 100]);
+        assert!(buff.capacity() >= IMAGE_INIT_SIZE);
+
+        // the buffer is grown until the image fits
+        let mut buff = Vec::new();
+        super::fill_image_buffer(&mut buff, image(IMAGE_INIT_SIZE * 3)).unwrap();
+        assert_eq!(buff.len(), IMAGE_INIT_SIZE * 3);
+        assert!(buff.capacity() >= IMAGE_INIT_SIZE * 3);
+
+        // a pre-grown buffer is reused without shrinking
+        let capacity = buff.capacity();
+        let ptr = buff.as_ptr();
+        super::fill_image_buffer(&mut buff, image(10)).unwrap();
+        assert_eq!(buff, vec![1; 10]);
+        assert_eq!(buff.capacity(), capacity);
+        assert_eq!(buff.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn fill_image_buffer_error() {
+        // no image, e.g. disconnected, which is indistinguishable from a small buffer
+        let mut calls = 0;
+        let mut buff = Vec::with_capacity(IMAGE_INIT_SIZE);
+        buff.push(1);
+        assert_eq!(
+            super::fill_image_buffer(&mut buff, |_| {
+                calls += 1;
+                Err(Error::MAAError)
+            }),
+            Err(Error::MAAError)
+        );
+        assert_eq!(calls, 3);
+        assert!(buff.is_empty());
+        assert!(buff.capacity() < IMAGE_INIT_SIZE * IMAGE_GROWTH);
+
+        // give up when the maximum size is reached
+        let mut buff = Vec::new();
+        assert_eq!(
+            super::fill_image_buffer(&mut buff, |spare| {
+                assert!(spare.len() <= IMAGE_MAX_SIZE * 2);
+                Err(Error::MAAError)
+            }),
+            Err(Error::MAAError)
+        );
+        assert!(buff.capacity() < IMAGE_INIT_SIZE * IMAGE_GROWTH);
+
+        // other errors are returned immediately
+        let mut calls = 0;
+        let mut buff = Vec::new();
+        assert_eq!(
+            super::fill_image_buffer(&mut buff, |_| {
+                calls += 1;
+                Err(Error::BufferTooSmall)
+            }),
+            Err(Error::BufferTooSmall)
+        );
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn retry_with_backoff() {
+        let backoff = Duration::from_millis(1);
+
+        // device comes back after two failed attempts
+        let mut attempts = 0;
+        let start = Instant::now();
+        assert_eq!(
+            super::retry_with_backoff(3, backoff, || {
+                attempts += 1;
+                (attempts == 3).then_some(attempts)
+            }),
+            Some(3)
+        );
+        assert_eq!(attempts, 3);
+        // waited 1ms and 2ms between attempts
+        assert!(start.elapsed() >= backoff * 3);
+
+        // device never comes back
+        let mut attempts = 0;
+        assert_eq!(
+            super::retry_with_backoff(2, backoff, || -> Option<()> {
+                attempts += 1;
+                None
+            }),
+            None
+        );
+        assert_eq!(attempts, 3);
+
+        // no retry
+        let mut attempts = 0;
+        assert_eq!(
+            super::retry_with_backoff(0, backoff, || -> Option<()> {
+                attempts += 1;
+                None
+            }),
+            None
+        );
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn retry_connect() {
+        use std::cell::Cell;
+
+        let backoff = Duration::from_millis(1);
+        let timeout = Duration::from_millis(20);
+
+        // connection timed out, then succeeded but device not connected, then connected
+        let attempts = Cell::new(0);
+        assert_eq!(
+            super::retry_connect(
+                3,
+                backoff,
+                || {
+                    attempts.set(attempts.get() + 1);
+                    match attempts.get() {
+                        1 => Err(Error::Timeout(timeout)),
+                        n => Ok(n),
+                    }
+                },
+                || attempts.get() >= 3,
+            ),
+            Some(3)
+        );
+        assert_eq!(attempts.get(), 3);
+
+        // connect call always succeeds but device never connected
+        let mut attempts = 0;
+        assert_eq!(
+            super::retry_connect(
+                2,
+                backoff,
+                || {
+                    attempts += 1;
+                    Ok(attempts)
+                },
+                || false,
+            ),
+            None
+        );
+        assert_eq!(attempts, 3);
+    }
+
+    #[cfg(feature = "runtime")]
+    mod load_guard {
+        use std::sync::atomic::AtomicBool;
+
+        use super::*;
+
+        static UNLOADED: AtomicBool = AtomicBool::new(false);
+
+        fn mock_unload() {
+            UNLOADED.store(true, Ordering::Relaxed);
+        }
+
+        #[test]
+        fn unload_on_drop() {
+            let guard = LoadGuard {
+                unload: mock_unload,
+            };
+            assert!(!UNLOADED.load(Ordering::Relaxed));
+            drop(guard);
+            assert!(UNLOADED.load(Ordering::Relaxed));
+
+            // Unloading with live instances only warns
+            UNLOADED.store(false, Ordering::Relaxed);
+            INSTANCES.fetch_add(1, Ordering::Relaxed);
+            let result = std::panic::catch_unwind(|| {
+                drop(LoadGuard {
+                    unload: mock_unload,
+                })
+            });
+            INSTANCES.fetch_sub(1, Ordering::Relaxed);
+            assert!(result.is_ok());
+            assert!(UNLOADED.load(Ordering::Relaxed));
+        }
+
+        #[test]
+        fn load_guard() {
+            assert!(super::load_guard("/path/to/not/exist/libMaaCore.so").is_err());
+            assert!(!binding::loaded());
+
+            // A failed load leaves nothing to unload, dropping a real guard unloads MaaCore
+            if let Some(path) = std::env::var_os("MAA_CORE_LIB") {
+                let guard = super::load_guard(path).unwrap();
+                assert!(binding::loaded());
+                drop(guard);
+                assert!(!binding::loaded());
+            }
+        }
+    }
+
     #[test]
     fn asst_bool() {
         assert_eq!(0u8.to_result(), Err(super::Error::MAAError));
@@ -0,0 +1,92 @@
+//! Minimal PNG header parser
+
+/// The 8-byte signature at the start of every PNG file
+const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Get the width and height of a PNG image from its header.
+///
+/// Only the signature and the `IHDR` chunk (which must be the first chunk) are read,
+/// so the rest of the image does not need to be valid or even present.
+///
+/// Return `None` if the data is not a PNG image.
+pub(crate) fn png_size(data: &[u8]) -> Option<(u32, u32)> {
+    // signature (8) + chunk length (4) + chunk type (4) + width (4) + height (4)
+    let header = data.get(..24)?;
+    if &header[..8] != SIGNATURE || &header[12..16] != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(header[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(header[20..24].try_into().ok()?);
+
+    Some((width, height))
+}
+
+/// Decode a PNG image, e.g. a screenshot returned by MaaCore.
+#[cfg(feature = "image")]
+pub(crate) fn decode_png(data: &[u8]) -> crate::Result<image::DynamicImage> {
+    image::load_from_memory_with_format(data, image::ImageFormat::Png)
+        .map_err(|e| crate::Error::custom(format!("Failed to decode screenshot: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the beginning of a PNG screenshot with given size
+    fn screenshot(width: u32, height: u32) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        data.extend_from_slice(&13u32.to_be_bytes());
+        data.extend_from_slice(b"IHDR");
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        // bit depth, color type, compression, filter, interlace
+        data.extend_from_slice(&[8, 2, 0, 0, 0]);
+        // CRC, not checked
+        data.extend_from_slice(&[0; 4]);
+        data
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(png_size(&screenshot(1280, 720)), Some((1280, 720)));
+        assert_eq!(png_size(&screenshot(2560, 1440)), Some((2560, 1440)));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn decode() {
+        use image::GenericImageView;
+
+        // A 2x1 RGB image with a red and a blue pixel
+        const RED_BLUE: &[u8] = &[
+            0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48,
+            0x44, 0x52, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00,
+            0x00, 0x7b, 0x40, 0xe8, 0xdd, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x44, 0x41, 0x54, 0x78,
+            0x9c, 0x63, 0xf8, 0xcf, 0x00, 0x04, 0xff, 0x01, 0x07, 0x00, 0x01, 0xff, 0xe2, 0x23,
+            0x9e, 0x59, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+        ];
+
+        let image = decode_png(RED_BLUE).unwrap();
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(png_size(RED_BLUE), Some((2, 1)));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 0, 255, 255]);
+
+        assert!(decode_png(&screenshot(1280, 720)).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(png_size(&[]), None);
+        assert_eq!(png_size(&screenshot(1280, 720)[..20]), None);
+
+        let mut data = screenshot(1280, 720);
+        data[0] = 0;
+        assert_eq!(png_size(&data), None);
+
+        let mut data = screenshot(1280, 720);
+        data[12..16].copy_from_slice(b"IDAT");
+        assert_eq!(png_size(&data), None);
+    }
+}
@@ -21,6 +21,18 @@ pub mod primitive {
     pub type AsstInstanceOptionKey = AsstOptionKey;
 }
 
+mod stage;
+pub use stage::{InvalidStageError, Stage, StageCategory};
+
+/// Language of human-facing names, e.g. [`TaskType::display_name`]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Lang {
+    #[default]
+    En,
+    ZhCn,
+    Ja,
+}
+
 /// Available static option key
 #[repr(u8)]
 #[derive(Clone, Copy)]
@@ -47,7 +59,7 @@ pub enum InstanceOptionKey {
 
 /// Available touch mode
 #[repr(u8)]
-#[derive(Default, Clone, Copy, PartialEq)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TouchMode {
     #[default]
     Adb,
@@ -67,15 +79,17 @@ impl TouchMode {
         }
         names
     };
-    pub const VARIANTS: [TouchMode; Self::COUNT] = {
-        let mut i = 0;
-        let mut variants = [TouchMode::Adb; Self::COUNT];
-        while i < Self::COUNT {
-            variants[i] = unsafe { std::mem::transmute::<u8, Self>(i as u8) };
-            i += 1;
-        }
-        variants
-    };
+    pub const VARIANTS: [TouchMode; Self::COUNT] = [
+        TouchMode::Adb,
+        TouchMode::MiniTouch,
+        TouchMode::MaaTouch,
+        TouchMode::MacPlayTools,
+    ];
+
+    /// Iterate over all touch modes in declaration order
+    pub fn iter() -> impl Iterator<Item = TouchMode> {
+        Self::VARIANTS.into_iter()
+    }
 
     /// Convert TouchMode to a static string slice
     pub const fn to_str(self) -> &'static str {
@@ -87,6 +101,40 @@ impl TouchMode {
         }
     }
 
+    /// The first version of MaaCore supporting the touch mode
+    #[cfg(feature = "semver")]
+    pub const fn supported_since(self) -> semver::Version {
+        match self {
+            TouchMode::Adb | TouchMode::MiniTouch => semver::Version::new(4, 0, 0),
+            TouchMode::MaaTouch => semver::Version::new(4, 12, 0),
+            TouchMode::MacPlayTools => semver::Version::new(4, 13, 0),
+        }
+    }
+
+    /// Check if the touch mode is supported by the given version of MaaCore
+    ///
+    /// Pre-releases of the first supported version are considered as supported.
+    #[cfg(feature = "semver")]
+    pub fn is_supported_by(self, core: &semver::Version) -> bool {
+        let since = self.supported_since();
+        (core.major, core.minor, core.patch) >= (since.major, since.minor, since.patch)
+    }
+
+    /// Human-facing name of the touch mode in the given language
+    ///
+    /// Fall back to the name returned by `to_str` if there is no translation.
+    pub const fn display_name(self, lang: Lang) -> &'static str {
+        let translated = match (lang, self) {
+            (Lang::ZhCn, TouchMode::Adb) => Some("ADB 输入"),
+            (Lang::Ja, TouchMode::Adb) => Some("ADB 入力"),
+            _ => None,
+        };
+        match translated {
+            Some(name) => name,
+            None => self.to_str(),
+        }
+    }
+
     fn from_str_opt(s: &str) -> Option<TouchMode> {
         Self::VARIANTS
             .iter()
@@ -95,6 +143,30 @@ impl TouchMode {
     }
 }
 
+impl TryFrom<u8> for TouchMode {
+    type Error = UnknownTouchModeError;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(TouchMode::Adb),
+            1 => Ok(TouchMode::MiniTouch),
+            2 => Ok(TouchMode::MaaTouch),
+            3 => Ok(TouchMode::MacPlayTools),
+            _ => Err(UnknownTouchModeError(v.to_string())),
+        }
+    }
+}
+
+impl TryFrom<u64> for TouchMode {
+    type Error = UnknownTouchModeError;
+
+    fn try_from(v: u64) -> Result<Self, Self::Error> {
+        u8::try_from(v)
+            .map_err(|_| UnknownTouchModeError(v.to_string()))
+            .and_then(Self::try_from)
+    }
+}
+
 impl std::str::FromStr for TouchMode {
     type Err = UnknownTouchModeError;
 
@@ -142,11 +214,8 @@ impl<'de> serde::Deserialize<'de> for TouchMode {
             where
                 E: serde::de::Error,
             {
-                if v < TouchMode::COUNT as u64 {
-                    Ok(unsafe { std::mem::transmute::<u8, TouchMode>(v as u8) })
-                } else {
-                    Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
-                }
+                TouchMode::try_from(v)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
             }
 
             fn visit_str<E>(self, value: &str) -> std::result::Result<TouchMode, E>
@@ -162,13 +231,19 @@ impl<'de> serde::Deserialize<'de> for TouchMode {
     }
 }
 
+/// Serialize as the discriminant, or the name if the feature `serde-string` is enabled.
+///
+/// Both forms can be deserialized regardless of the feature.
 #[cfg(feature = "serde")]
 impl serde::Serialize for TouchMode {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_u64(*self as u64)
+        #[cfg(feature = "serde-string")]
+        return serializer.serialize_str(self.to_str());
+        #[cfg(not(feature = "serde-string"))]
+        return serializer.serialize_u64(*self as u64);
     }
 }
 
@@ -184,9 +259,166 @@ impl std::fmt::Display for TouchMode {
     }
 }
 
+/// Available client type (server) of the game
+#[repr(u8)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientType {
+    #[default]
+    Official,
+    Bilibili,
+    Txwy,
+    YoStarEN,
+    YoStarJP,
+    YoStarKR,
+}
+
+impl ClientType {
+    pub const COUNT: usize = 6;
+    pub const NAMES: [&'static str; Self::COUNT] = {
+        let mut i = 0;
+        let mut names = [""; Self::COUNT];
+        while i < Self::COUNT {
+            names[i] = Self::VARIANTS[i].to_str();
+            i += 1;
+        }
+        names
+    };
+    pub const VARIANTS: [Self; Self::COUNT] = [
+        Self::Official,
+        Self::Bilibili,
+        Self::Txwy,
+        Self::YoStarEN,
+        Self::YoStarJP,
+        Self::YoStarKR,
+    ];
+
+    /// Convert ClientType to a static string slice, which is the name used by MaaCore
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::Official => "Official",
+            Self::Bilibili => "Bilibili",
+            Self::Txwy => "txwy",
+            Self::YoStarEN => "YoStarEN",
+            Self::YoStarJP => "YoStarJP",
+            Self::YoStarKR => "YoStarKR",
+        }
+    }
+
+    /// The sub directory of global resource used by this client, e.g. `YoStarEN` in
+    /// `resource/global/YoStarEN`.
+    ///
+    /// Return `None` for clients using the default resource, i.e. `Official` and `Bilibili`.
+    pub const fn resource_suffix(self) -> Option<&'static str> {
+        match self {
+            Self::Official | Self::Bilibili => None,
+            Self::Txwy => Some("txwy"),
+            Self::YoStarEN => Some("YoStarEN"),
+            Self::YoStarJP => Some("YoStarJP"),
+            Self::YoStarKR => Some("YoStarKR"),
+        }
+    }
+
+    fn from_str_opt(s: &str) -> Option<Self> {
+        const ALIASES: [(&str, ClientType); 6] = [
+            ("CN", ClientType::Official),
+            ("TW", ClientType::Txwy),
+            ("Global", ClientType::YoStarEN),
+            ("EN", ClientType::YoStarEN),
+            ("JP", ClientType::YoStarJP),
+            ("KR", ClientType::YoStarKR),
+        ];
+
+        Self::VARIANTS
+            .iter()
+            .map(|v| (v.to_str(), *v))
+            .chain(ALIASES)
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, v)| v)
+    }
+}
+
+impl std::str::FromStr for ClientType {
+    type Err = UnknownClientTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_str_opt(s).ok_or_else(|| UnknownClientTypeError(s.to_owned()))
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug)]
+pub struct UnknownClientTypeError(String);
+
+impl std::fmt::Display for UnknownClientTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown client type `{}`, expected one of ", self.0)?;
+        let mut iter = ClientType::NAMES.iter();
+        if let Some(name) = iter.next() {
+            write!(f, "`{}`", name)?;
+            for v in iter {
+                write!(f, ", `{}`", v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownClientTypeError {}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ClientType {
+    fn deserialize<D>(deserializer: D) -> Result<ClientType, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ClientTypeVisitor;
+
+        impl serde::de::Visitor<'_> for ClientTypeVisitor {
+            type Value = ClientType;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a valid client type")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ClientType, E>
+            where
+                E: serde::de::Error,
+            {
+                ClientType::from_str_opt(value)
+                    .ok_or_else(|| E::unknown_variant(value, &ClientType::NAMES))
+            }
+        }
+
+        deserializer.deserialize_str(ClientTypeVisitor)
+    }
+}
+
+/// Serialize as the name used by MaaCore, because the client type is passed in task params.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClientType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.to_str())
+    }
+}
+
+impl std::fmt::Debug for ClientType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+impl std::fmt::Display for ClientType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
 /// Available task type for MAA
 #[repr(u8)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TaskType {
     StartUp,
     CloseDown,
@@ -207,6 +439,20 @@ pub enum TaskType {
 }
 
 impl TaskType {
+    /// Common synonyms of task types, which are matched case-insensitively
+    const ALIASES: [(&'static str, Self); 11] = [
+        ("start", Self::StartUp),
+        ("close", Self::CloseDown),
+        ("combat", Self::Fight),
+        ("battle", Self::Fight),
+        ("infra", Self::Infrast),
+        ("base", Self::Infrast),
+        ("credit-store", Self::Mall),
+        ("shop", Self::Mall),
+        ("mission", Self::Award),
+        ("rogue", Self::Roguelike),
+        ("sss", Self::SSSCopilot),
+    ];
     pub const COUNT: usize = 16;
     pub const NAMES: [&'static str; Self::COUNT] = {
         let mut i = 0;
@@ -217,15 +463,107 @@ impl TaskType {
         }
         names
     };
-    pub const VARIANTS: [Self; Self::COUNT] = {
-        let mut i = 0;
-        let mut variants = [Self::StartUp; Self::COUNT];
-        while i < Self::COUNT {
-            variants[i] = unsafe { std::mem::transmute::<u8, Self>(i as u8) };
-            i += 1;
+    pub const VARIANTS: [Self; Self::COUNT] = [
+        Self::StartUp,
+        Self::CloseDown,
+        Self::Fight,
+        Self::Recruit,
+        Self::Infrast,
+        Self::Mall,
+        Self::Award,
+        Self::Roguelike,
+        Self::Copilot,
+        Self::SSSCopilot,
+        Self::Depot,
+        Self::OperBox,
+        Self::Reclamation,
+        Self::Custom,
+        Self::SingleStep,
+        Self::VideoRecognition,
+    ];
+
+    /// Rank of the task type in the recommended execution order, lower runs earlier
+    ///
+    /// `StartUp` ranks first and `CloseDown` ranks last, and daily tasks follow the order of the
+    /// GUI. Task types of the same rank have no preferred order, so a task list should be sorted
+    /// by a stable sort. This is advisory, MaaCore runs tasks in the order they are appended.
+    pub const fn order_rank(self) -> u8 {
+        match self {
+            Self::StartUp => 0,
+            Self::Recruit => 1,
+            Self::Infrast => 2,
+            Self::Fight | Self::Copilot | Self::SSSCopilot => 3,
+            Self::Mall => 4,
+            Self::Award => 5,
+            Self::Roguelike | Self::Reclamation => 6,
+            Self::Depot | Self::OperBox => 7,
+            Self::Custom | Self::SingleStep | Self::VideoRecognition => 8,
+            Self::CloseDown => 9,
         }
-        variants
-    };
+    }
+
+    /// Human-facing name of the task type in the given language
+    ///
+    /// Fall back to the name returned by `to_str` if there is no translation.
+    pub const fn display_name(self, lang: Lang) -> &'static str {
+        let translated = match lang {
+            Lang::En => None,
+            Lang::ZhCn => Some(match self {
+                Self::StartUp => "开始唤醒",
+                Self::CloseDown => "关闭游戏",
+                Self::Fight => "刷理智",
+                Self::Recruit => "自动公招",
+                Self::Infrast => "基建换班",
+                Self::Mall => "获取信用及购物",
+                Self::Award => "领取奖励",
+                Self::Roguelike => "自动肉鸽",
+                Self::Copilot => "自动战斗",
+                Self::SSSCopilot => "保全派驻",
+                Self::Depot => "仓库识别",
+                Self::OperBox => "干员识别",
+                Self::Reclamation => "生息演算",
+                Self::Custom => "自定义任务",
+                Self::SingleStep => "单步任务",
+                Self::VideoRecognition => "视频识别",
+            }),
+            Lang::Ja => match self {
+                Self::StartUp => Some("ウェイクアップ"),
+                Self::CloseDown => Some("ゲーム終了"),
+                Self::Fight => Some("作戦"),
+                Self::Recruit => Some("公開求人"),
+                Self::Infrast => Some("基地"),
+                Self::Mall => Some("購買部"),
+                Self::Award => Some("報酬受取"),
+                Self::Roguelike => Some("統合戦略"),
+                Self::Copilot => Some("自動戦闘"),
+                Self::SSSCopilot => Some("保全駐在"),
+                Self::Reclamation => Some("生息演算"),
+                _ => None,
+            },
+        };
+        match translated {
+            Some(name) => name,
+            None => self.to_str(),
+        }
+    }
+
+    /// The category of the task type, used to group task types in help text
+    pub const fn category(self) -> TaskCategory {
+        match self {
+            Self::Fight | Self::Copilot | Self::SSSCopilot => TaskCategory::Combat,
+            Self::Infrast => TaskCategory::Base,
+            Self::Mall | Self::Award => TaskCategory::Economy,
+            Self::Recruit | Self::Depot | Self::OperBox => TaskCategory::Progress,
+            Self::Roguelike | Self::Reclamation => TaskCategory::Exploration,
+            Self::StartUp | Self::CloseDown => TaskCategory::System,
+            Self::Custom | Self::SingleStep | Self::VideoRecognition => TaskCategory::Meta,
+        }
+    }
+
+    /// Iterate over all task types in declaration order
+    pub fn iter() -> impl Iterator<Item = Self> {
+        Self::VARIANTS.into_iter()
+    }
 
     pub const fn to_str(self) -> &'static str {
         match self {
@@ -252,6 +590,12 @@ impl TaskType {
         Self::VARIANTS
             .iter()
             .find(|v| v.to_str().eq_ignore_ascii_case(s))
+            .or_else(|| {
+                Self::ALIASES
+                    .iter()
+                    .find(|(alias, _)| alias.eq_ignore_ascii_case(s))
+                    .map(|(_, v)| v)
+            })
             .copied()
     }
 }
@@ -276,6 +620,42 @@ impl std::fmt::Display for UnknownTaskType {
 
 impl std::error::Error for UnknownTaskType {}
 
+impl TryFrom<u8> for TaskType {
+    type Error = UnknownTaskType;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        match v {
+            0 => Ok(Self::StartUp),
+            1 => Ok(Self::CloseDown),
+            2 => Ok(Self::Fight),
+            3 => Ok(Self::Recruit),
+            4 => Ok(Self::Infrast),
+            5 => Ok(Self::Mall),
+            6 => Ok(Self::Award),
+            7 => Ok(Self::Roguelike),
+            8 => Ok(Self::Copilot),
+            9 => Ok(Self::SSSCopilot),
+            10 => Ok(Self::Depot),
+            11 => Ok(Self::OperBox),
+            12 => Ok(Self::Reclamation),
+            13 => Ok(Self::Custom),
+            14 => Ok(Self::SingleStep),
+            15 => Ok(Self::VideoRecognition),
+            _ => Err(UnknownTaskType(v.to_string())),
+        }
+    }
+}
+
+impl TryFrom<u64> for TaskType {
+    type Error = UnknownTaskType;
+
+    fn try_from(v: u64) -> Result<Self, Self::Error> {
+        u8::try_from(v)
+            .map_err(|_| UnknownTaskType(v.to_string()))
+            .and_then(Self::try_from)
+    }
+}
+
 impl std::str::FromStr for TaskType {
     type Err = UnknownTaskType;
 
@@ -303,11 +683,8 @@ impl<'de> serde::Deserialize<'de> for TaskType {
             where
                 E: serde::de::Error,
             {
-                if v < TaskType::COUNT as u64 {
-                    Ok(unsafe { std::mem::transmute::<u8, TaskType>(v as u8) })
-                } else {
-                    Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
-                }
+                TaskType::try_from(v)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
             }
 
             fn visit_str<E>(self, value: &str) -> Result<TaskType, E>
@@ -323,13 +700,19 @@ impl<'de> serde::Deserialize<'de> for TaskType {
     }
 }
 
+/// Serialize as the discriminant, or the name if the feature `serde-string` is enabled.
+///
+/// Both forms can be deserialized regardless of the feature.
 #[cfg(feature = "serde")]
 impl serde::Serialize for TaskType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_u64(*self as u64)
+        #[cfg(feature = "serde-string")]
+        return serializer.serialize_str(self.to_str());
+        #[cfg(not(feature = "serde-string"))]
+        return serializer.serialize_u64(*self as u64);
     }
 }
 
@@ -345,10 +728,57 @@ impl std::fmt::Display for TaskType {
     }
 }
 
+/// Category of task types, see [`TaskType::category`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TaskCategory {
+    Combat,
+    Base,
+    Economy,
+    Progress,
+    Exploration,
+    System,
+    Meta,
+}
+
+impl TaskCategory {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::Combat => "Combat",
+            Self::Base => "Base",
+            Self::Economy => "Economy",
+            Self::Progress => "Progress",
+            Self::Exploration => "Exploration",
+            Self::System => "System",
+            Self::Meta => "Meta",
+        }
+    }
+}
+
+impl std::fmt::Display for TaskCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
     use super::*;
 
+    #[test]
+    fn hash() {
+        let modes: HashSet<_> = TouchMode::iter().chain(TouchMode::iter()).collect();
+        assert_eq!(modes.len(), TouchMode::COUNT);
+
+        let clients: HashSet<_> = ClientType::VARIANTS.into_iter().collect();
+        assert_eq!(clients.len(), ClientType::COUNT);
+
+        let task_types: HashSet<_> = TaskType::iter().collect();
+        assert_eq!(task_types.len(), TaskType::COUNT);
+        assert!(task_types.contains(&TaskType::Fight));
+    }
+
     mod touch_mode {
         use TouchMode::*;
 
@@ -379,7 +809,7 @@ mod tests {
 
         #[cfg(feature = "serde")]
         mod serde {
-            use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+            use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
 
             use super::*;
 
@@ -408,6 +838,16 @@ mod tests {
                 ]);
             }
 
+            #[test]
+            fn serialize() {
+                for mode in TouchMode::iter() {
+                    #[cfg(feature = "serde-string")]
+                    assert_tokens(&mode, &[Token::Str(mode.to_str())]);
+                    #[cfg(not(feature = "serde-string"))]
+                    assert_tokens(&mode, &[Token::U64(mode as u64)]);
+                }
+            }
+
             #[test]
             fn deserialize_error() {
                 assert_de_tokens_error::<TouchMode>(
@@ -423,6 +863,59 @@ mod tests {
             }
         }
 
+        #[test]
+        fn try_from() {
+            for (i, mode) in TouchMode::VARIANTS.into_iter().enumerate() {
+                assert_eq!(TouchMode::try_from(i as u8), Ok(mode));
+                assert_eq!(TouchMode::try_from(i as u64), Ok(mode));
+                assert_eq!(mode as u8, i as u8);
+            }
+            assert_eq!(
+                TouchMode::try_from(4u8),
+                Err(UnknownTouchModeError("4".to_owned()))
+            );
+            assert_eq!(
+                TouchMode::try_from(256u64),
+                Err(UnknownTouchModeError("256".to_owned()))
+            );
+        }
+
+        #[test]
+        fn iter() {
+            assert_eq!(TouchMode::iter().count(), TouchMode::COUNT);
+            assert!(TouchMode::iter().eq(TouchMode::VARIANTS));
+        }
+
+        #[cfg(feature = "semver")]
+        #[test]
+        fn supported_since() {
+            use semver::Version;
+
+            assert_eq!(Adb.supported_since(), Version::new(4, 0, 0));
+            assert_eq!(MaaTouch.supported_since(), Version::new(4, 12, 0));
+
+            let current = Version::parse("5.0.0").unwrap();
+            assert!(TouchMode::iter().all(|mode| mode.is_supported_by(&current)));
+
+            let old = Version::parse("4.11.3").unwrap();
+            assert!(MiniTouch.is_supported_by(&old));
+            assert!(!MaaTouch.is_supported_by(&old));
+            assert!(MaaTouch.is_supported_by(&Version::parse("4.12.0-beta.1").unwrap()));
+            assert!(!MacPlayTools.is_supported_by(&Version::parse("4.12.9").unwrap()));
+        }
+
+        #[test]
+        fn display_name() {
+            for mode in TouchMode::iter() {
+                assert_eq!(mode.display_name(Lang::En), mode.to_str());
+            }
+            assert_eq!(Adb.display_name(Lang::ZhCn), "ADB 输入");
+            assert_eq!(Adb.display_name(Lang::Ja), "ADB 入力");
+            // Fallback
+            assert_eq!(MaaTouch.display_name(Lang::ZhCn), "maatouch");
+            assert_eq!(MacPlayTools.display_name(Lang::Ja), "MacPlayTools");
+        }
+
         #[test]
         fn to_str() {
             assert_eq!(Adb.to_str(), "adb");
@@ -438,6 +931,112 @@ mod tests {
         }
     }
 
+    mod client_type {
+        use ClientType::*;
+
+        use super::*;
+
+        #[test]
+        fn parse() {
+            assert_eq!("Official".parse(), Ok(Official));
+            assert_eq!("official".parse(), Ok(Official));
+            assert_eq!("Bilibili".parse(), Ok(Bilibili));
+            assert_eq!("txwy".parse(), Ok(Txwy));
+            assert_eq!("Txwy".parse(), Ok(Txwy));
+            assert_eq!("YoStarEN".parse(), Ok(YoStarEN));
+            assert_eq!("yostaren".parse(), Ok(YoStarEN));
+            assert_eq!("YoStarJP".parse(), Ok(YoStarJP));
+            assert_eq!("YoStarKR".parse(), Ok(YoStarKR));
+
+            // Aliases
+            assert_eq!("CN".parse(), Ok(Official));
+            assert_eq!("tw".parse(), Ok(Txwy));
+            assert_eq!("Global".parse(), Ok(YoStarEN));
+            assert_eq!("EN".parse(), Ok(YoStarEN));
+            assert_eq!("JP".parse(), Ok(YoStarJP));
+            assert_eq!("kr".parse(), Ok(YoStarKR));
+
+            assert_eq!(
+                "".parse::<ClientType>(),
+                Err(UnknownClientTypeError("".to_owned()))
+            );
+            assert_eq!(
+                "Unknown".parse::<ClientType>(),
+                Err(UnknownClientTypeError("Unknown".to_owned()))
+            );
+            assert_eq!(
+                UnknownClientTypeError("Unknown".to_owned()).to_string(),
+                "unknown client type `Unknown`, expected one of `Official`, `Bilibili`, `txwy`, \
+                `YoStarEN`, `YoStarJP`, `YoStarKR`",
+            );
+        }
+
+        #[cfg(feature = "serde")]
+        mod serde {
+            use serde_test::{
+                assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, assert_tokens, Token,
+            };
+
+            use super::*;
+
+            #[test]
+            fn serde() {
+                for client in ClientType::VARIANTS {
+                    assert_tokens(&client, &[Token::Str(client.to_str())]);
+                }
+
+                assert_de_tokens(&[YoStarEN, Official], &[
+                    Token::Seq { len: Some(2) },
+                    Token::Str("Global"),
+                    Token::Str("CN"),
+                    Token::SeqEnd,
+                ]);
+                assert_ser_tokens(&Txwy, &[Token::Str("txwy")]);
+            }
+
+            #[test]
+            fn deserialize_error() {
+                assert_de_tokens_error::<ClientType>(
+                    &[Token::Str("Unknown")],
+                    "unknown variant `Unknown`, expected one of `Official`, `Bilibili`, `txwy`, \
+                    `YoStarEN`, `YoStarJP`, `YoStarKR`",
+                );
+
+                assert_de_tokens_error::<ClientType>(
+                    &[Token::U64(0)],
+                    "invalid type: integer `0`, expected a valid client type",
+                );
+            }
+        }
+
+        #[test]
+        fn resource_suffix() {
+            assert_eq!(Official.resource_suffix(), None);
+            assert_eq!(Bilibili.resource_suffix(), None);
+            assert_eq!(Txwy.resource_suffix(), Some("txwy"));
+            assert_eq!(YoStarEN.resource_suffix(), Some("YoStarEN"));
+            assert_eq!(YoStarJP.resource_suffix(), Some("YoStarJP"));
+            assert_eq!(YoStarKR.resource_suffix(), Some("YoStarKR"));
+        }
+
+        #[test]
+        fn to_str() {
+            assert_eq!(Official.to_str(), "Official");
+            assert_eq!(Bilibili.to_str(), "Bilibili");
+            assert_eq!(Txwy.to_str(), "txwy");
+            assert_eq!(YoStarEN.to_str(), "YoStarEN");
+            assert_eq!(YoStarJP.to_str(), "YoStarJP");
+            assert_eq!(YoStarKR.to_str(), "YoStarKR");
+            assert_eq!(ClientType::default(), Official);
+        }
+
+        #[test]
+        fn fmt() {
+            assert_eq!(format!("{}", YoStarEN), "YoStarEN");
+            assert_eq!(format!("{:?}", Txwy), "txwy");
+        }
+    }
+
     mod task_type {
         use TaskType::*;
 
@@ -461,6 +1060,18 @@ mod tests {
             assert_eq!("Custom".parse(), Ok(Custom));
             assert_eq!("SingleStep".parse(), Ok(SingleStep));
             assert_eq!("VideoRecognition".parse(), Ok(VideoRecognition));
+
+            // Aliases
+            assert_eq!("combat".parse(), Ok(Fight));
+            assert_eq!("Infra".parse(), Ok(Infrast));
+            assert_eq!("mission".parse(), Ok(Award));
+            assert_eq!("credit-store".parse(), Ok(Mall));
+            assert_eq!("SSS".parse(), Ok(SSSCopilot));
+            assert_eq!(
+                "credit".parse::<TaskType>(),
+                Err(UnknownTaskType("credit".to_owned()))
+            );
+
             assert_eq!(
                 "Unknown".parse::<TaskType>(),
                 Err(UnknownTaskType("Unknown".to_owned()))
@@ -475,7 +1086,7 @@ mod tests {
 
         #[cfg(feature = "serde")]
         mod serde {
-            use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+            use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
 
             use super::*;
 
@@ -498,6 +1109,16 @@ mod tests {
                 ]);
             }
 
+            #[test]
+            fn serialize() {
+                for task_type in TaskType::iter() {
+                    #[cfg(feature = "serde-string")]
+                    assert_tokens(&task_type, &[Token::Str(task_type.to_str())]);
+                    #[cfg(not(feature = "serde-string"))]
+                    assert_tokens(&task_type, &[Token::U64(task_type as u64)]);
+                }
+            }
+
             #[test]
             fn deserialize_error() {
                 assert_de_tokens_error::<TaskType>(
@@ -514,6 +1135,90 @@ mod tests {
             }
         }
 
+        #[test]
+        fn try_from() {
+            for (i, task_type) in TaskType::VARIANTS.into_iter().enumerate() {
+                assert_eq!(TaskType::try_from(i as u8), Ok(task_type));
+                assert_eq!(TaskType::try_from(i as u64), Ok(task_type));
+                assert_eq!(task_type as u8, i as u8);
+            }
+            assert_eq!(
+                TaskType::try_from(16u8),
+                Err(UnknownTaskType("16".to_owned()))
+            );
+            assert_eq!(
+                TaskType::try_from(u64::MAX),
+                Err(UnknownTaskType(u64::MAX.to_string()))
+            );
+        }
+
+        #[test]
+        fn iter() {
+            assert_eq!(TaskType::iter().count(), TaskType::COUNT);
+            assert!(TaskType::iter().eq(TaskType::VARIANTS));
+        }
+
+        #[test]
+        fn category() {
+            use TaskCategory::*;
+
+            let categories = TaskType::iter()
+                .map(|task_type| (task_type, task_type.category()))
+                .collect::<Vec<_>>();
+            assert_eq!(categories, [
+                (StartUp, System),
+                (CloseDown, System),
+                (Fight, Combat),
+                (Recruit, Progress),
+                (Infrast, Base),
+                (Mall, Economy),
+                (Award, Economy),
+                (Roguelike, Exploration),
+                (Copilot, Combat),
+                (SSSCopilot, Combat),
+                (Depot, Progress),
+                (OperBox, Progress),
+                (Reclamation, Exploration),
+                (Custom, Meta),
+                (SingleStep, Meta),
+                (VideoRecognition, Meta),
+            ]);
+
+            assert_eq!(Combat.to_string(), "Combat");
+            assert_eq!(Exploration.to_string(), "Exploration");
+        }
+
+        #[test]
+        fn order_rank() {
+            assert!(StartUp.order_rank() < Fight.order_rank());
+            assert!(TaskType::iter()
+                .filter(|&t| t != StartUp)
+                .all(|t| t.order_rank() > StartUp.order_rank()));
+            assert!(TaskType::iter()
+                .filter(|&t| t != CloseDown)
+                .all(|t| t.order_rank() < CloseDown.order_rank()));
+
+            // Stable sort keeps the order of task types of the same rank
+            let mut tasks = [CloseDown, Award, Copilot, Fight, StartUp, Mall];
+            tasks.sort_by_key(|t| t.order_rank());
+            assert_eq!(tasks, [StartUp, Copilot, Fight, Mall, Award, CloseDown]);
+        }
+
+        #[test]
+        fn display_name() {
+            for task_type in TaskType::iter() {
+                assert_eq!(task_type.display_name(Lang::En), task_type.to_str());
+                assert_eq!(task_type.to_str(), task_type.to_string());
+            }
+            assert_eq!(Fight.display_name(Lang::ZhCn), "刷理智");
+            assert_eq!(Infrast.display_name(Lang::ZhCn), "基建换班");
+            assert_eq!(Fight.display_name(Lang::Ja), "作戦");
+            assert_eq!(Roguelike.display_name(Lang::Ja), "統合戦略");
+            // Fallback
+            assert_eq!(Depot.display_name(Lang::Ja), "Depot");
+            assert_eq!(VideoRecognition.display_name(Lang::Ja), "VideoRecognition");
+        }
+
         #[test]
         fn to_str() {
             assert_eq!(StartUp.to_str(), "StartUp");
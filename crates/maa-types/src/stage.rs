@@ -0,0 +1,203 @@
+/// A stage code of the game, e.g. `1-7`, `CE-6` or `PR-A-1`
+///
+/// A stage code is made of segments of ASCII letters and digits joined by `-`, and the last
+/// segment is the number of the stage. Letters are converted to upper case when parsing.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Stage(String);
+
+impl Stage {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The category of the stage, inferred from the prefix of the code
+    pub fn category(&self) -> StageCategory {
+        let (prefix, _) = self.0.rsplit_once('-').unwrap_or((&self.0, ""));
+        match prefix {
+            "CE" => StageCategory::Lmd,
+            "LS" => StageCategory::Exp,
+            "CA" => StageCategory::SkillSummary,
+            "AP" => StageCategory::PurchaseCertificate,
+            "SK" => StageCategory::Carbon,
+            "PR-A" | "PR-B" | "PR-C" | "PR-D" => StageCategory::Chip,
+            _ if is_main(prefix) => StageCategory::Main,
+            _ => StageCategory::Other,
+        }
+    }
+}
+
+/// Main story stages are `<episode>-<n>`, and their hard versions are `H<episode>-<n>`
+fn is_main(prefix: &str) -> bool {
+    let episode = prefix.strip_prefix('H').unwrap_or(prefix);
+    !episode.is_empty() && episode.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl std::str::FromStr for Stage {
+    type Err = InvalidStageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = s.split('-');
+        let number = segments.next_back().unwrap_or_default();
+        let valid = s.contains('-')
+            && !number.is_empty()
+            && number.bytes().all(|b| b.is_ascii_digit())
+            && segments
+                .all(|seg| !seg.is_empty() && seg.bytes().all(|b| b.is_ascii_alphanumeric()));
+
+        if valid {
+            Ok(Stage(s.to_ascii_uppercase()))
+        } else {
+            Err(InvalidStageError(s.to_owned()))
+        }
+    }
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug)]
+pub struct InvalidStageError(String);
+
+impl std::fmt::Display for InvalidStageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid stage `{}`, expected a code like `1-7`, `CE-6` or `PR-A-1`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for InvalidStageError {}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Stage {
+    fn deserialize<D>(deserializer: D) -> Result<Stage, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct StageVisitor;
+
+        impl serde::de::Visitor<'_> for StageVisitor {
+            type Value = Stage;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a valid stage code")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Stage, E>
+            where
+                E: serde::de::Error,
+            {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(StageVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Stage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Category of stages, see [`Stage::category`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StageCategory {
+    /// Main story stages, e.g. `1-7` and `H12-4`
+    Main,
+    /// LMD (CE) stages
+    Lmd,
+    /// Battle record (LS) stages
+    Exp,
+    /// Skill summary (CA) stages
+    SkillSummary,
+    /// Purchase certificate (AP) stages
+    PurchaseCertificate,
+    /// Carbon (SK) stages
+    Carbon,
+    /// Chip (PR) stages
+    Chip,
+    /// Other stages, e.g. event stages
+    Other,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(s: &str) -> Stage {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(stage("1-7").as_str(), "1-7");
+        assert_eq!(stage("CE-6").as_str(), "CE-6");
+        assert_eq!(stage("ce-6").as_str(), "CE-6");
+        assert_eq!(stage("PR-A-1").as_str(), "PR-A-1");
+        assert_eq!(stage("H12-4").as_str(), "H12-4");
+        assert_eq!(stage("SN-10").to_string(), "SN-10");
+
+        for invalid in [
+            "", "1-7 ", "CE", "CE-", "-6", "CE--6", "CE-A", "CE_6", "1-7;rm",
+        ] {
+            assert_eq!(
+                invalid.parse::<Stage>(),
+                Err(InvalidStageError(invalid.to_owned()))
+            );
+        }
+        assert_eq!(
+            InvalidStageError("CE".to_owned()).to_string(),
+            "invalid stage `CE`, expected a code like `1-7`, `CE-6` or `PR-A-1`"
+        );
+    }
+
+    #[test]
+    fn category() {
+        assert_eq!(stage("1-7").category(), StageCategory::Main);
+        assert_eq!(stage("H12-4").category(), StageCategory::Main);
+        assert_eq!(stage("CE-6").category(), StageCategory::Lmd);
+        assert_eq!(stage("LS-6").category(), StageCategory::Exp);
+        assert_eq!(stage("CA-5").category(), StageCategory::SkillSummary);
+        assert_eq!(stage("AP-5").category(), StageCategory::PurchaseCertificate);
+        assert_eq!(stage("SK-5").category(), StageCategory::Carbon);
+        assert_eq!(stage("PR-A-1").category(), StageCategory::Chip);
+        assert_eq!(stage("pr-d-2").category(), StageCategory::Chip);
+        assert_eq!(stage("SN-10").category(), StageCategory::Other);
+        assert_eq!(stage("H-1").category(), StageCategory::Other);
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+        use super::*;
+
+        #[test]
+        fn serde() {
+            assert_tokens(&stage("CE-6"), &[Token::Str("CE-6")]);
+
+            assert_de_tokens_error::<Stage>(
+                &[Token::Str("CE")],
+                "invalid stage `CE`, expected a code like `1-7`, `CE-6` or `PR-A-1`",
+            );
+        }
+    }
+}